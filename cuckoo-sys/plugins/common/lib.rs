@@ -21,6 +21,17 @@
 //! Descriptions of what the corresponding C function should look like follow
 //! in each function.
 //!
+//! #Versioning
+//!
+//! Functions described as optional are extensions to the interface, which
+//! cuckoo-miner detects by whether the plugin exports them, either leaving the
+//! corresponding capability unset or using a default (e.g. a proof size of 42)
+//! when they're missing. Adding one doesn't change the interface version,
+//! so the version reported by `cuckoo_abi_version` says nothing about which
+//! extensions a plugin implements. It's reserved for breaking changes to the
+//! required functions, i.e. changes which plugins built against an earlier
+//! version can't be called safely with.
+//!
 
 #![allow(dead_code, non_camel_case_types, non_upper_case_globals, non_snake_case)]
 
@@ -29,6 +40,56 @@ use libc::*;
 
 extern "C" {
 
+    /// #Description 
+    ///
+    /// Reports which version of this plugin interface the plugin implements.
+    /// This is the first function called after the plugin is loaded, and
+    /// cuckoo-miner will refuse to call any other function in a plugin reporting
+    /// a version it doesn't support. Plugins which don't export this function
+    /// are assumed to implement version 1, the interface as described here.
+    /// Optional functions are detected separately, see Versioning above.
+    ///
+    /// #Arguments
+    ///
+    /// * None
+    ///
+    /// #Returns
+    ///
+    /// * The interface version implemented by the plugin, currently 1
+    ///
+    /// #Corresponding C (Unix)
+    /// 
+    /// ```
+    ///  extern "C" int cuckoo_abi_version();
+    /// ```
+
+    pub fn cuckoo_abi_version() -> uint32_t;
+
+    /// #Description 
+    ///
+    /// Reports the number of nonces in each solution the plugin finds, i.e. the
+    /// length of the cycles it searches for. This is optional, and plugins which
+    /// don't export it are assumed to find conventional cycles of length 42. The
+    /// size must be even and no more than 1024. This is called once when the plugin is
+    /// loaded, before `cuckoo_init`, and cuckoo-miner sizes every `sol_nonces` buffer
+    /// it passes to the plugin accordingly.
+    ///
+    /// #Arguments
+    ///
+    /// * None
+    ///
+    /// #Returns
+    ///
+    /// * The number of nonces in the plugin's solutions
+    ///
+    /// #Corresponding C (Unix)
+    /// 
+    /// ```
+    ///  extern "C" int cuckoo_proof_size();
+    /// ```
+
+    pub fn cuckoo_proof_size() -> uint32_t;
+
     /// #Description 
     ///
     /// Call to the call_cuckoo function in the library, which calls a base
@@ -42,14 +103,14 @@ extern "C" {
     ///
     /// * `header_len` (IN) The length of the header, in bytes.
     ///
-    /// * `sol_nonces` (OUT) A caller-allocated array of 42 unsigned bytes. This currently must
-    ///    be of size 42, corresponding to a conventional cuckoo-cycle solution length. 
+    /// * `sol_nonces` (OUT) A caller-allocated array of u32s, with room for as many nonces
+    ///    as the plugin reports via `cuckoo_proof_size` (42 if it doesn't export it).
     ///    If a solution is found, the solution nonces will be stored in this array, otherwise,
     ///    they will be left untouched.
     ///
     /// #Returns
     ///
-    /// 1 if a solution is found, with the 42 solution nonces contained within
+    /// 1 if a solution is found, with the solution nonces contained within
     /// `sol_nonces`. Returns 0 if no solution is found and `sol_nonces` remains
     /// untouched.
     ///
@@ -86,6 +147,47 @@ extern "C" {
                        header_len: size_t,
                        sol_nonces: *mut uint32_t) -> uint32_t;
 
+    /// #Description 
+    ///
+    /// As `cuckoo_call`, but returns every cycle found in the graph rather than
+    /// just the first, as a single graph can contain several. This function is
+    /// optional, and plugins which don't export it are only asked for one cycle.
+    ///
+    /// #Arguments
+    ///
+    /// * `header` (IN) As for `cuckoo_call`.
+    ///
+    /// * `header_len` (IN) The length of the header, in bytes.
+    ///
+    /// * `sol_nonces` (OUT) A caller-allocated array of u32s, with room for
+    ///    `max_solutions` solutions of the plugin's proof size. The nonces of each
+    ///    cycle found are stored one after another, the first cycle's nonces at
+    ///    the start of the array.
+    ///
+    /// * `max_solutions` (IN) The most cycles there is room for in `sol_nonces`.
+    ///    Any further cycles should be dropped.
+    ///
+    /// #Returns
+    ///
+    /// The number of cycles stored in `sol_nonces`, 0 if none were found.
+    ///
+    /// #Corresponding C (Unix)
+    /// 
+    /// ```
+    ///  extern "C" int cuckoo_call_all(char* header_data, 
+    ///                                int header_length,
+    ///                                uint_32* sol_nonces,
+    ///                                int max_solutions);
+    /// ```
+    /// All memory should be allocated on the caller side. The implementing plugin 
+    /// should not allocate any memory to be returned to the caller.
+    ///
+
+    pub fn cuckoo_call_all(header: *const c_uchar, 
+                           header_len: uint32_t,
+                           sol_nonces: *mut uint32_t,
+                           max_solutions: uint32_t) -> uint32_t;
+
     /// #Description 
     ///
     /// Queries a plugin to determine its description, name, and capabilities. This should
@@ -112,7 +214,9 @@ extern "C" {
     ///
     /// #Returns
     ///
-    /// The function has no return value, but uses the OUT values described above.
+    /// The function has no return value, but uses the OUT values described above. If
+    /// the name or description is too long, the plugin should fill the whole buffer,
+    /// in which case the caller will retry with larger buffers.
     ///
     /// #Corresponding C (Unix)
     /// 
@@ -257,31 +361,119 @@ extern "C" {
                                 name_len: uint32_t,
                                 value: *mut uint32_t) -> uint32_t;
 
+    /// #Description 
+    ///
+    /// Sets a parameter to a typed value, for parameters which aren't simple
+    /// integers. This is optional, but plugins exporting it must also export
+    /// `cuckoo_get_parameter_value`. cuckoo-miner checks values against the
+    /// type and range given in `cuckoo_parameter_list` before passing them on,
+    /// but the plugin should still check them itself.
+    ///
+    /// #Arguments
+    ///
+    /// * `name` (IN) The name of the parameter to set.
+    ///
+    /// * `name_len` (IN) The length, in bytes, of the `name` argument.
+    ///
+    /// * `value` (IN) The value as JSON, depending on the parameter's type: a number
+    /// for `int`, `true` or `false` for `bool`, a string for `enum` and `string`, or an
+    /// array of numbers, one per device, for `per_device`, e.g. `[4, 8]`.
+    ///
+    /// * `value_len` (IN) The length, in bytes, of the `value` argument.
+    ///
+    /// #Returns
+    ///
+    /// 0 if the parameter was set
+    /// 1 if the parameter does not exist
+    /// 2 if the value isn't valid for the parameter
+    ///
+    /// #Corresponding C (Unix)
+    /// 
+    /// ```
+    ///  extern "C" int cuckoo_set_parameter_value(char *name,
+    ///                                            int name_len,
+    ///                                            char *value,
+    ///                                            int value_len);
+    /// ```
+    ///
+
+    pub fn cuckoo_set_parameter_value(name: *const c_uchar, 
+                                      name_len: uint32_t,
+                                      value: *const c_uchar,
+                                      value_len: uint32_t) -> uint32_t;
+
+    /// #Description 
+    ///
+    /// Retrieves the typed value of a parameter, as JSON in the same form
+    /// as given to `cuckoo_set_parameter_value`.
+    ///
+    /// #Arguments
+    ///
+    /// * `name` (IN) The name of the parameter to retrieve.
+    ///
+    /// * `name_len` (IN) The length, in bytes, of the `name` argument.
+    ///
+    /// * `value_out_buf` (OUT) A buffer in which to store the value.
+    ///
+    /// * `value_len` (IN-OUT) Coming in, the maximum number of bytes to write to
+    /// `value_out_buf`, coming out, the number of bytes written to `value_out_buf`
+    ///
+    /// #Returns
+    ///
+    /// 0 if the parameter was retrieved
+    /// 1 if the parameter does not exist
+    /// 3 if there was not enough space in the buffer to write the value
+    ///
+    /// #Corresponding C (Unix)
+    /// 
+    /// ```
+    ///  extern "C" int cuckoo_get_parameter_value(char *name,
+    ///                                            int name_len,
+    ///                                            char *value_out_buf,
+    ///                                            int *value_len);
+    /// ```
+    ///
+
+    pub fn cuckoo_get_parameter_value(name: *const c_uchar, 
+                                      name_len: uint32_t,
+                                      value_out_buf: *mut c_uchar,
+                                      value_len: *mut uint32_t) -> uint32_t;
+
     /// #Description 
     ///
     /// Retrieves a JSON list of the plugin's available parameters, their
     /// description and their defaults. e.g:
     /// ```
     ///   [{
-    ///      name:"num_threads",
-    ///      type:"int",
-    ///      description: "Number of worker threads",
-    ///      default_value: "1",
-    ///      min_value: "1",
-    ///      max_value: "32"
+    ///      "name": "num_threads",
+    ///      "type": "int",
+    ///      "description": "Number of worker threads",
+    ///      "default_value": 1,
+    ///      "min_value": 1,
+    ///      "max_value": 32
+    ///    },
+    ///    {
+    ///      "name": "trim_strategy",
+    ///      "type": "enum",
+    ///      "description": "Trimming strategy",
+    ///      "default_value": "lean",
+    ///      "values": ["lean", "mean"]
     ///    },
     ///    {
-    ///      name:"num_trims",
-    ///      type:"int",
-    ///      description: "Maximum number of trimming rounds",
-    ///      default_value: "7",
-    ///      min_value: "0",
-    ///      max_value: "50"
+    ///      "name": "device_threads",
+    ///      "type": "per_device",
+    ///      "description": "Number of threads on each device",
+    ///      "default_value": [1],
+    ///      "min_value": 1,
+    ///      "max_value": 1024
     ///    }]
     ///
     /// ```
-    /// This should correspond to an easily deserialised structure on the 
-    /// rust side.
+    /// `type` is one of `int` (the default), `bool`, `enum`, `string` or `per_device`,
+    /// and `default_value` is given as for `cuckoo_set_parameter_value`. `min_value`
+    /// and `max_value` bound `int` and `per_device` values, and `values` lists the
+    /// allowed values of an `enum`. Only `int` and `bool` parameters (as 0 or 1) can
+    /// be set via `cuckoo_set_parameter`.
     ///
     /// #Arguments
     ///
@@ -315,6 +507,47 @@ extern "C" {
     pub fn cuckoo_parameter_list(params_out_buf: *mut c_uchar, 
                                 params_len: *mut size_t) -> uint32_t;
 
+    /// #Description 
+    ///
+    /// Retrieves a JSON object describing the algorithm the plugin implements,
+    /// so callers don't have to work it out from the plugin's file name. This is
+    /// optional, and every field in the object is optional too. e.g:
+    /// ```
+    ///   {
+    ///      "variation": "tomato",
+    ///      "edge_bits": 27,
+    ///      "easiness": 50
+    ///   }
+    /// ```
+    /// where `variation` is the cuckoo algorithm variation (simple, edgetrim,
+    /// tomato or mean), `edge_bits` is log2 of the number of edges in the graph
+    /// (one less than the cuckoo size in the plugin's name) and `easiness` is the
+    /// percentage of the graph's nodes used as edges. The proof size is reported
+    /// separately, by `cuckoo_proof_size`.
+    ///
+    /// #Arguments
+    ///
+    /// * `metadata_out_buf` (OUT) A buffer in which to store the JSON object.
+    ///
+    /// * `metadata_len` (IN-OUT) Coming in, the maximum number of bytes to write to
+    /// `metadata_out_buf`, coming out, the number of bytes written to `metadata_out_buf`
+    ///
+    /// #Returns
+    ///
+    /// 0 if the metadata was successfully retrieved, 
+    /// 3 if there was not enough space in the buffer to write it
+    ///
+    /// #Corresponding C (Unix)
+    /// 
+    /// ```
+    ///  extern "C" int cuckoo_metadata(char *metadata_out_buf,
+    ///                                 int* metadata_len);
+    /// ```
+    ///
+
+    pub fn cuckoo_metadata(metadata_out_buf: *mut c_uchar, 
+                           metadata_len: *mut uint32_t) -> uint32_t;
+
     
     /// #Description 
     ///
@@ -395,11 +628,13 @@ extern "C" {
     ///
     /// Reads and pops the next solution from the output queue, if one exists. 
     /// Does not block, and intended to be called continually as part of a mining loop. 
+    /// Where the graph for a hash contains several cycles, each should be put in the
+    /// output queue as a separate solution, all with the same nonce.
     ///
     /// #Arguments
     ///
-    /// * `sol_nonces` (OUT) A block of 42 u32s in which the solution nonces will be stored,
-    ///    if any exist.
+    /// * `sol_nonces` (OUT) A block of u32s, sized according to `cuckoo_proof_size`, in which
+    ///    the solution nonces will be stored, if any exist.
     ///
     /// * `nonce` (OUT) A block of 8 u8s representing a Big-Endian u64, used for identification
     ///   purposes so the caller can reconstruct the header used to generate the solution
@@ -504,5 +739,30 @@ extern "C" {
     /// ```
 
     pub fn cuckoo_hashes_since_last_call() -> uint32_t;
+
+    /// #Description 
+    ///
+    /// Asks a `cuckoo_call` in progress to give up, so that a caller which no
+    /// longer needs the result (e.g. because a new block has arrived) doesn't
+    /// have to wait for the whole graph to be searched. The abandoned
+    /// `cuckoo_call` should return 0 as soon as it can. This is called from a
+    /// different thread than the `cuckoo_call`, must not block, and should do
+    /// nothing if no call is in progress. This function is optional.
+    ///
+    /// #Arguments
+    ///
+    /// * None
+    ///
+    /// #Returns
+    ///
+    /// * Nothing
+    ///
+    /// #Corresponding C (Unix)
+    /// 
+    /// ```
+    ///  extern "C" void cuckoo_abort();
+    /// ```
+
+    pub fn cuckoo_abort();
     
 }
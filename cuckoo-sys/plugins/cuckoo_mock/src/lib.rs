@@ -0,0 +1,498 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A mock cuckoo-miner plugin for testing, which doesn't do any mining. Instead
+//! its behaviour is scripted via the `CUCKOO_MOCK_CONFIG` environment variable,
+//! which contains either a JSON config or the path to a file containing one.
+//! The config is re-read each time `cuckoo_init` is called, i.e. whenever the
+//! plugin is loaded. All fields are optional, e.g:
+//!
+//! ```
+//!   {
+//!      "name": "mock",
+//!      "proof_size": 42,
+//!      "solutions": {"<hex encoded hash>": [proof_size solution nonces]},
+//!      "extra_solutions": [[proof_size solution nonces], ...],
+//!      "default_solution": [proof_size solution nonces],
+//!      "parameters": [{"name":"NUM_THREADS", "description":"Threads",
+//!                      "default_value":1, "min_value":1, "max_value":32},
+//!                     {"name":"MODE", "type":"enum", "description":"Mode",
+//!                      "default_value":"lean", "values":["lean", "mean"]}],
+//!      "metadata": {"variation": "simple", "edge_bits": 15, "easiness": 50},
+//!      "set_parameter_results": {"NUM_THREADS": 2},
+//!      "call_delay_ms": 1000,
+//!      "stop_processing_delay_ms": 500,
+//!      "stop_processing_stalls": false,
+//!      "print_on_call": "Progress written to stdout",
+//!      "crash_on_call": false
+//!   }
+//! ```
+//!
+//! Hashes given to `cuckoo_call` or pushed to the input queue which are in
+//! `solutions` produce the given solution, any others produce `default_solution`
+//! if set, otherwise no solution. Any hash producing a solution also produces
+//! `extra_solutions`, as further cycles in the same graph. These are returned
+//! by `cuckoo_call_all` and put in the output queue after the first, but never
+//! returned by `cuckoo_call`. Queued hashes are processed whenever the
+//! output queue is read. `cuckoo_call` and `cuckoo_call_all` take `call_delay_ms`
+//! to return, unless `cuckoo_abort` is called first, in which case they return
+//! no solution. They first write `print_on_call` straight to stdout, as
+//! plugins printing progress do, and abort the process if `crash_on_call` is set.
+//!
+
+#![allow(non_camel_case_types)]
+
+extern crate libc;
+#[macro_use]
+extern crate lazy_static;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
+
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{ptr, slice};
+
+use libc::*;
+
+/// Environment variable holding the mock's config, or a path to it
+const CONFIG_ENV_VAR: &str = "CUCKOO_MOCK_CONFIG";
+
+const ABI_VERSION: uint32_t = 1;
+
+const DEFAULT_PROOF_SIZE: uint32_t = 42;
+
+/// How many hashes may wait in the input queue
+const INPUT_QUEUE_LIMIT: usize = 16;
+
+/// The mock's scripted behaviour
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct MockConfig {
+    /// Name reported by cuckoo_description
+    name: Option<String>,
+
+    /// Proof size reported by cuckoo_proof_size
+    proof_size: Option<u32>,
+
+    /// Solutions to return for particular hex encoded hashes
+    solutions: HashMap<String, Vec<u32>>,
+
+    /// Solution to return for any hash not in `solutions`
+    default_solution: Option<Vec<u32>>,
+
+    /// Further solutions found alongside every solution
+    extra_solutions: Vec<Vec<u32>>,
+
+    /// Parameter list returned by cuckoo_parameter_list
+    parameters: Vec<serde_json::Value>,
+
+    /// Object returned by cuckoo_metadata
+    metadata: serde_json::Value,
+
+    /// Return codes for cuckoo_set_parameter and cuckoo_set_parameter_value,
+    /// by parameter name
+    set_parameter_results: HashMap<String, u32>,
+
+    /// Time to wait before returning from cuckoo_call
+    call_delay_ms: u64,
+
+    /// Time to wait before returning from cuckoo_stop_processing
+    stop_processing_delay_ms: u64,
+
+    /// Whether cuckoo_stop_processing should never return
+    stop_processing_stalls: bool,
+
+    /// Text written to stdout by cuckoo_call
+    print_on_call: Option<String>,
+
+    /// Whether cuckoo_call should abort the process
+    crash_on_call: bool,
+}
+
+type InputQueue = VecDeque<(Vec<u8>, [u8; 8])>;
+type OutputQueue = VecDeque<(Vec<u32>, [u8; 8])>;
+
+lazy_static!{
+    static ref CONFIG: RwLock<MockConfig> = RwLock::new(MockConfig::default());
+    static ref PARAMETERS: Mutex<HashMap<String, serde_json::Value>> = Mutex::new(HashMap::new());
+    static ref IS_RUNNING: AtomicBool = AtomicBool::new(false);
+    static ref ABORTED: AtomicBool = AtomicBool::new(false);
+    static ref HASH_COUNT: AtomicUsize = AtomicUsize::new(0);
+    static ref INPUT_QUEUE: Mutex<InputQueue> = Mutex::new(VecDeque::new());
+    static ref OUTPUT_QUEUE: Mutex<OutputQueue> = Mutex::new(VecDeque::new());
+}
+
+// Reads the config from the environment, falling back to the default
+// (no solutions, no errors) if it's missing or invalid
+
+fn read_config() -> MockConfig {
+    let value = match env::var(CONFIG_ENV_VAR) {
+        Ok(v) => v,
+        Err(_) => return MockConfig::default(),
+    };
+    let json = if value.trim_start().starts_with('{') {
+        value
+    } else {
+        let mut contents = String::new();
+        match File::open(&value).and_then(|mut f| f.read_to_string(&mut contents)) {
+            Ok(_) => contents,
+            Err(e) => {
+                eprintln!("cuckoo_mock: unable to read config {}: {}", value, e);
+                return MockConfig::default();
+            }
+        }
+    };
+    match serde_json::from_str(&json) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("cuckoo_mock: invalid config: {}", e);
+            MockConfig::default()
+        }
+    }
+}
+
+// Returns the scripted solutions for a hash, if any, each padded or
+// truncated to the proof size
+
+fn solutions_for(hash: &[u8]) -> Vec<Vec<u32>> {
+    let config = CONFIG.read().unwrap();
+    let hex: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+    let mut solutions = match config.solutions.get(&hex).or(config.default_solution.as_ref()) {
+        Some(n) => vec![n.clone()],
+        None => return Vec::new(),
+    };
+    solutions.extend(config.extra_solutions.iter().cloned());
+    for s in solutions.iter_mut() {
+        s.resize(config.proof_size.unwrap_or(DEFAULT_PROOF_SIZE) as usize, 0);
+    }
+    solutions
+}
+
+// Prints and crashes as configured, then waits for the configured call
+// delay, returning false if cuckoo_abort was called in the meantime
+
+fn wait_for_call() -> bool {
+    {
+        let config = CONFIG.read().unwrap();
+        if let Some(ref text) = config.print_on_call {
+            unsafe {
+                write(STDOUT_FILENO, text.as_ptr() as *const c_void, text.len());
+            }
+        }
+        if config.crash_on_call {
+            unsafe {
+                abort();
+            }
+        }
+    }
+    HASH_COUNT.fetch_add(1, Ordering::SeqCst);
+    ABORTED.store(false, Ordering::SeqCst);
+    let delay = Duration::from_millis(CONFIG.read().unwrap().call_delay_ms);
+    let start = Instant::now();
+    while start.elapsed() < delay {
+        if ABORTED.load(Ordering::SeqCst) {
+            return false;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    true
+}
+
+unsafe fn write_buffer(src: &[u8], buf: *mut c_uchar, buf_len: *mut uint32_t) -> bool {
+    let fits = src.len() <= *buf_len as usize;
+    let len = if fits { src.len() } else { *buf_len as usize };
+    ptr::copy_nonoverlapping(src.as_ptr(), buf, len);
+    *buf_len = len as uint32_t;
+    fits
+}
+
+/// Reports the plugin interface version implemented by this plugin
+
+#[no_mangle]
+pub extern "C" fn cuckoo_abi_version() -> uint32_t {
+    ABI_VERSION
+}
+
+/// Reports the configured proof size. This is called before cuckoo_init,
+/// so reads the config itself
+
+#[no_mangle]
+pub extern "C" fn cuckoo_proof_size() -> uint32_t {
+    read_config().proof_size.unwrap_or(DEFAULT_PROOF_SIZE)
+}
+
+/// (Re)reads the mock's config from the environment
+
+#[no_mangle]
+pub extern "C" fn cuckoo_init() {
+    *CONFIG.write().unwrap() = read_config();
+    PARAMETERS.lock().unwrap().clear();
+}
+
+/// Returns the scripted solution for the given header, if any
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_call(header: *const c_uchar,
+                                     header_len: uint32_t,
+                                     sol_nonces: *mut uint32_t) -> uint32_t {
+    let header = slice::from_raw_parts(header, header_len as usize);
+    if !wait_for_call() {
+        return 0;
+    }
+    match solutions_for(header).first() {
+        Some(s) => {
+            ptr::copy_nonoverlapping(s.as_ptr(), sol_nonces, s.len());
+            1
+        },
+        None => 0,
+    }
+}
+
+/// Returns all of the scripted solutions for the given header, up to
+/// `max_solutions`
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_call_all(header: *const c_uchar,
+                                         header_len: uint32_t,
+                                         sol_nonces: *mut uint32_t,
+                                         max_solutions: uint32_t) -> uint32_t {
+    let header = slice::from_raw_parts(header, header_len as usize);
+    if !wait_for_call() {
+        return 0;
+    }
+    let solutions = solutions_for(header);
+    let mut count = 0;
+    for s in solutions.iter().take(max_solutions as usize) {
+        ptr::copy_nonoverlapping(s.as_ptr(), sol_nonces.add(count * s.len()), s.len());
+        count += 1;
+    }
+    count as uint32_t
+}
+
+/// Makes a cuckoo_call in progress return early
+
+#[no_mangle]
+pub extern "C" fn cuckoo_abort() {
+    ABORTED.store(true, Ordering::SeqCst);
+}
+
+/// Writes the configured name and a fixed description
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_description(name_buf: *mut c_uchar,
+                                            name_buf_len: *mut uint32_t,
+                                            description_buf: *mut c_uchar,
+                                            description_buf_len: *mut uint32_t) {
+    let name = CONFIG.read().unwrap().name.clone().unwrap_or(String::from("mock"));
+    write_buffer(name.as_bytes(), name_buf, name_buf_len);
+    write_buffer(b"Scriptable mock plugin for testing", description_buf, description_buf_len);
+}
+
+/// Writes the configured parameter list
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_parameter_list(params_out_buf: *mut c_uchar,
+                                               params_len: *mut uint32_t) -> uint32_t {
+    let json = serde_json::to_string(&CONFIG.read().unwrap().parameters).unwrap();
+    if write_buffer(json.as_bytes(), params_out_buf, params_len) {
+        0
+    } else {
+        3
+    }
+}
+
+/// Writes the configured metadata, or an empty object
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_metadata(metadata_out_buf: *mut c_uchar,
+                                         metadata_len: *mut uint32_t) -> uint32_t {
+    let json = match CONFIG.read().unwrap().metadata {
+        serde_json::Value::Null => String::from("{}"),
+        ref m => serde_json::to_string(m).unwrap(),
+    };
+    if write_buffer(json.as_bytes(), metadata_out_buf, metadata_len) {
+        0
+    } else {
+        3
+    }
+}
+
+/// Returns the configured result for the parameter, storing the value
+/// if that result is 0
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_set_parameter(name: *const c_uchar,
+                                              name_len: uint32_t,
+                                              value: uint32_t) -> uint32_t {
+    let name = String::from_utf8_lossy(slice::from_raw_parts(name, name_len as usize)).into_owned();
+    let result = CONFIG.read().unwrap().set_parameter_results.get(&name).cloned().unwrap_or(0);
+    if result == 0 {
+        PARAMETERS.lock().unwrap().insert(name, serde_json::Value::from(value));
+    }
+    result
+}
+
+/// Returns a previously set integer parameter, or 1 if it hasn't been set
+/// or isn't an integer
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_get_parameter(name: *const c_uchar,
+                                              name_len: uint32_t,
+                                              value: *mut uint32_t) -> uint32_t {
+    let name = String::from_utf8_lossy(slice::from_raw_parts(name, name_len as usize)).into_owned();
+    match PARAMETERS.lock().unwrap().get(&name).and_then(|v| v.as_u64()) {
+        Some(v) => {
+            *value = v as uint32_t;
+            0
+        },
+        None => 1,
+    }
+}
+
+/// Returns the configured result for the parameter, storing the JSON value
+/// if that result is 0, or returning 2 if it isn't JSON
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_set_parameter_value(name: *const c_uchar,
+                                                    name_len: uint32_t,
+                                                    value: *const c_uchar,
+                                                    value_len: uint32_t) -> uint32_t {
+    let name = String::from_utf8_lossy(slice::from_raw_parts(name, name_len as usize)).into_owned();
+    let value = match serde_json::from_slice(slice::from_raw_parts(value, value_len as usize)) {
+        Ok(v) => v,
+        Err(_) => return 2,
+    };
+    let result = CONFIG.read().unwrap().set_parameter_results.get(&name).cloned().unwrap_or(0);
+    if result == 0 {
+        PARAMETERS.lock().unwrap().insert(name, value);
+    }
+    result
+}
+
+/// Writes a previously set parameter as JSON, or returns 1 if it hasn't been set
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_get_parameter_value(name: *const c_uchar,
+                                                    name_len: uint32_t,
+                                                    value_out_buf: *mut c_uchar,
+                                                    value_len: *mut uint32_t) -> uint32_t {
+    let name = String::from_utf8_lossy(slice::from_raw_parts(name, name_len as usize)).into_owned();
+    let json = match PARAMETERS.lock().unwrap().get(&name) {
+        Some(v) => serde_json::to_string(v).unwrap(),
+        None => return 1,
+    };
+    if write_buffer(json.as_bytes(), value_out_buf, value_len) {
+        0
+    } else {
+        3
+    }
+}
+
+/// Accepts hashes while processing and the input queue isn't full
+
+#[no_mangle]
+pub extern "C" fn cuckoo_is_queue_under_limit() -> uint32_t {
+    if IS_RUNNING.load(Ordering::SeqCst) &&
+       INPUT_QUEUE.lock().unwrap().len() < INPUT_QUEUE_LIMIT {
+        1
+    } else {
+        0
+    }
+}
+
+/// Pushes a hash to the input queue, to be processed on the next read
+/// from the output queue
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_push_to_input_queue(hash: *const c_uchar,
+                                                    hash_len: uint32_t,
+                                                    nonce: *const c_uchar) -> uint32_t {
+    if !IS_RUNNING.load(Ordering::SeqCst) {
+        return 0;
+    }
+    let hash = slice::from_raw_parts(hash, hash_len as usize).to_vec();
+    let mut nonce_bytes = [0; 8];
+    ptr::copy_nonoverlapping(nonce, nonce_bytes.as_mut_ptr(), 8);
+    INPUT_QUEUE.lock().unwrap().push_back((hash, nonce_bytes));
+    1
+}
+
+/// "Processes" everything in the input queue, putting all scripted solutions
+/// into the output queue, then pops a solution from the output queue, returning
+/// 1 if there was one
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_read_from_output_queue(sol_nonces: *mut uint32_t,
+                                                       nonce: *mut c_uchar) -> uint32_t {
+    let mut output = OUTPUT_QUEUE.lock().unwrap();
+    for (hash, n) in INPUT_QUEUE.lock().unwrap().drain(..) {
+        HASH_COUNT.fetch_add(1, Ordering::SeqCst);
+        for s in solutions_for(&hash) {
+            output.push_back((s, n));
+        }
+    }
+    match output.pop_front() {
+        Some((s, n)) => {
+            ptr::copy_nonoverlapping(s.as_ptr(), sol_nonces, s.len());
+            ptr::copy_nonoverlapping(n.as_ptr(), nonce, 8);
+            1
+        },
+        None => 0,
+    }
+}
+
+/// Starts accepting hashes
+
+#[no_mangle]
+pub extern "C" fn cuckoo_start_processing() -> uint32_t {
+    IS_RUNNING.store(true, Ordering::SeqCst);
+    1
+}
+
+/// Stops accepting hashes and clears both queues, after any
+/// configured delay
+
+#[no_mangle]
+pub extern "C" fn cuckoo_stop_processing() -> uint32_t {
+    let (delay, stalls) = {
+        let config = CONFIG.read().unwrap();
+        (config.stop_processing_delay_ms, config.stop_processing_stalls)
+    };
+    IS_RUNNING.store(false, Ordering::SeqCst);
+    if stalls {
+        loop {
+            thread::sleep(Duration::from_secs(3600));
+        }
+    }
+    thread::sleep(Duration::from_millis(delay));
+    INPUT_QUEUE.lock().unwrap().clear();
+    OUTPUT_QUEUE.lock().unwrap().clear();
+    1
+}
+
+/// Returns the number of hashes seen since this was last called
+
+#[no_mangle]
+pub extern "C" fn cuckoo_hashes_since_last_call() -> uint32_t {
+    HASH_COUNT.swap(0, Ordering::SeqCst) as uint32_t
+}
@@ -0,0 +1,281 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Implementation of the Cuckoo Cycle simple miner designed by John Tromp,
+//! ported to Rust from the C code at https://github.com/tromp/cuckoo via
+//! Grin's implementation (as is the verifier in miner::verify).
+
+use std::collections::HashSet;
+use std::cmp;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+/// Length of a cuckoo cycle solution
+pub const PROOFSIZE: usize = 42;
+
+const MAXPATHLEN: usize = 8192;
+
+/// An edge in the Cuckoo graph, simply references two u64 nodes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+struct Edge {
+	u: u64,
+	v: u64,
+}
+
+/// Cuckoo cycle context
+pub struct Cuckoo {
+	mask: u64,
+	v: [u64; 4],
+}
+
+impl Cuckoo {
+	/// Initializes a new Cuckoo Cycle setup, using the provided byte array to
+	/// generate the siphash keys, as the C plugins do.
+	pub fn new(header: &[u8], sizeshift: u32) -> Cuckoo {
+		let mut hasher = Sha256::new();
+		let mut hashed = [0; 32];
+		hasher.input(header);
+		hasher.result(&mut hashed);
+
+		let k0 = u8_to_u64(hashed, 0);
+		let k1 = u8_to_u64(hashed, 8);
+		let mut v = [0; 4];
+		v[0] = k0 ^ 0x736f6d6570736575;
+		v[1] = k1 ^ 0x646f72616e646f6d;
+		v[2] = k0 ^ 0x6c7967656e657261;
+		v[3] = k1 ^ 0x7465646279746573;
+		Cuckoo {
+			v: v,
+			mask: (1 << sizeshift) / 2 - 1,
+		}
+	}
+
+	/// Generates a node in the cuckoo graph generated from our seed.
+	fn new_node(&self, nonce: u64, uorv: u64) -> u64 {
+		((siphash24(self.v, 2 * nonce + uorv) & self.mask) << 1) | uorv
+	}
+
+	/// Creates a new edge in the cuckoo graph generated by our seed from a
+	/// nonce.
+	fn new_edge(&self, nonce: u64) -> Edge {
+		Edge {
+			u: self.new_node(nonce, 0),
+			v: self.new_node(nonce, 1),
+		}
+	}
+}
+
+enum CycleSol {
+	ValidProof([u32; PROOFSIZE]),
+	InvalidCycle,
+	NoCycle,
+}
+
+/// Miner for the simple algorithm, following paths through a
+/// single cuckoo hash table until a cycle is found.
+pub struct Miner {
+	easiness: u64,
+	cuckoo: Cuckoo,
+	graph: Vec<u32>,
+}
+
+impl Miner {
+	/// Creates a new miner for the given header, graph size and
+	/// easiness percentage
+	pub fn new(header: &[u8], sizeshift: u32, ease: u32) -> Miner {
+		let cuckoo = Cuckoo::new(header, sizeshift);
+		let size = 1 << sizeshift;
+		Miner {
+			easiness: (ease as u64) * size / 100,
+			cuckoo: cuckoo,
+			graph: vec![0; size as usize + 1],
+		}
+	}
+
+	/// Searches for a solution, returning the solution nonces of the
+	/// first 42-cycle found
+	pub fn mine(&mut self) -> Option<[u32; PROOFSIZE]> {
+		self.mine_all(1).pop()
+	}
+
+	/// Searches the whole graph, returning the solution nonces of every
+	/// 42-cycle found, up to `max_solutions`
+	pub fn mine_all(&mut self, max_solutions: usize) -> Vec<[u32; PROOFSIZE]> {
+		let mut solutions = Vec::new();
+		let mut us = [0; MAXPATHLEN];
+		let mut vs = [0; MAXPATHLEN];
+		for nonce in 0..self.easiness {
+			us[0] = self.cuckoo.new_node(nonce, 0) as u32;
+			vs[0] = self.cuckoo.new_node(nonce, 1) as u32;
+			// 0 is reserved as nil
+			if us[0] == 0 {
+				continue;
+			}
+			let u = self.graph[us[0] as usize];
+			let v = self.graph[vs[0] as usize];
+			let nu = match self.path(u, &mut us) {
+				Some(n) => n,
+				None => continue,
+			};
+			let nv = match self.path(v, &mut vs) {
+				Some(n) => n,
+				None => continue,
+			};
+			match self.find_sol(nu, &us, nv, &vs) {
+				CycleSol::ValidProof(res) => {
+					solutions.push(res);
+					if solutions.len() >= max_solutions {
+						break;
+					}
+				},
+				CycleSol::InvalidCycle => continue,
+				CycleSol::NoCycle => self.update_graph(nu, &us, nv, &vs),
+			}
+		}
+		solutions
+	}
+
+	fn path(&self, mut u: u32, us: &mut [u32]) -> Option<usize> {
+		let mut nu = 0;
+		while u != 0 {
+			nu += 1;
+			if nu >= MAXPATHLEN {
+				return None;
+			}
+			us[nu] = u;
+			u = self.graph[u as usize];
+		}
+		Some(nu)
+	}
+
+	fn update_graph(&mut self, mut nu: usize, us: &[u32], mut nv: usize, vs: &[u32]) {
+		if nu < nv {
+			while nu != 0 {
+				nu -= 1;
+				self.graph[us[nu + 1] as usize] = us[nu];
+			}
+			self.graph[us[0] as usize] = vs[0];
+		} else {
+			while nv != 0 {
+				nv -= 1;
+				self.graph[vs[nv + 1] as usize] = vs[nv];
+			}
+			self.graph[vs[0] as usize] = us[0];
+		}
+	}
+
+	fn find_sol(&mut self, mut nu: usize, us: &[u32], mut nv: usize, vs: &[u32]) -> CycleSol {
+		if us[nu] != vs[nv] {
+			return CycleSol::NoCycle;
+		}
+		let min = cmp::min(nu, nv);
+		nu -= min;
+		nv -= min;
+		while us[nu] != vs[nv] {
+			nu += 1;
+			nv += 1;
+		}
+		if nu + nv + 1 == PROOFSIZE {
+			self.solution(us, nu, vs, nv)
+		} else {
+			CycleSol::InvalidCycle
+		}
+	}
+
+	// Recovers the nonces of the edges making up a found cycle
+
+	fn solution(&mut self, us: &[u32], mut nu: usize, vs: &[u32], mut nv: usize) -> CycleSol {
+		let mut cycle = HashSet::new();
+		cycle.insert(Edge { u: us[0] as u64, v: vs[0] as u64 });
+		while nu != 0 {
+			nu -= 1;
+			cycle.insert(Edge { u: us[(nu + 1) & !1] as u64, v: us[nu | 1] as u64 });
+		}
+		while nv != 0 {
+			nv -= 1;
+			cycle.insert(Edge { v: vs[(nv + 1) & !1] as u64, u: vs[nv | 1] as u64 });
+		}
+		let mut n = 0;
+		let mut sol = [0; PROOFSIZE];
+		for nonce in 0..self.easiness {
+			let edge = self.cuckoo.new_edge(nonce);
+			if cycle.remove(&edge) {
+				sol[n] = nonce as u32;
+				n += 1;
+				if n == PROOFSIZE {
+					return CycleSol::ValidProof(sol);
+				}
+			}
+		}
+		CycleSol::NoCycle
+	}
+}
+
+/// Utility to transform a 8 bytes of a byte array into a u64.
+fn u8_to_u64(p: [u8; 32], i: usize) -> u64 {
+	(p[i] as u64) | (p[i + 1] as u64) << 8 | (p[i + 2] as u64) << 16 | (p[i + 3] as u64) << 24 |
+	(p[i + 4] as u64) << 32 | (p[i + 5] as u64) << 40 |
+	(p[i + 6] as u64) << 48 | (p[i + 7] as u64) << 56
+}
+
+/// Implements siphash 2-4 specialized for a 4 u64 array key and a u64 nonce
+fn siphash24(v: [u64; 4], nonce: u64) -> u64 {
+	let mut v0 = v[0];
+	let mut v1 = v[1];
+	let mut v2 = v[2];
+	let mut v3 = v[3] ^ nonce;
+
+	// macro for left rotation
+	macro_rules! rotl {
+		($num:ident, $shift:expr) => {
+			$num = ($num << $shift) | ($num >> (64 - $shift));
+		}
+	}
+
+	// macro for a single siphash round
+	macro_rules! round {
+		() => {
+			v0 = v0.wrapping_add(v1);
+			v2 = v2.wrapping_add(v3);
+			rotl!(v1, 13);
+			rotl!(v3, 16);
+			v1 ^= v0;
+			v3 ^= v2;
+			rotl!(v0, 32);
+			v2 = v2.wrapping_add(v1);
+			v0 = v0.wrapping_add(v3);
+			rotl!(v1, 17);
+			rotl!(v3, 21);
+			v1 ^= v2;
+			v3 ^= v0;
+			rotl!(v2, 32);
+		}
+	}
+
+	// 2 rounds
+	round!();
+	round!();
+
+	v0 ^= nonce;
+	v2 ^= 0xff;
+
+	// and then 4 rounds, hence siphash 2-4
+	round!();
+	round!();
+	round!();
+	round!();
+
+	v0 ^ v1 ^ v2 ^ v3
+}
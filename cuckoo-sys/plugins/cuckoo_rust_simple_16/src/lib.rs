@@ -0,0 +1,320 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A pure Rust cuckoo-miner plugin, implementing the Cuckoo Cycle simple miner
+//! for a graph of size 16. This exports the full plugin interface described
+//! in `plugins/common/lib.rs`, including the async input and output queues,
+//! and returns every cycle in a graph, both from `cuckoo_call_all` and via
+//! the output queue, where each is tagged with its header's nonce. It can be
+//! used to exercise cuckoo-miner without a C toolchain or the cuckoo submodule.
+//!
+
+#![allow(non_camel_case_types)]
+
+extern crate libc;
+extern crate crypto;
+#[macro_use]
+extern crate lazy_static;
+
+mod cuckoo;
+
+use std::collections::VecDeque;
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use std::{ptr, slice};
+
+use libc::*;
+
+use cuckoo::{Miner, PROOFSIZE};
+
+/// The version of the plugin interface implemented here
+const ABI_VERSION: uint32_t = 1;
+
+/// Size of the cuckoo graph, as a power of 2
+const SIZESHIFT: u32 = 16;
+
+/// Percentage of the graph's nodes to use as edges
+const EASINESS: u32 = 50;
+
+const PLUGIN_NAME: &str = "rust_simple_16";
+const PLUGIN_DESCRIPTION: &str = "Pure Rust implementation of the Cuckoo Cycle simple miner, Cuckoo size 16";
+
+const PARAMETER_LIST: &str = "[{\"name\":\"NUM_THREADS\",\
+    \"description\":\"Number of worker threads used in async mode\",\
+    \"default_value\":1,\"min_value\":1,\"max_value\":32}]";
+
+const METADATA: &str = "{\"variation\":\"simple\",\"edge_bits\":15,\"easiness\":50}";
+
+/// How many hashes may wait in the input queue per worker thread
+const QUEUE_SIZE_PER_THREAD: usize = 2;
+
+/// The most cycles put in the output queue for a single hash
+const MAX_SOLUTIONS_PER_HASH: usize = 16;
+
+type InputQueue = VecDeque<(Vec<u8>, [u8; 8])>;
+type OutputQueue = VecDeque<([u32; PROOFSIZE], [u8; 8])>;
+
+lazy_static!{
+    static ref NUM_THREADS: AtomicUsize = AtomicUsize::new(1);
+    static ref IS_RUNNING: AtomicBool = AtomicBool::new(false);
+    static ref ACTIVE_JOBS: AtomicUsize = AtomicUsize::new(0);
+    static ref HASH_COUNT: AtomicUsize = AtomicUsize::new(0);
+    static ref INPUT_QUEUE: Mutex<InputQueue> = Mutex::new(VecDeque::new());
+    static ref OUTPUT_QUEUE: Mutex<OutputQueue> = Mutex::new(VecDeque::new());
+    static ref WORKERS: RwLock<Vec<JoinHandle<()>>> = RwLock::new(Vec::new());
+}
+
+// Copies as much of `src` as fits into the caller's buffer, updating
+// the length to what was written
+
+unsafe fn write_buffer(src: &[u8], buf: *mut c_uchar, buf_len: *mut uint32_t) -> bool {
+    let fits = src.len() <= *buf_len as usize;
+    let len = if fits { src.len() } else { *buf_len as usize };
+    ptr::copy_nonoverlapping(src.as_ptr(), buf, len);
+    *buf_len = len as uint32_t;
+    fits
+}
+
+// Pulls hashes from the input queue and mines them until processing is stopped
+
+fn worker_loop() {
+    while IS_RUNNING.load(Ordering::SeqCst) {
+        let next = INPUT_QUEUE.lock().unwrap().pop_front();
+        match next {
+            Some((hash, nonce)) => {
+                let solutions = Miner::new(&hash, SIZESHIFT, EASINESS).mine_all(MAX_SOLUTIONS_PER_HASH);
+                OUTPUT_QUEUE.lock().unwrap().extend(solutions.into_iter().map(|s| (s, nonce)));
+                //only count the hash once its solution can be read
+                HASH_COUNT.fetch_add(1, Ordering::SeqCst);
+            },
+            None => thread::sleep(Duration::from_millis(1)),
+        }
+    }
+}
+
+/// Reports the plugin interface version implemented by this plugin
+
+#[no_mangle]
+pub extern "C" fn cuckoo_abi_version() -> uint32_t {
+    ABI_VERSION
+}
+
+/// Reports the number of nonces in each solution
+
+#[no_mangle]
+pub extern "C" fn cuckoo_proof_size() -> uint32_t {
+    PROOFSIZE as uint32_t
+}
+
+/// Initialises the plugin, resetting all parameters to their defaults
+
+#[no_mangle]
+pub extern "C" fn cuckoo_init() {
+    NUM_THREADS.store(1, Ordering::SeqCst);
+}
+
+/// Mines synchronously on the given header, filling `sol_nonces` with
+/// the first solution found
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_call(header: *const c_uchar,
+                                     header_len: uint32_t,
+                                     sol_nonces: *mut uint32_t) -> uint32_t {
+    let header = slice::from_raw_parts(header, header_len as usize);
+    HASH_COUNT.fetch_add(1, Ordering::SeqCst);
+    match Miner::new(header, SIZESHIFT, EASINESS).mine() {
+        Some(s) => {
+            ptr::copy_nonoverlapping(s.as_ptr(), sol_nonces, PROOFSIZE);
+            1
+        },
+        None => 0,
+    }
+}
+
+/// Mines synchronously on the given header, filling `sol_nonces` with
+/// every solution found, up to `max_solutions`
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_call_all(header: *const c_uchar,
+                                         header_len: uint32_t,
+                                         sol_nonces: *mut uint32_t,
+                                         max_solutions: uint32_t) -> uint32_t {
+    let header = slice::from_raw_parts(header, header_len as usize);
+    HASH_COUNT.fetch_add(1, Ordering::SeqCst);
+    let solutions = Miner::new(header, SIZESHIFT, EASINESS).mine_all(max_solutions as usize);
+    for (i, s) in solutions.iter().enumerate() {
+        ptr::copy_nonoverlapping(s.as_ptr(), sol_nonces.add(i * PROOFSIZE), PROOFSIZE);
+    }
+    solutions.len() as uint32_t
+}
+
+/// Writes the plugin's name and description into the provided buffers
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_description(name_buf: *mut c_uchar,
+                                            name_buf_len: *mut uint32_t,
+                                            description_buf: *mut c_uchar,
+                                            description_buf_len: *mut uint32_t) {
+    write_buffer(PLUGIN_NAME.as_bytes(), name_buf, name_buf_len);
+    write_buffer(PLUGIN_DESCRIPTION.as_bytes(), description_buf, description_buf_len);
+}
+
+/// Writes the JSON list of the plugin's parameters into the provided buffer
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_parameter_list(params_out_buf: *mut c_uchar,
+                                               params_len: *mut uint32_t) -> uint32_t {
+    if write_buffer(PARAMETER_LIST.as_bytes(), params_out_buf, params_len) {
+        0
+    } else {
+        3
+    }
+}
+
+/// Writes the JSON description of the algorithm into the provided buffer
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_metadata(metadata_out_buf: *mut c_uchar,
+                                         metadata_len: *mut uint32_t) -> uint32_t {
+    if write_buffer(METADATA.as_bytes(), metadata_out_buf, metadata_len) {
+        0
+    } else {
+        3
+    }
+}
+
+/// Sets a parameter, returning 1 if the parameter doesn't exist or 2 if
+/// the value is out of range
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_set_parameter(name: *const c_uchar,
+                                              name_len: uint32_t,
+                                              value: uint32_t) -> uint32_t {
+    let name = slice::from_raw_parts(name, name_len as usize);
+    if name != b"NUM_THREADS" {
+        return 1;
+    }
+    if value < 1 || value > 32 {
+        return 2;
+    }
+    NUM_THREADS.store(value as usize, Ordering::SeqCst);
+    0
+}
+
+/// Retrieves a parameter, returning 1 if the parameter doesn't exist
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_get_parameter(name: *const c_uchar,
+                                              name_len: uint32_t,
+                                              value: *mut uint32_t) -> uint32_t {
+    let name = slice::from_raw_parts(name, name_len as usize);
+    if name != b"NUM_THREADS" {
+        return 1;
+    }
+    *value = NUM_THREADS.load(Ordering::SeqCst) as uint32_t;
+    0
+}
+
+/// Returns 1 if the input queue can accept more hashes, 0 otherwise,
+/// including when processing isn't running
+
+#[no_mangle]
+pub extern "C" fn cuckoo_is_queue_under_limit() -> uint32_t {
+    if !IS_RUNNING.load(Ordering::SeqCst) {
+        return 0;
+    }
+    let limit = NUM_THREADS.load(Ordering::SeqCst) * QUEUE_SIZE_PER_THREAD;
+    if INPUT_QUEUE.lock().unwrap().len() < limit {
+        1
+    } else {
+        0
+    }
+}
+
+/// Pushes a hash and its nonce to the input queue, returning 0 if processing
+/// isn't running
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_push_to_input_queue(hash: *const c_uchar,
+                                                    hash_len: uint32_t,
+                                                    nonce: *const c_uchar) -> uint32_t {
+    if !IS_RUNNING.load(Ordering::SeqCst) {
+        return 0;
+    }
+    let hash = slice::from_raw_parts(hash, hash_len as usize).to_vec();
+    let mut nonce_bytes = [0; 8];
+    ptr::copy_nonoverlapping(nonce, nonce_bytes.as_mut_ptr(), 8);
+    INPUT_QUEUE.lock().unwrap().push_back((hash, nonce_bytes));
+    1
+}
+
+/// Pops a solution and the nonce that generated it from the output queue,
+/// returning 1 if there was one
+
+#[no_mangle]
+pub unsafe extern "C" fn cuckoo_read_from_output_queue(sol_nonces: *mut uint32_t,
+                                                       nonce: *mut c_uchar) -> uint32_t {
+    match OUTPUT_QUEUE.lock().unwrap().pop_front() {
+        Some((s, n)) => {
+            ptr::copy_nonoverlapping(s.as_ptr(), sol_nonces, PROOFSIZE);
+            ptr::copy_nonoverlapping(n.as_ptr(), nonce, 8);
+            1
+        },
+        None => 0,
+    }
+}
+
+/// Starts the worker threads which process the input queue. Processing is
+/// reference counted, so overlapping jobs share the same workers
+
+#[no_mangle]
+pub extern "C" fn cuckoo_start_processing() -> uint32_t {
+    let mut workers = WORKERS.write().unwrap();
+    if ACTIVE_JOBS.fetch_add(1, Ordering::SeqCst) == 0 {
+        IS_RUNNING.store(true, Ordering::SeqCst);
+        for _ in 0..NUM_THREADS.load(Ordering::SeqCst) {
+            workers.push(thread::spawn(worker_loop));
+        }
+    }
+    1
+}
+
+/// Stops processing for one job. Once no jobs remain, stops the worker threads,
+/// waiting for them to exit, and clears both queues
+
+#[no_mangle]
+pub extern "C" fn cuckoo_stop_processing() -> uint32_t {
+    let mut workers = WORKERS.write().unwrap();
+    if ACTIVE_JOBS.load(Ordering::SeqCst) == 0 {
+        return 0;
+    }
+    if ACTIVE_JOBS.fetch_sub(1, Ordering::SeqCst) == 1 {
+        IS_RUNNING.store(false, Ordering::SeqCst);
+        for w in workers.drain(..) {
+            let _ = w.join();
+        }
+        INPUT_QUEUE.lock().unwrap().clear();
+        OUTPUT_QUEUE.lock().unwrap().clear();
+    }
+    1
+}
+
+/// Returns the number of hashes processed since this was last called
+
+#[no_mangle]
+pub extern "C" fn cuckoo_hashes_since_last_call() -> uint32_t {
+    HASH_COUNT.swap(0, Ordering::SeqCst) as uint32_t
+}
@@ -0,0 +1,72 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Worker process which hosts a single mining plugin, so that a misbehaving
+//! plugin can't take down the process that's using it. Requests are read
+//! from stdin and responses written to stdout, see the `worker` module of
+//! cuckoo-sys for details.
+//!
+//! Plugins print progress to stdout, which would corrupt the responses, so
+//! before anything is loaded the worker moves its stdout to a private file
+//! descriptor and points fd 1 at stderr instead. This isn't possible on
+//! non-unix platforms, where plugins mustn't write to stdout.
+
+extern crate cuckoo_sys;
+#[cfg(unix)]
+extern crate libc;
+
+#[cfg(unix)]
+use std::fs::File;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::io::FromRawFd;
+use std::process;
+
+// Takes over the process's stdout for responses, leaving anything else
+// written to fd 1 going to stderr
+
+#[cfg(unix)]
+fn protocol_output() -> io::Result<File> {
+    unsafe {
+        let fd = libc::fcntl(libc::STDOUT_FILENO, libc::F_DUPFD_CLOEXEC, 3);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let output = File::from_raw_fd(fd);
+        if libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(not(unix))]
+fn protocol_output() -> io::Result<io::Stdout> {
+    Ok(io::stdout())
+}
+
+fn main() {
+    let output = match protocol_output() {
+        Ok(o) => o,
+        Err(e) => {
+            eprintln!("cuckoo_plugin_worker: unable to redirect stdout: {}", e);
+            process::exit(1);
+        }
+    };
+    let stdin = io::stdin();
+    if let Err(e) = cuckoo_sys::run_worker(stdin.lock(), output) {
+        eprintln!("cuckoo_plugin_worker: {:?}", e);
+        process::exit(1);
+    }
+}
@@ -28,13 +28,31 @@ extern crate lazy_static;
 extern crate libloading as libloading;
 extern crate libc;
 extern crate error;
+extern crate crypto;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 #[macro_use]
 extern crate log;
 
 pub mod manager;
+pub mod worker;
+pub mod trust;
+pub mod parameter;
 
-pub use manager::{load_cuckoo_lib,
+pub use manager::{CUCKOO_ABI_VERSION,
+                  MIN_CUCKOO_ABI_VERSION,
+                  DEFAULT_PROOF_SIZE,
+                  MAX_PROOF_SIZE,
+                  MAX_CYCLES_PER_CALL,
+                  PluginHandle,
+                  PluginCapabilities,
+                  plugin_in_use,
+                  load_cuckoo_lib,
+                  load_cuckoo_lib_trusted,
                   unload_cuckoo_lib,
+                  call_cuckoo_proof_size,
                   call_cuckoo,
                   call_cuckoo_description,
                   call_cuckoo_parameter_list,
@@ -45,4 +63,15 @@ pub use manager::{load_cuckoo_lib,
                   call_cuckoo_read_from_output_queue,
                   call_cuckoo_start_processing,
                   call_cuckoo_stop_processing,
-                  call_cuckoo_hashes_since_last_call};
\ No newline at end of file
+                  call_cuckoo_hashes_since_last_call};
+
+pub use trust::{TrustPolicy, VerifiedLibrary};
+
+pub use parameter::{CuckooPluginParameter,
+                    ParameterType,
+                    ParameterValue,
+                    parse_parameter_list};
+
+pub use worker::{PluginWorker,
+                 default_worker_path,
+                 run_worker};
\ No newline at end of file
@@ -18,7 +18,10 @@
 //! miner modules. These functions are meant for internal cuckoo-miner crates, 
 //! and will not be exposed to other projects including the cuckoo-miner crate.
 
+use std::collections::HashMap;
+use std::fs;
 use std::sync::{Mutex};
+use std::{fmt, ops};
 
 use libloading;
 use libc::*;
@@ -27,23 +30,61 @@ use libc::*;
 mod test;
 
 use error::CuckooMinerError;
+use trust::{TrustPolicy, VerifiedLibrary};
+
+/// The version of the plugin interface (as described in the plugins' common
+/// lib.rs) implemented by this version of cuckoo-miner. Plugins report the
+/// version they implement via `cuckoo_abi_version`. Only breaking changes to
+/// the interface bump this, optional functions are detected by whether the
+/// plugin exports them (see [PluginCapabilities](struct.PluginCapabilities.html)).
+
+pub const CUCKOO_ABI_VERSION: u32 = 1;
+
+/// The oldest plugin interface version that can still be loaded. Plugins
+/// which don't export `cuckoo_abi_version` at all predate the handshake and
+/// are treated as implementing this version.
+
+pub const MIN_CUCKOO_ABI_VERSION: u32 = 1;
+
+/// The number of nonces in a solution found by plugins which don't
+/// report a proof size via `cuckoo_proof_size`, i.e. a conventional
+/// cuckoo cycle of length 42.
+
+pub const DEFAULT_PROOF_SIZE: u32 = 42;
+
+/// The largest proof size a plugin can report. Plugins reporting a
+/// larger (or an odd) proof size are rejected when loaded.
+
+pub const MAX_PROOF_SIZE: u32 = 1024;
+
+/// The most cycles a plugin may return from a single `cuckoo_call_all`.
+/// Any further cycles in the same graph are dropped by the plugin.
+
+pub const MAX_CYCLES_PER_CALL: u32 = 16;
 
 // PRIVATE MEMBERS
 
 // Type definitions corresponding to each function that the plugin implements
 
+type CuckooAbiVersion = unsafe extern fn() -> uint32_t;
+type CuckooProofSize = unsafe extern fn() -> uint32_t;
 type CuckooInit = unsafe extern fn();
 type CuckooCall = unsafe extern fn(*const c_uchar, uint32_t, *mut uint32_t) -> uint32_t;
+type CuckooCallAll = unsafe extern fn(*const c_uchar, uint32_t, *mut uint32_t, uint32_t) -> uint32_t;
 type CuckooDescription = unsafe extern fn(*mut c_uchar,*mut uint32_t,*mut c_uchar,*mut uint32_t);
 type CuckooParameterList = unsafe extern fn(*mut c_uchar,*mut uint32_t) -> uint32_t;
+type CuckooMetadata = unsafe extern fn(*mut c_uchar,*mut uint32_t) -> uint32_t;
 type CuckooSetParameter = unsafe extern fn(*const c_uchar, uint32_t, uint32_t) -> uint32_t;
 type CuckooGetParameter = unsafe extern fn(*const c_uchar, uint32_t, *mut uint32_t) -> uint32_t;
+type CuckooSetParameterValue = unsafe extern fn(*const c_uchar, uint32_t, *const c_uchar, uint32_t) -> uint32_t;
+type CuckooGetParameterValue = unsafe extern fn(*const c_uchar, uint32_t, *mut c_uchar, *mut uint32_t) -> uint32_t;
 type CuckooIsQueueUnderLimit = unsafe extern fn()->uint32_t;
 type CuckooPushToInputQueue = unsafe extern fn(*const c_uchar, uint32_t, *const c_uchar) -> uint32_t;
 type CuckooReadFromOutputQueue = unsafe extern fn(*mut uint32_t, *mut c_uchar) -> uint32_t;
 type CuckooStartProcessing = unsafe extern fn()->uint32_t;
 type CuckooStopProcessing = unsafe extern fn()->uint32_t;
 type CuckooHashesSinceLastCall = unsafe extern fn()->uint32_t;
+type CuckooAbort = unsafe extern fn();
 
 // Keep static references to the library and each call that a plugin can expose
 // wrapped in mutex, for theoretical thread-safety, though it's unlikely that
@@ -52,6 +93,8 @@ type CuckooHashesSinceLastCall = unsafe extern fn()->uint32_t;
 
 lazy_static!{
     static ref LOADED_LIBRARY: Mutex<Option<libloading::Library>> = Mutex::new(None);
+    // The private copy the loaded library was loaded from, if it was verified
+    static ref LOADED_COPY: Mutex<Option<VerifiedLibrary>> = Mutex::new(None);
     static ref CUCKOO_INIT: Mutex<Option<CuckooInit>> = Mutex::new(None);
     static ref CUCKOO_CALL: Mutex<Option<CuckooCall>> = Mutex::new(None);
     static ref CUCKOO_DESCRIPTION: Mutex<Option<CuckooDescription>> = Mutex::new(None);
@@ -64,16 +107,153 @@ lazy_static!{
     static ref CUCKOO_START_PROCESSING: Mutex<Option<CuckooStartProcessing>> = Mutex::new(None);
     static ref CUCKOO_STOP_PROCESSING: Mutex<Option<CuckooStopProcessing>> = Mutex::new(None);
     static ref CUCKOO_HASHES_SINCE_LAST_CALL: Mutex<Option<CuckooHashesSinceLastCall>> = Mutex::new(None);
+
+    // Held while loading or unloading the plugin, so that a load can't
+    // interleave with replacing the plugin
+    static ref LOAD_LOCK: Mutex<()> = Mutex::new(());
+
+    // Whether the loaded plugin has been told to start processing
+    // without being told to stop
+    static ref IS_PROCESSING: Mutex<bool> = Mutex::new(false);
+
+    // The proof size of the loaded plugin
+    static ref PROOF_SIZE: Mutex<Option<u32>> = Mutex::new(None);
+
+    // How many handles and workers currently hold each plugin library,
+    // keyed by canonical path
+    static ref PLUGINS_IN_USE: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+}
+
+// Size of the buffers first offered to a plugin's cuckoo_description and
+// cuckoo_parameter_list functions
+
+const INITIAL_BUFFER_SIZE: usize = 256;
+
+// Buffers are doubled up to this size for as long as the plugin reports
+// they're too small
+
+const MAX_BUFFER_SIZE: usize = 1024 * 1024;
+
+// Calls cuckoo_description with heap buffers, doubling them while either result
+// fills its buffer completely (and so may have been truncated). Each buffer is
+// left holding exactly the bytes the plugin wrote
+
+unsafe fn read_description(c: CuckooDescription, name_bytes: &mut Vec<u8>,
+                           description_bytes: &mut Vec<u8>) {
+    let mut size = INITIAL_BUFFER_SIZE;
+    loop {
+        name_bytes.resize(size, 0);
+        description_bytes.resize(size, 0);
+        let mut name_len = size as u32;
+        let mut desc_len = size as u32;
+        c(name_bytes.as_mut_ptr(), &mut name_len, description_bytes.as_mut_ptr(), &mut desc_len);
+        let name_len = (name_len as usize).min(size);
+        let desc_len = (desc_len as usize).min(size);
+        if (name_len < size && desc_len < size) || size >= MAX_BUFFER_SIZE {
+            name_bytes.truncate(name_len);
+            description_bytes.truncate(desc_len);
+            return;
+        }
+        size *= 2;
+    }
+}
+
+// Calls cuckoo_parameter_list with a heap buffer, doubling it while the plugin
+// returns 3 (buffer too small). The buffer is left holding exactly the bytes
+// the plugin wrote, and the plugin's last return code is returned. Also used
+// for cuckoo_metadata, which works the same way
+
+unsafe fn read_parameter_list(c: CuckooParameterList, param_list_bytes: &mut Vec<u8>) -> u32 {
+    read_buffer(param_list_bytes, |buf, len| c(buf, len))
+}
+
+// As read_parameter_list, for any function filling a buffer that way
+
+unsafe fn read_buffer<F>(bytes: &mut Vec<u8>, mut f: F) -> u32
+    where F: FnMut(*mut c_uchar, *mut uint32_t) -> u32 {
+    let mut size = INITIAL_BUFFER_SIZE;
+    loop {
+        bytes.resize(size, 0);
+        let mut len = size as u32;
+        let result = f(bytes.as_mut_ptr(), &mut len);
+        if result != 3 || size >= MAX_BUFFER_SIZE {
+            bytes.truncate((len as usize).min(size));
+            return result;
+        }
+        size *= 2;
+    }
+}
+
+// Key identifying a plugin library in PLUGINS_IN_USE, resolving relative
+// paths and symlinks where the library still exists
+
+fn in_use_key(full_path: &str) -> String {
+    fs::canonicalize(full_path)
+        .map(|p| p.to_string_lossy().into_owned())
+        .unwrap_or(String::from(full_path))
+}
+
+// Marks a plugin library as in use for as long as the guard is held
+
+pub(crate) struct InUseGuard {
+    key: String,
+}
+
+impl InUseGuard {
+    pub(crate) fn new(full_path: &str) -> InUseGuard {
+        let key = in_use_key(full_path);
+        *PLUGINS_IN_USE.lock().unwrap().entry(key.clone()).or_insert(0) += 1;
+        InUseGuard { key: key }
+    }
+}
+
+impl Drop for InUseGuard {
+    fn drop(&mut self) {
+        let mut in_use = PLUGINS_IN_USE.lock().unwrap();
+        let remaining = match in_use.get_mut(&self.key) {
+            Some(count) => {
+                *count -= 1;
+                *count
+            },
+            None => return,
+        };
+        if remaining == 0 {
+            in_use.remove(&self.key);
+        }
+    }
+}
+
+/// #Description 
+///
+/// Checks whether a plugin library is currently held by a
+/// [PluginHandle](struct.PluginHandle.html) or a
+/// [PluginWorker](../worker/struct.PluginWorker.html) in this process, e.g.
+/// by a running miner, and so mustn't be replaced or reloaded.
+///
+/// #Arguments
+///
+/// * `full_path` (IN) The path to the plugin library.
+///
+/// #Returns
+///
+/// true if the library is in use.
+///
+
+pub fn plugin_in_use(full_path: &str) -> bool {
+    PLUGINS_IN_USE.lock().unwrap().contains_key(&in_use_key(full_path))
 }
 
 // Loads the library at lib_full_path into the LOADED_LIBRARY static,
-// as well as all associated plugin functions into their statics
+// as well as all associated plugin functions into their statics. Functions
+// the plugin doesn't export are left empty. Any plugin already loaded is
+// unloaded as unload_cuckoo_lib does, once the new one has passed its checks
 
-fn load_lib(lib_full_path:&str) -> Result<(), CuckooMinerError> {
+fn load_lib(lib_full_path:&str, verified:Option<VerifiedLibrary>) -> Result<(), CuckooMinerError> {
     debug!("Loading miner plugin: {}", &lib_full_path);
-    let mut loaded_library_ref = LOADED_LIBRARY.lock().unwrap();
-    
-    let result = libloading::Library::new(lib_full_path.clone());
+    let _load = LOAD_LOCK.lock().unwrap();
+
+    let load_path = verified.as_ref().map_or(lib_full_path, |v| v.load_path());
+    let result = libloading::Library::new(load_path);
     let loaded_lib = {
         match result {
             Ok(l) => l,
@@ -83,7 +263,14 @@ fn load_lib(lib_full_path:&str) -> Result<(), CuckooMinerError> {
         }
     };
 
+    check_abi_version(&loaded_lib, lib_full_path)?;
+    let proof_size = check_proof_size(&loaded_lib, lib_full_path)?;
+    unload_lib()?;
+
+    let mut loaded_library_ref = LOADED_LIBRARY.lock().unwrap();
     *loaded_library_ref = Some(loaded_lib);
+    *LOADED_COPY.lock().unwrap() = verified;
+    *PROOF_SIZE.lock().unwrap() = Some(proof_size);
 
     {
         let mut cuckoo_init_ref = CUCKOO_INIT.lock().unwrap();
@@ -98,54 +285,34 @@ fn load_lib(lib_full_path:&str) -> Result<(), CuckooMinerError> {
         let mut cuckoo_start_processing_ref = CUCKOO_START_PROCESSING.lock().unwrap();
         let mut cuckoo_stop_processing_ref = CUCKOO_STOP_PROCESSING.lock().unwrap();
         let mut cuckoo_hashes_since_last_call_ref = CUCKOO_HASHES_SINCE_LAST_CALL.lock().unwrap();
-        unsafe {
-            let fn_ref:CuckooCall = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_call\0")?;
-            *cuckoo_call_ref = Some(fn_ref);
-
-            let fn_ref:CuckooInit = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_init\0")?;
-            *cuckoo_init_ref = Some(fn_ref);
-
-            let fn_ref:CuckooDescription = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_description\0")?;
-            *cuckoo_description_ref = Some(fn_ref);
-
-            let fn_ref:CuckooParameterList = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_parameter_list\0")?;
-            *cuckoo_parameter_list_ref = Some(fn_ref);
-
-            let fn_ref:CuckooGetParameter = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_get_parameter\0")?;
-            *cuckoo_get_parameter_ref = Some(fn_ref);
-
-            let fn_ref:CuckooSetParameter = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_set_parameter\0")?;
-            *cuckoo_set_parameter_ref = Some(fn_ref);
-            
-            let fn_ref:CuckooIsQueueUnderLimit = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_is_queue_under_limit\0")?;
-            *cuckoo_is_queue_under_limit_ref = Some(fn_ref);
-    
-            let fn_ref:CuckooPushToInputQueue = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_push_to_input_queue\0")?;
-            *cuckoo_push_to_input_queue_ref = Some(fn_ref);
-
-            let fn_ref:CuckooReadFromOutputQueue = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_read_from_output_queue\0")?;
-            *cuckoo_read_from_output_queue_ref = Some(fn_ref);
-
-            let fn_ref:CuckooStartProcessing = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_start_processing\0")?;
-            *cuckoo_start_processing_ref = Some(fn_ref);
-        
-            let fn_ref:CuckooStopProcessing = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_stop_processing\0")?;
-            *cuckoo_stop_processing_ref = Some(fn_ref);
-
-            let fn_ref:CuckooHashesSinceLastCall = *loaded_library_ref.as_mut().unwrap().get(b"cuckoo_hashes_since_last_call\0")?;
-            *cuckoo_hashes_since_last_call_ref = Some(fn_ref);
-
-        }
+        let loaded_library = loaded_library_ref.as_ref().unwrap();
+        *cuckoo_call_ref = load_symbol(loaded_library, b"cuckoo_call\0");
+        *cuckoo_init_ref = load_symbol(loaded_library, b"cuckoo_init\0");
+        *cuckoo_description_ref = load_symbol(loaded_library, b"cuckoo_description\0");
+        *cuckoo_parameter_list_ref = load_symbol(loaded_library, b"cuckoo_parameter_list\0");
+        *cuckoo_get_parameter_ref = load_symbol(loaded_library, b"cuckoo_get_parameter\0");
+        *cuckoo_set_parameter_ref = load_symbol(loaded_library, b"cuckoo_set_parameter\0");
+        *cuckoo_is_queue_under_limit_ref = load_symbol(loaded_library, b"cuckoo_is_queue_under_limit\0");
+        *cuckoo_push_to_input_queue_ref = load_symbol(loaded_library, b"cuckoo_push_to_input_queue\0");
+        *cuckoo_read_from_output_queue_ref = load_symbol(loaded_library, b"cuckoo_read_from_output_queue\0");
+        *cuckoo_start_processing_ref = load_symbol(loaded_library, b"cuckoo_start_processing\0");
+        *cuckoo_stop_processing_ref = load_symbol(loaded_library, b"cuckoo_stop_processing\0");
+        *cuckoo_hashes_since_last_call_ref = load_symbol(loaded_library, b"cuckoo_hashes_since_last_call\0");
     }
     
-    //automagically call the init
-    call_cuckoo_init().unwrap();
+    //automagically call the init, if the plugin has one
+    if CUCKOO_INIT.lock().unwrap().is_some() {
+        call_cuckoo_init()?;
+    }
     Ok(())
 }
 
 /// #Description 
 ///
-/// Unloads the currently loaded plugin and all symbols.
+/// Unloads the currently loaded plugin and all symbols. If the plugin is
+/// currently processing asynchronously, processing is stopped first. Once
+/// unloaded, all `call_cuckoo_*` functions return a `PluginNotLoadedError`
+/// until another plugin is loaded. Does nothing if no plugin is loaded.
 ///
 /// #Arguments
 ///
@@ -153,48 +320,63 @@ fn load_lib(lib_full_path:&str) -> Result<(), CuckooMinerError> {
 ///
 /// #Returns
 ///
-/// Nothing
+/// Ok if the plugin was unloaded, or a `PluginProcessingError` if the plugin
+/// was processing and couldn't be stopped, in which case it remains loaded.
 ///
 
-pub fn unload_cuckoo_lib(){
-
-    let cuckoo_get_parameter_ref = CUCKOO_GET_PARAMETER.lock().unwrap();
-    drop(cuckoo_get_parameter_ref);
-
-    let cuckoo_set_parameter_ref = CUCKOO_SET_PARAMETER.lock().unwrap();
-    drop(cuckoo_set_parameter_ref);
-
-    let cuckoo_parameter_list_ref = CUCKOO_PARAMETER_LIST.lock().unwrap();
-    drop(cuckoo_parameter_list_ref);
-
-    let cuckoo_call_ref = CUCKOO_CALL.lock().unwrap();
-    drop(cuckoo_call_ref);
-    
-    let cuckoo_description_ref = CUCKOO_DESCRIPTION.lock().unwrap();
-    drop(cuckoo_description_ref);
-
-    let cuckoo_is_queue_under_limit_ref = CUCKOO_IS_QUEUE_UNDER_LIMIT.lock().unwrap();
-    drop(cuckoo_is_queue_under_limit_ref);
-
-    let cuckoo_push_to_input_queue_ref = CUCKOO_PUSH_TO_INPUT_QUEUE.lock().unwrap();
-    drop(cuckoo_push_to_input_queue_ref);
-
-    let cuckoo_read_from_output_queue_ref = CUCKOO_READ_FROM_OUTPUT_QUEUE.lock().unwrap();
-    drop(cuckoo_read_from_output_queue_ref);
-
-    let cuckoo_start_processing_ref = CUCKOO_START_PROCESSING.lock().unwrap();
-    drop(cuckoo_start_processing_ref);
-
-    let cuckoo_stop_processing_ref = CUCKOO_STOP_PROCESSING.lock().unwrap();
-    drop(cuckoo_stop_processing_ref);
+pub fn unload_cuckoo_lib() -> Result<(), CuckooMinerError> {
+    let _load = LOAD_LOCK.lock().unwrap();
+    unload_lib()
+}
 
-    let cuckoo_hashes_since_last_call_ref = CUCKOO_HASHES_SINCE_LAST_CALL.lock().unwrap();
-    drop(cuckoo_hashes_since_last_call_ref);
+// Unloads the plugin, as unload_cuckoo_lib, with LOAD_LOCK already held
 
-    let loaded_library_ref = LOADED_LIBRARY.lock().unwrap();
-    drop(loaded_library_ref);
+fn unload_lib() -> Result<(), CuckooMinerError> {
+    if *IS_PROCESSING.lock().unwrap() {
+        debug!("Stopping plugin processing before unloading");
+        if let Err(e) = call_cuckoo_stop_processing() {
+            return Err(CuckooMinerError::PluginProcessingError(
+                format!("Unable to stop processing before unloading plugin: {:?}", e)));
+        }
+    }
 
-    
+    //hold every lock while clearing, so no call can pick up a
+    //symbol from the library as it's being unloaded
+    let mut loaded_library_ref = LOADED_LIBRARY.lock().unwrap();
+    let mut cuckoo_init_ref = CUCKOO_INIT.lock().unwrap();
+    let mut cuckoo_call_ref = CUCKOO_CALL.lock().unwrap();
+    let mut cuckoo_description_ref = CUCKOO_DESCRIPTION.lock().unwrap();
+    let mut cuckoo_parameter_list_ref = CUCKOO_PARAMETER_LIST.lock().unwrap();
+    let mut cuckoo_get_parameter_ref = CUCKOO_GET_PARAMETER.lock().unwrap();
+    let mut cuckoo_set_parameter_ref = CUCKOO_SET_PARAMETER.lock().unwrap();
+    let mut cuckoo_is_queue_under_limit_ref = CUCKOO_IS_QUEUE_UNDER_LIMIT.lock().unwrap();
+    let mut cuckoo_push_to_input_queue_ref = CUCKOO_PUSH_TO_INPUT_QUEUE.lock().unwrap();
+    let mut cuckoo_read_from_output_queue_ref = CUCKOO_READ_FROM_OUTPUT_QUEUE.lock().unwrap();
+    let mut cuckoo_start_processing_ref = CUCKOO_START_PROCESSING.lock().unwrap();
+    let mut cuckoo_stop_processing_ref = CUCKOO_STOP_PROCESSING.lock().unwrap();
+    let mut cuckoo_hashes_since_last_call_ref = CUCKOO_HASHES_SINCE_LAST_CALL.lock().unwrap();
+
+    *cuckoo_init_ref = None;
+    *cuckoo_call_ref = None;
+    *cuckoo_description_ref = None;
+    *cuckoo_parameter_list_ref = None;
+    *cuckoo_get_parameter_ref = None;
+    *cuckoo_set_parameter_ref = None;
+    *cuckoo_is_queue_under_limit_ref = None;
+    *cuckoo_push_to_input_queue_ref = None;
+    *cuckoo_read_from_output_queue_ref = None;
+    *cuckoo_start_processing_ref = None;
+    *cuckoo_stop_processing_ref = None;
+    *cuckoo_hashes_since_last_call_ref = None;
+
+    if let Some(l) = loaded_library_ref.take() {
+        debug!("Unloading miner plugin");
+        drop(l);
+    }
+    *LOADED_COPY.lock().unwrap() = None;
+    *IS_PROCESSING.lock().unwrap() = false;
+    *PROOF_SIZE.lock().unwrap() = None;
+    Ok(())
 }
 
 
@@ -204,6 +386,11 @@ pub fn unload_cuckoo_lib(){
 ///
 /// Loads a cuckoo plugin library with the given full path, loading the library
 /// as well as static references to the library's set of plugin functions.
+/// Plugins reporting a plugin interface version outside of
+/// `MIN_CUCKOO_ABI_VERSION`..=`CUCKOO_ABI_VERSION` via `cuckoo_abi_version`
+/// are rejected, and the previously loaded plugin (if any) remains loaded.
+/// Otherwise the previously loaded plugin is unloaded first, as by
+/// [unload_cuckoo_lib](fn.unload_cuckoo_lib.html), stopping any processing.
 ///
 /// #Arguments
 ///
@@ -212,7 +399,10 @@ pub fn unload_cuckoo_lib(){
 /// #Returns
 ///
 /// Ok if successful, a [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
-/// with specific detail if an error is encountered.
+/// with specific detail if an error is encountered, or a `PluginAbiVersionError`
+/// if the plugin implements an unsupported interface version. A `PluginProcessingError`
+/// is returned if the previously loaded plugin couldn't be stopped, in which case
+/// it remains loaded.
 ///
 /// #Example
 ///
@@ -225,11 +415,59 @@ pub fn unload_cuckoo_lib(){
 ///
 
 pub fn load_cuckoo_lib(full_path:&str) -> Result<(), CuckooMinerError>{
-    let result=load_lib(full_path);
+    let result=load_lib(full_path, None);
     if let Err(e) = result {return Err(e)}
     Ok(()) 
 }
 
+/// #Description 
+///
+/// As [load_cuckoo_lib](fn.load_cuckoo_lib.html), but first checks the library
+/// against the given [TrustPolicy](../trust/struct.TrustPolicy.html). A library
+/// the policy refuses is never opened, and the previously loaded plugin (if any)
+/// remains loaded.
+///
+/// #Arguments
+///
+/// * `full_path` The full path to the plugin library .so/.dylib 
+///
+/// * `trust_policy` The policy the library must pass
+///
+/// #Returns
+///
+/// As for [load_cuckoo_lib](fn.load_cuckoo_lib.html), or a `PluginUntrustedError`
+/// if the policy refuses the library.
+///
+
+pub fn load_cuckoo_lib_trusted(full_path:&str, trust_policy:&TrustPolicy) -> Result<(), CuckooMinerError>{
+    let verified = trust_policy.open_verified(full_path)?;
+    load_lib(full_path, Some(verified))
+}
+
+/// #Description 
+///
+/// Returns the number of nonces in each solution found by the currently loaded
+/// plugin, as reported by its `cuckoo_proof_size` function when it was loaded,
+/// or `DEFAULT_PROOF_SIZE` if it doesn't export one.
+///
+/// #Arguments
+///
+/// * None
+///
+/// #Returns
+///
+/// Ok(proof_size) if a plugin is loaded, otherwise a `PluginNotLoadedError`
+///
+///
+
+pub fn call_cuckoo_proof_size() -> Result<u32, CuckooMinerError> {
+    match *PROOF_SIZE.lock().unwrap() {
+        None => Err(CuckooMinerError::PluginNotLoadedError(
+            String::from("No miner plugin is loaded. Please call init() with the name of a valid mining plugin."))),
+        Some(p) => Ok(p),
+    }
+}
+
 /// #Description 
 ///
 /// Initialises the cuckoo plugin, mostly allowing it to write a list of its accepted
@@ -262,8 +500,9 @@ pub fn call_cuckoo_init()
 /// #Description 
 ///
 /// Call to the cuckoo_call function of the currently loaded plugin, which will perform 
-/// a Cuckoo Cycle on the given seed, returning the first solution (a length 42 cycle)
-/// that is found. The implementation details are dependent on particular loaded plugin.
+/// a Cuckoo Cycle on the given seed, returning the first solution (a cycle with as many
+/// edges as the plugin's proof size) that is found. The implementation details are
+/// dependent on particular loaded plugin.
 ///
 /// #Arguments
 ///
@@ -272,14 +511,13 @@ pub fn call_cuckoo_init()
 ///    this is a SHA3 hash of a Grin blockheader, but from the plugin's perspective this 
 ///    can be anything.
 ///
-/// * `solutions` (OUT) A caller-allocated array of 42 unsigned bytes. This currently must
-///    be of size 42, corresponding to a conventional cuckoo-cycle solution length. 
-///    If a solution is found, the solution nonces will be stored in this array, otherwise,
-///    they will be left untouched.
+/// * `solutions` (OUT) A vector which is resized to the plugin's proof size (see
+///    [call_cuckoo_proof_size](fn.call_cuckoo_proof_size.html)) before the call.
+///    If a solution is found, the solution nonces will be stored in this vector.
 ///
 /// #Returns
 ///
-/// Ok(1) if a solution is found, with the 42 solution nonces contained within
+/// Ok(1) if a solution is found, with the solution nonces contained within
 /// `sol_nonces`. Returns Ok(0) if no solution is found and `sol_nonces` remains
 /// untouched. A [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
 /// will be returned if there is no plugin loaded, or if there is an error calling the function.
@@ -301,13 +539,14 @@ pub fn call_cuckoo_init()
 /// ```
 ///
 
-pub fn call_cuckoo(header: &[u8], solutions:&mut [u32; 42] ) -> Result<u32, CuckooMinerError> {
+pub fn call_cuckoo(header: &[u8], solutions:&mut Vec<u32> ) -> Result<u32, CuckooMinerError> {
     debug!("Calling loaded miner: header {:?}", header);
     let cuckoo_call_ref = CUCKOO_CALL.lock().unwrap(); 
     match *cuckoo_call_ref {
         None => return Err(CuckooMinerError::PluginNotLoadedError(
             String::from("No miner plugin is loaded. Please call init() with the name of a valid mining plugin."))),
         Some(c) => unsafe {
+                        solutions.resize(call_cuckoo_proof_size()? as usize, 0);
                         return Ok(c(header.as_ptr(), header.len() as u32, solutions.as_mut_ptr()));
                    },
         
@@ -320,21 +559,17 @@ pub fn call_cuckoo(header: &[u8], solutions:&mut [u32; 42] ) -> Result<u32, Cuck
 /// return various information about the plugin, including it's name, description, and
 /// other information to be added soon.
 ///
-/// #Arguments
-///
-/// * `name_bytes` (OUT) A caller-allocated u8 array to which the plugin will write its
-/// name. 
+/// The plugin is given heap buffers, which are grown and the call retried whenever
+/// either result fills its buffer completely, so long names and descriptions
+/// aren't truncated.
 ///
-/// * `name_bytes_len` (IN-OUT) When called, this should contain the maximum number of bytes
-/// the plugin should write to `name_bytes`. Upon return, this is filled with the number
-/// of bytes that were written to `name_bytes`.
+/// #Arguments
 ///
-/// * `description_bytes` (OUT) A caller-allocated u8 array to which the plugin will write its
-/// description. 
+/// * `name_bytes` (OUT) A vector which will be filled with the bytes of the
+/// plugin's name. Any existing contents are replaced.
 ///
-/// * `description_bytes_len` (IN-OUT) When called, this should contain the maximum number of bytes
-/// the plugin should write to `description_bytes`. Upon return, this is filled with the number
-/// of bytes that were written to `description_bytes`.
+/// * `description_bytes` (OUT) A vector which will be filled with the bytes of the
+/// plugin's description. Any existing contents are replaced.
 ///
 ///
 /// #Returns
@@ -347,25 +582,20 @@ pub fn call_cuckoo(header: &[u8], solutions:&mut [u32; 42] ) -> Result<u32, Cuck
 /// 
 /// ```
 ///  load_cuckoo_lib(&full_path)?;
-///  let mut name_bytes:[u8;256]=[0;256];
-///  let mut description_bytes:[u8;256]=[0;256];
-///  let mut name_len=name_bytes.len() as u32;
-///  let mut desc_len=description_bytes.len() as u32;
-///  call_cuckoo_description(&mut name_bytes, &mut name_len, 
-///                          &mut description_bytes, &mut desc_len);
+///  let mut name_bytes=Vec::new();
+///  let mut description_bytes=Vec::new();
+///  call_cuckoo_description(&mut name_bytes, &mut description_bytes)?;
 /// ```
 ///
 
-pub fn call_cuckoo_description(name_bytes: &mut [u8;256], name_bytes_len:&mut u32,
-                           description_bytes: &mut [u8;256], description_bytes_len:&mut u32) 
+pub fn call_cuckoo_description(name_bytes: &mut Vec<u8>, description_bytes: &mut Vec<u8>) 
     -> Result<(), CuckooMinerError>{
     let cuckoo_description_ref = CUCKOO_DESCRIPTION.lock().unwrap(); 
     match *cuckoo_description_ref {
         None => return Err(CuckooMinerError::PluginNotLoadedError(
             String::from("No miner plugin is loaded. Please call init() with the name of a valid mining plugin."))),
         Some(c) => unsafe {
-                        c(name_bytes.as_mut_ptr(), name_bytes_len, 
-                          description_bytes.as_mut_ptr(), description_bytes_len);
+                        read_description(c, name_bytes, description_bytes);
                         return Ok(());
                    },
         
@@ -378,38 +608,36 @@ pub fn call_cuckoo_description(name_bytes: &mut [u8;256], name_bytes_len:&mut u3
 /// which will provide an informative JSON array of the parameters that the plugin supports, as well
 /// as their descriptions and range of values.
 ///
-/// #Arguments
+/// The plugin is given a heap buffer, which is grown and the call retried for as
+/// long as the plugin returns 3 (buffer too small), up to a limit of 1MB.
 ///
-/// * `param_list_bytes` (OUT) A reference to a block of [u8] bytes to fill with the JSON
-///    result array
+/// #Arguments
 ///
-/// * `param_list_len` (IN-OUT) When called, this should contain the maximum number of bytes
-///    the plugin should write to `param_list_bytes`. Upon return, this is filled with the number
-///    of bytes that were written to `param_list_bytes`.
+/// * `param_list_bytes` (OUT) A vector which will be filled with the JSON result
+///    array. Any existing contents are replaced.
 ///
 /// #Returns
 ///
 /// 0 if the parameter list was retrived, and the result is stored in `param_list_bytes`
-/// 3 if the buffer and size given was too small to store the parameters
+/// 3 if the parameter list is still too large for the largest buffer tried
 ///
 /// #Example
 /// 
 /// ```
-///   let mut param_list_bytes:[u8;1024]=[0;1024];
-///   let mut param_list_len=param_list_bytes.len() as u32;
+///   let mut param_list_bytes=Vec::new();
 ///   //get a list of parameters
-///   let parameter_list=call_cuckoo_parameter_list(&mut param_list_bytes, &mut param_list_len);
+///   let parameter_list=call_cuckoo_parameter_list(&mut param_list_bytes);
 /// ```
 ///
 
-pub fn call_cuckoo_parameter_list(param_list_bytes: &mut [u8], param_list_len:&mut u32) 
+pub fn call_cuckoo_parameter_list(param_list_bytes: &mut Vec<u8>) 
     -> Result<u32, CuckooMinerError>{
     let cuckoo_parameter_list_ref = CUCKOO_PARAMETER_LIST.lock().unwrap(); 
     match *cuckoo_parameter_list_ref {
         None => return Err(CuckooMinerError::PluginNotLoadedError(
             String::from("No miner plugin is loaded. Please call init() with the name of a valid mining plugin."))),
         Some(c) => unsafe {
-                        return Ok(c(param_list_bytes.as_mut_ptr(), param_list_len));
+                        return Ok(read_parameter_list(c, param_list_bytes));
                    },
         
     };
@@ -569,8 +797,8 @@ pub fn call_cuckoo_push_to_input_queue(hash: &[u8], nonce:&[u8])
 ///
 /// #Arguments
 ///
-/// * `sol_nonces` (OUT) A block of 42 u32s in which the solution nonces will be stored,
-///    if any exist.
+/// * `sol_nonces` (OUT) A vector which is resized to the plugin's proof size, in which
+///    the solution nonces will be stored, if any exist.
 ///
 /// * `nonce` (OUT) A block of 8 u8s representing a Big-Endian u64, used for identification
 ///   purposes so the caller can reconstruct the header used to generate the solution
@@ -586,7 +814,7 @@ pub fn call_cuckoo_push_to_input_queue(hash: &[u8], nonce:&[u8])
 ///
 /// 
 /// ```
-///     let mut sol_nonces[u32;42]=[0;42];
+///     let mut sol_nonces=Vec::new();
 ///     let mut nonce[u8;8]=[0;8];  //Initialise this with a u64
 ///     while call_cuckoo_read_from_output_queue(&mut sol_nonces, &mut nonce).unwrap()!=0 {
 ///        ...
@@ -594,12 +822,13 @@ pub fn call_cuckoo_push_to_input_queue(hash: &[u8], nonce:&[u8])
 /// ```
 ///
 
-pub fn call_cuckoo_read_from_output_queue(solutions:&mut [u32; 42], nonce:&mut[u8; 8] ) -> Result<u32, CuckooMinerError> {
+pub fn call_cuckoo_read_from_output_queue(solutions:&mut Vec<u32>, nonce:&mut[u8; 8] ) -> Result<u32, CuckooMinerError> {
     let cuckoo_read_from_output_queue_ref = CUCKOO_READ_FROM_OUTPUT_QUEUE.lock().unwrap(); 
     match *cuckoo_read_from_output_queue_ref {
         None => return Err(CuckooMinerError::PluginNotLoadedError(
             String::from("No miner plugin is loaded. Please call init() with the name of a valid mining plugin."))),
         Some(c) => unsafe {
+                        solutions.resize(call_cuckoo_proof_size()? as usize, 0);
                         return Ok(c(solutions.as_mut_ptr(), nonce.as_mut_ptr()));
                    },
         
@@ -638,7 +867,9 @@ pub fn call_cuckoo_start_processing()
         None => return Err(CuckooMinerError::PluginNotLoadedError(
             String::from("No miner plugin is loaded. Please call init() with the name of a valid mining plugin."))),
         Some(c) => unsafe {
-                        return Ok(c());
+                        let result = c();
+                        *IS_PROCESSING.lock().unwrap() = true;
+                        return Ok(result);
                    },
         
     };
@@ -674,7 +905,9 @@ pub fn call_cuckoo_stop_processing()
         None => return Err(CuckooMinerError::PluginNotLoadedError(
             String::from("No miner plugin is loaded. Please call init() with the name of a valid mining plugin."))),
         Some(c) => unsafe {
-                        return Ok(c());
+                        let result = c();
+                        *IS_PROCESSING.lock().unwrap() = false;
+                        return Ok(result);
                    },
         
     };
@@ -714,3 +947,686 @@ pub fn call_cuckoo_hashes_since_last_call()
         
     };
 }
+
+// PLUGIN HANDLES
+
+// Helper to resolve a single symbol from a loaded library, copying out
+// the function pointer. Returns None if the plugin doesn't export the symbol.
+// The pointer is only valid while the library it was resolved from remains loaded.
+
+fn load_symbol<T: Copy>(library: &libloading::Library, name: &[u8]) -> Option<T> {
+    unsafe {
+        match library.get::<T>(name) {
+            Ok(symbol) => Some(*symbol),
+            Err(_) => None,
+        }
+    }
+}
+
+// Performs the ABI version handshake with a freshly loaded library, before any
+// of its other functions are resolved or called. Returns the version the plugin
+// implements, or a PluginAbiVersionError if it's one this crate can't call safely
+
+fn check_abi_version(library: &libloading::Library, full_path: &str) -> Result<u32, CuckooMinerError> {
+    let version = match load_symbol::<CuckooAbiVersion>(library, b"cuckoo_abi_version\0") {
+        Some(c) => unsafe { c() },
+        None => {
+            debug!("Plugin {} doesn't report an ABI version, assuming version {}", 
+                   full_path, MIN_CUCKOO_ABI_VERSION);
+            MIN_CUCKOO_ABI_VERSION
+        }
+    };
+    if version < MIN_CUCKOO_ABI_VERSION || version > CUCKOO_ABI_VERSION {
+        return Err(CuckooMinerError::PluginAbiVersionError(format!(
+            "{} - plugin implements ABI version {}, supported versions are {} to {}",
+            full_path, version, MIN_CUCKOO_ABI_VERSION, CUCKOO_ABI_VERSION)));
+    }
+    Ok(version)
+}
+
+// Reads the plugin's proof size via its optional cuckoo_proof_size function,
+// rejecting sizes that can't be a cycle or that are unreasonably large
+
+fn check_proof_size(library: &libloading::Library, full_path: &str) -> Result<u32, CuckooMinerError> {
+    let proof_size = match load_symbol::<CuckooProofSize>(library, b"cuckoo_proof_size\0") {
+        Some(c) => unsafe { c() },
+        None => DEFAULT_PROOF_SIZE,
+    };
+    if proof_size == 0 || proof_size % 2 != 0 || proof_size > MAX_PROOF_SIZE {
+        return Err(CuckooMinerError::PluginUnsupportedError(format!(
+            "{} - plugin reports unsupported proof size {}", full_path, proof_size)));
+    }
+    Ok(proof_size)
+}
+
+/// #Description 
+///
+/// The set of capabilities a loaded plugin provides, derived from which of
+/// the plugin functions it exports. Only `cuckoo_init` and `cuckoo_description`
+/// are entirely optional, every other function belongs to one of the following
+/// groups, and a group is only supported if all of its functions are present.
+///
+/// * `SYNC` - `cuckoo_call`
+/// * `ASYNC_QUEUE` - `cuckoo_is_queue_under_limit`, `cuckoo_push_to_input_queue`,
+///   `cuckoo_read_from_output_queue`, `cuckoo_start_processing` and `cuckoo_stop_processing`
+/// * `PARAMETERS` - `cuckoo_parameter_list`, `cuckoo_get_parameter` and `cuckoo_set_parameter`
+/// * `HASH_METRICS` - `cuckoo_hashes_since_last_call`
+/// * `TYPED_PARAMETERS` - `cuckoo_set_parameter_value` and `cuckoo_get_parameter_value`
+/// * `ABORT` - `cuckoo_abort`
+/// * `ALL_CYCLES` - `cuckoo_call_all`
+///
+/// A plugin must support at least one of `SYNC` or `ASYNC_QUEUE` to be loaded.
+///
+/// #Example
+///
+/// ```
+///  let plugin = PluginHandle::load("/path/to/cuckoo/plugins/cuckoo_cuda_30.so")?;
+///  if !plugin.capabilities().contains(PluginCapabilities::SYNC) {
+///      println!("Plugin only supports async mode");
+///  }
+/// ```
+///
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PluginCapabilities {
+    bits: u32,
+}
+
+impl PluginCapabilities {
+    /// Synchronous mining via cuckoo_call
+    pub const SYNC: PluginCapabilities = PluginCapabilities { bits: 0x01 };
+
+    /// Asynchronous mining via the plugin's input and output queues
+    pub const ASYNC_QUEUE: PluginCapabilities = PluginCapabilities { bits: 0x02 };
+
+    /// Listing, reading and setting plugin parameters
+    pub const PARAMETERS: PluginCapabilities = PluginCapabilities { bits: 0x04 };
+
+    /// Reporting the number of hashes processed
+    pub const HASH_METRICS: PluginCapabilities = PluginCapabilities { bits: 0x08 };
+
+    /// Reading and setting parameters with typed values, not just u32s
+    pub const TYPED_PARAMETERS: PluginCapabilities = PluginCapabilities { bits: 0x10 };
+
+    /// Abandoning a cuckoo_call in progress
+    pub const ABORT: PluginCapabilities = PluginCapabilities { bits: 0x20 };
+
+    /// Returning every cycle found in a graph via cuckoo_call_all
+    pub const ALL_CYCLES: PluginCapabilities = PluginCapabilities { bits: 0x40 };
+
+    /// Returns an empty set of capabilities
+    pub fn empty() -> PluginCapabilities {
+        PluginCapabilities { bits: 0 }
+    }
+
+    /// Returns the set with every capability
+    pub fn all() -> PluginCapabilities {
+        PluginCapabilities::SYNC | PluginCapabilities::ASYNC_QUEUE |
+        PluginCapabilities::PARAMETERS | PluginCapabilities::HASH_METRICS |
+        PluginCapabilities::TYPED_PARAMETERS | PluginCapabilities::ABORT |
+        PluginCapabilities::ALL_CYCLES
+    }
+
+    /// Builds a set from its raw bit representation, ignoring unknown bits
+    pub fn from_bits(bits: u32) -> PluginCapabilities {
+        PluginCapabilities { bits: bits & PluginCapabilities::all().bits }
+    }
+
+    /// The raw bit representation of the set
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    /// Whether no capabilities are set
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Whether all of the capabilities in `other` are also in this set
+    pub fn contains(&self, other: PluginCapabilities) -> bool {
+        self.bits & other.bits == other.bits
+    }
+
+    /// Adds all of the capabilities in `other` to this set
+    pub fn insert(&mut self, other: PluginCapabilities) {
+        self.bits |= other.bits;
+    }
+}
+
+impl ops::BitOr for PluginCapabilities {
+    type Output = PluginCapabilities;
+    fn bitor(self, other: PluginCapabilities) -> PluginCapabilities {
+        PluginCapabilities { bits: self.bits | other.bits }
+    }
+}
+
+impl fmt::Display for PluginCapabilities {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = [(PluginCapabilities::SYNC, "sync"),
+                     (PluginCapabilities::ASYNC_QUEUE, "async_queue"),
+                     (PluginCapabilities::PARAMETERS, "parameters"),
+                     (PluginCapabilities::HASH_METRICS, "hash_metrics"),
+                     (PluginCapabilities::TYPED_PARAMETERS, "typed_parameters"),
+                     (PluginCapabilities::ABORT, "abort"),
+                     (PluginCapabilities::ALL_CYCLES, "all_cycles")];
+        let supported = names.iter()
+            .filter(|&&(c, _)| self.contains(c))
+            .map(|&(_, n)| n)
+            .collect::<Vec<_>>();
+        write!(f, "[{}]", supported.join(", "))
+    }
+}
+
+/// #Description 
+///
+/// A handle to a single loaded plugin library, owning the library itself
+/// as well as each of the plugin functions resolved from it. Unlike the
+/// `call_cuckoo_*` functions above, which operate on a single process-wide
+/// plugin, any number of handles can be held at once, so for instance a
+/// cuckoo16 test miner and a cuckoo30 production miner can live in the same
+/// process. The library is unloaded when the handle is dropped.
+///
+/// Plugins don't need to export every function. Whatever a plugin supports
+/// is reported by [capabilities](#method.capabilities), and calling a function
+/// the plugin doesn't export returns a `PluginUnsupportedError`.
+///
+/// Note that loading the same library file twice will return the same
+/// underlying library from the OS, so two handles to the same plugin will
+/// share whatever internal state that plugin keeps. The exception is
+/// [load_trusted](#method.load_trusted), which loads a private copy.
+///
+/// #Example
+///
+/// ```
+///  let plugin = PluginHandle::load("/path/to/cuckoo/plugins/cuckoo_simple_30.so")?;
+///  let mut solution = Vec::new();
+///  let result = plugin.call_cuckoo(&header, &mut solution)?;
+/// ```
+///
+
+pub struct PluginHandle {
+    full_path: String,
+    abi_version: u32,
+    proof_size: u32,
+    capabilities: PluginCapabilities,
+    cuckoo_init: Option<CuckooInit>,
+    cuckoo_call: Option<CuckooCall>,
+    cuckoo_call_all: Option<CuckooCallAll>,
+    cuckoo_description: Option<CuckooDescription>,
+    cuckoo_parameter_list: Option<CuckooParameterList>,
+    cuckoo_metadata: Option<CuckooMetadata>,
+    cuckoo_get_parameter: Option<CuckooGetParameter>,
+    cuckoo_set_parameter: Option<CuckooSetParameter>,
+    cuckoo_get_parameter_value: Option<CuckooGetParameterValue>,
+    cuckoo_set_parameter_value: Option<CuckooSetParameterValue>,
+    cuckoo_is_queue_under_limit: Option<CuckooIsQueueUnderLimit>,
+    cuckoo_push_to_input_queue: Option<CuckooPushToInputQueue>,
+    cuckoo_read_from_output_queue: Option<CuckooReadFromOutputQueue>,
+    cuckoo_start_processing: Option<CuckooStartProcessing>,
+    cuckoo_stop_processing: Option<CuckooStopProcessing>,
+    cuckoo_hashes_since_last_call: Option<CuckooHashesSinceLastCall>,
+    cuckoo_abort: Option<CuckooAbort>,
+    // Must outlive all of the function pointers above
+    _library: libloading::Library,
+    // The private copy the library was loaded from, if it was verified,
+    // which must outlive the library
+    verified: Option<VerifiedLibrary>,
+    _in_use: InUseGuard,
+}
+
+impl PluginHandle {
+
+    /// #Description 
+    ///
+    /// Loads the cuckoo plugin library at the given full path, checks that it
+    /// implements a supported version of the plugin interface, resolves
+    /// whichever plugin functions it exports and calls its `cuckoo_init` function.
+    ///
+    /// #Arguments
+    ///
+    /// * `full_path` The full path to the plugin library .so/.dylib 
+    ///
+    /// #Returns
+    ///
+    /// Ok(PluginHandle) if successful, a [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
+    /// with specific detail if an error is encountered, including a `PluginAbiVersionError`
+    /// if the plugin implements an unsupported interface version, or a `PluginSymbolError`
+    /// if the plugin supports neither synchronous nor asynchronous mining.
+    ///
+
+    pub fn load(full_path:&str) -> Result<PluginHandle, CuckooMinerError> {
+        PluginHandle::load_library(full_path, None, true)
+    }
+
+    /// As [load](#method.load), but first checks the library against the given
+    /// [TrustPolicy](../trust/struct.TrustPolicy.html), returning a
+    /// `PluginUntrustedError` without opening the library if it's refused.
+    /// What's loaded is a private copy of the contents which were checked (see
+    /// [VerifiedLibrary](../trust/struct.VerifiedLibrary.html)), so unlike
+    /// [load](#method.load), it never shares state with another handle.
+
+    pub fn load_trusted(full_path:&str, trust_policy:&TrustPolicy) -> Result<PluginHandle, CuckooMinerError> {
+        let verified = trust_policy.open_verified(full_path)?;
+        PluginHandle::load_library(full_path, Some(verified), true)
+    }
+
+    /// #Description 
+    ///
+    /// Loads the plugin at the given path to find out what it supports, as
+    /// [load](#method.load) or [load_trusted](#method.load_trusted), except that
+    /// `cuckoo_init` isn't called if the plugin is already in use elsewhere in this
+    /// process. The OS returns the same underlying library in that case, and calling
+    /// `cuckoo_init` would reset the state of the plugin that's in use, e.g. any
+    /// parameters set on it. Trusted loads are of a private copy, so are always
+    /// initialised.
+    ///
+    /// #Arguments
+    ///
+    /// * `full_path` The full path to the plugin library .so/.dylib 
+    ///
+    /// * `trust_policy` The policy the library must pass, if any
+    ///
+    /// #Returns
+    ///
+    /// As for [load](#method.load), or a `PluginUntrustedError` if the policy
+    /// refuses the library.
+    ///
+
+    pub fn query(full_path:&str, trust_policy:Option<&TrustPolicy>) -> Result<PluginHandle, CuckooMinerError> {
+        match trust_policy {
+            Some(p) => PluginHandle::load_trusted(full_path, p),
+            None => PluginHandle::load_library(full_path, None, !plugin_in_use(full_path)),
+        }
+    }
+
+    fn load_library(full_path:&str, verified:Option<VerifiedLibrary>, init:bool)
+        -> Result<PluginHandle, CuckooMinerError> {
+        debug!("Loading miner plugin handle: {}", &full_path);
+        let load_path = verified.as_ref().map_or(full_path, |v| v.load_path());
+        let library = match libloading::Library::new(load_path) {
+            Ok(l) => l,
+            Err(e) => {
+                return Err(CuckooMinerError::PluginNotFoundError(format!("{} - {:?}", full_path, e)));
+            }
+        };
+
+        let abi_version = check_abi_version(&library, full_path)?;
+        let proof_size = check_proof_size(&library, full_path)?;
+
+        let mut handle = PluginHandle {
+            full_path: String::from(full_path),
+            abi_version: abi_version,
+            proof_size: proof_size,
+            capabilities: PluginCapabilities::empty(),
+            cuckoo_init: load_symbol(&library, b"cuckoo_init\0"),
+            cuckoo_call: load_symbol(&library, b"cuckoo_call\0"),
+            cuckoo_call_all: load_symbol(&library, b"cuckoo_call_all\0"),
+            cuckoo_description: load_symbol(&library, b"cuckoo_description\0"),
+            cuckoo_parameter_list: load_symbol(&library, b"cuckoo_parameter_list\0"),
+            cuckoo_metadata: load_symbol(&library, b"cuckoo_metadata\0"),
+            cuckoo_get_parameter: load_symbol(&library, b"cuckoo_get_parameter\0"),
+            cuckoo_set_parameter: load_symbol(&library, b"cuckoo_set_parameter\0"),
+            cuckoo_get_parameter_value: load_symbol(&library, b"cuckoo_get_parameter_value\0"),
+            cuckoo_set_parameter_value: load_symbol(&library, b"cuckoo_set_parameter_value\0"),
+            cuckoo_is_queue_under_limit: load_symbol(&library, b"cuckoo_is_queue_under_limit\0"),
+            cuckoo_push_to_input_queue: load_symbol(&library, b"cuckoo_push_to_input_queue\0"),
+            cuckoo_read_from_output_queue: load_symbol(&library, b"cuckoo_read_from_output_queue\0"),
+            cuckoo_start_processing: load_symbol(&library, b"cuckoo_start_processing\0"),
+            cuckoo_stop_processing: load_symbol(&library, b"cuckoo_stop_processing\0"),
+            cuckoo_hashes_since_last_call: load_symbol(&library, b"cuckoo_hashes_since_last_call\0"),
+            cuckoo_abort: load_symbol(&library, b"cuckoo_abort\0"),
+            _library: library,
+            verified: verified,
+            _in_use: InUseGuard::new(full_path),
+        };
+
+        handle.capabilities = handle.derive_capabilities();
+        debug!("Plugin {} capabilities: {}", full_path, handle.capabilities);
+        if !handle.capabilities.contains(PluginCapabilities::SYNC) &&
+           !handle.capabilities.contains(PluginCapabilities::ASYNC_QUEUE) {
+            return Err(CuckooMinerError::PluginSymbolError(format!(
+                "{} - plugin exports neither cuckoo_call nor the async queue functions", full_path)));
+        }
+
+        //automagically call the init
+        if init {
+            handle.call_cuckoo_init();
+        }
+        Ok(handle)
+    }
+
+    // Works out which groups of functions were fully resolved
+
+    fn derive_capabilities(&self) -> PluginCapabilities {
+        let mut caps = PluginCapabilities::empty();
+        if self.cuckoo_call.is_some() {
+            caps.insert(PluginCapabilities::SYNC);
+        }
+        if self.cuckoo_is_queue_under_limit.is_some() &&
+           self.cuckoo_push_to_input_queue.is_some() &&
+           self.cuckoo_read_from_output_queue.is_some() &&
+           self.cuckoo_start_processing.is_some() &&
+           self.cuckoo_stop_processing.is_some() {
+            caps.insert(PluginCapabilities::ASYNC_QUEUE);
+        }
+        if self.cuckoo_parameter_list.is_some() &&
+           self.cuckoo_get_parameter.is_some() &&
+           self.cuckoo_set_parameter.is_some() {
+            caps.insert(PluginCapabilities::PARAMETERS);
+        }
+        if self.cuckoo_hashes_since_last_call.is_some() {
+            caps.insert(PluginCapabilities::HASH_METRICS);
+        }
+        if self.cuckoo_set_parameter_value.is_some() &&
+           self.cuckoo_get_parameter_value.is_some() {
+            caps.insert(PluginCapabilities::TYPED_PARAMETERS);
+        }
+        if self.cuckoo_abort.is_some() {
+            caps.insert(PluginCapabilities::ABORT);
+        }
+        if self.cuckoo_call_all.is_some() {
+            caps.insert(PluginCapabilities::ALL_CYCLES);
+        }
+        caps
+    }
+
+    // Error returned when calling a function the plugin doesn't export
+
+    fn unsupported(&self, function_name: &str) -> CuckooMinerError {
+        CuckooMinerError::PluginUnsupportedError(format!(
+            "{} is not supported by plugin {}", function_name, self.full_path))
+    }
+
+    /// Returns the full path of the library this handle was loaded from
+
+    pub fn full_path(&self) -> &str {
+        &self.full_path
+    }
+
+    /// Returns the SHA-256 hash of the library contents which were checked
+    /// against the trust policy and loaded, hex encoded, if the handle was
+    /// loaded via [load_trusted](#method.load_trusted)
+
+    pub fn verified_hash(&self) -> Option<&str> {
+        self.verified.as_ref().map(|v| v.hash())
+    }
+
+    /// Returns the version of the plugin interface the plugin implements
+
+    pub fn abi_version(&self) -> u32 {
+        self.abi_version
+    }
+
+    /// Returns the number of nonces in each solution the plugin finds
+
+    pub fn proof_size(&self) -> u32 {
+        self.proof_size
+    }
+
+    /// Returns the set of capabilities supported by the plugin, based on
+    /// which plugin functions it exports
+
+    pub fn capabilities(&self) -> PluginCapabilities {
+        self.capabilities
+    }
+
+    /// Calls the plugin's cuckoo_init function, if it has one. Called
+    /// automatically by [load](#method.load)
+
+    pub fn call_cuckoo_init(&self) {
+        if let Some(c) = self.cuckoo_init {
+            unsafe {
+                c();
+            }
+        }
+    }
+
+    /// Calls the plugin's cuckoo_call function, see
+    /// [call_cuckoo](fn.call_cuckoo.html) for details
+
+    pub fn call_cuckoo(&self, header: &[u8], solutions:&mut Vec<u32>) -> Result<u32, CuckooMinerError> {
+        debug!("Calling miner plugin {}: header {:?}", self.full_path, header);
+        match self.cuckoo_call {
+            None => Err(self.unsupported("cuckoo_call")),
+            Some(c) => unsafe {
+                solutions.resize(self.proof_size as usize, 0);
+                Ok(c(header.as_ptr(), header.len() as u32, solutions.as_mut_ptr()))
+            },
+        }
+    }
+
+    /// #Description 
+    ///
+    /// Calls the plugin's cuckoo_call_all function, which searches the graph
+    /// for the given header like cuckoo_call, but returns every cycle it finds
+    /// rather than just the first.
+    ///
+    /// #Arguments
+    ///
+    /// * `header` (IN) As for [call_cuckoo](fn.call_cuckoo.html).
+    ///
+    /// * `solutions` (OUT) A vector which is filled with the solution nonces of
+    ///    each cycle found, up to [MAX_CYCLES_PER_CALL](constant.MAX_CYCLES_PER_CALL.html).
+    ///    Any existing contents are replaced.
+    ///
+    /// #Returns
+    ///
+    /// Ok(n) where n is the number of cycles found, a `PluginUnsupportedError` if
+    /// the plugin doesn't export cuckoo_call_all, or an `UnexpectedResultError` if
+    /// the plugin claims to have found more cycles than it was given room for.
+    ///
+
+    pub fn call_cuckoo_all(&self, header: &[u8], solutions: &mut Vec<Vec<u32>>)
+        -> Result<u32, CuckooMinerError> {
+        debug!("Calling miner plugin {} for all cycles: header {:?}", self.full_path, header);
+        let c = match self.cuckoo_call_all {
+            None => return Err(self.unsupported("cuckoo_call_all")),
+            Some(c) => c,
+        };
+        let proof_size = self.proof_size as usize;
+        let mut buffer = vec![0u32; proof_size * MAX_CYCLES_PER_CALL as usize];
+        let count = unsafe {
+            c(header.as_ptr(), header.len() as u32, buffer.as_mut_ptr(), MAX_CYCLES_PER_CALL)
+        };
+        if count > MAX_CYCLES_PER_CALL {
+            return Err(CuckooMinerError::UnexpectedResultError(count));
+        }
+        *solutions = buffer.chunks(proof_size)
+            .take(count as usize)
+            .map(|s| s.to_vec())
+            .collect();
+        Ok(count)
+    }
+
+    /// Calls the plugin's cuckoo_description function, see
+    /// [call_cuckoo_description](fn.call_cuckoo_description.html) for details
+
+    pub fn call_cuckoo_description(&self, name_bytes: &mut Vec<u8>, description_bytes: &mut Vec<u8>) 
+        -> Result<(), CuckooMinerError>{
+        match self.cuckoo_description {
+            None => Err(self.unsupported("cuckoo_description")),
+            Some(c) => unsafe {
+                read_description(c, name_bytes, description_bytes);
+                Ok(())
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_parameter_list function, see
+    /// [call_cuckoo_parameter_list](fn.call_cuckoo_parameter_list.html) for details
+
+    pub fn call_cuckoo_parameter_list(&self, param_list_bytes: &mut Vec<u8>) 
+        -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_parameter_list {
+            None => Err(self.unsupported("cuckoo_parameter_list")),
+            Some(c) => unsafe {
+                Ok(read_parameter_list(c, param_list_bytes))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_metadata function, filling `metadata_bytes`
+    /// with the JSON object the plugin reports. As with
+    /// [call_cuckoo_parameter_list](fn.call_cuckoo_parameter_list.html), returns
+    /// 0 on success or 3 if the metadata is too large for the largest buffer
+    /// tried. Metadata is optional, so a plugin without the function returns
+    /// a `PluginUnsupportedError`.
+
+    pub fn call_cuckoo_metadata(&self, metadata_bytes: &mut Vec<u8>)
+        -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_metadata {
+            None => Err(self.unsupported("cuckoo_metadata")),
+            Some(c) => unsafe {
+                Ok(read_parameter_list(c, metadata_bytes))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_get_parameter function, see
+    /// [call_cuckoo_get_parameter](fn.call_cuckoo_get_parameter.html) for details
+
+    pub fn call_cuckoo_get_parameter(&self, name_bytes: &[u8], value:&mut u32) 
+        -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_get_parameter {
+            None => Err(self.unsupported("cuckoo_get_parameter")),
+            Some(c) => unsafe {
+                Ok(c(name_bytes.as_ptr(), name_bytes.len() as u32, value))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_set_parameter function, see
+    /// [call_cuckoo_set_parameter](fn.call_cuckoo_set_parameter.html) for details
+
+    pub fn call_cuckoo_set_parameter(&self, name_bytes: &[u8], value:u32) 
+        -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_set_parameter {
+            None => Err(self.unsupported("cuckoo_set_parameter")),
+            Some(c) => unsafe {
+                Ok(c(name_bytes.as_ptr(), name_bytes.len() as u32, value))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_get_parameter_value function, filling `value_bytes`
+    /// with the parameter's value as JSON (see
+    /// [ParameterValue](../parameter/enum.ParameterValue.html)). Returns 0 on success,
+    /// 1 if the parameter doesn't exist or 3 if the value is too large for the largest
+    /// buffer tried.
+
+    pub fn call_cuckoo_get_parameter_value(&self, name_bytes: &[u8], value_bytes: &mut Vec<u8>)
+        -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_get_parameter_value {
+            None => Err(self.unsupported("cuckoo_get_parameter_value")),
+            Some(c) => unsafe {
+                Ok(read_buffer(value_bytes, |buf, len| {
+                    c(name_bytes.as_ptr(), name_bytes.len() as u32, buf, len)
+                }))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_set_parameter_value function, passing the value
+    /// as JSON (see [ParameterValue](../parameter/enum.ParameterValue.html)). Returns
+    /// 0 on success, 1 if the parameter doesn't exist or 2 if the value is invalid.
+
+    pub fn call_cuckoo_set_parameter_value(&self, name_bytes: &[u8], value_bytes: &[u8])
+        -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_set_parameter_value {
+            None => Err(self.unsupported("cuckoo_set_parameter_value")),
+            Some(c) => unsafe {
+                Ok(c(name_bytes.as_ptr(), name_bytes.len() as u32,
+                     value_bytes.as_ptr(), value_bytes.len() as u32))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_is_queue_under_limit function, see
+    /// [call_cuckoo_is_queue_under_limit](fn.call_cuckoo_is_queue_under_limit.html) for details
+
+    pub fn call_cuckoo_is_queue_under_limit(&self) -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_is_queue_under_limit {
+            None => Err(self.unsupported("cuckoo_is_queue_under_limit")),
+            Some(c) => unsafe {
+                Ok(c())
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_push_to_input_queue function, see
+    /// [call_cuckoo_push_to_input_queue](fn.call_cuckoo_push_to_input_queue.html) for details
+
+    pub fn call_cuckoo_push_to_input_queue(&self, hash: &[u8], nonce:&[u8]) 
+        -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_push_to_input_queue {
+            None => Err(self.unsupported("cuckoo_push_to_input_queue")),
+            Some(c) => unsafe {
+                Ok(c(hash.as_ptr(), hash.len() as u32, nonce.as_ptr()))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_read_from_output_queue function, see
+    /// [call_cuckoo_read_from_output_queue](fn.call_cuckoo_read_from_output_queue.html) for details
+
+    pub fn call_cuckoo_read_from_output_queue(&self, solutions:&mut Vec<u32>, nonce:&mut[u8; 8]) 
+        -> Result<u32, CuckooMinerError> {
+        match self.cuckoo_read_from_output_queue {
+            None => Err(self.unsupported("cuckoo_read_from_output_queue")),
+            Some(c) => unsafe {
+                solutions.resize(self.proof_size as usize, 0);
+                Ok(c(solutions.as_mut_ptr(), nonce.as_mut_ptr()))
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_start_processing function, see
+    /// [call_cuckoo_start_processing](fn.call_cuckoo_start_processing.html) for details
+
+    pub fn call_cuckoo_start_processing(&self) -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_start_processing {
+            None => Err(self.unsupported("cuckoo_start_processing")),
+            Some(c) => unsafe {
+                Ok(c())
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_stop_processing function, see
+    /// [call_cuckoo_stop_processing](fn.call_cuckoo_stop_processing.html) for details
+
+    pub fn call_cuckoo_stop_processing(&self) -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_stop_processing {
+            None => Err(self.unsupported("cuckoo_stop_processing")),
+            Some(c) => unsafe {
+                Ok(c())
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_hashes_since_last_call function, see
+    /// [call_cuckoo_hashes_since_last_call](fn.call_cuckoo_hashes_since_last_call.html) for details
+
+    pub fn call_cuckoo_hashes_since_last_call(&self) -> Result<u32, CuckooMinerError>{
+        match self.cuckoo_hashes_since_last_call {
+            None => Err(self.unsupported("cuckoo_hashes_since_last_call")),
+            Some(c) => unsafe {
+                Ok(c())
+            },
+        }
+    }
+
+    /// Calls the plugin's cuckoo_abort function, asking a cuckoo_call in
+    /// progress on another thread to return as soon as it can. Returns
+    /// immediately, without waiting for the call to return.
+
+    pub fn call_cuckoo_abort(&self) -> Result<(), CuckooMinerError>{
+        match self.cuckoo_abort {
+            None => Err(self.unsupported("cuckoo_abort")),
+            Some(c) => unsafe {
+                c();
+                Ok(())
+            },
+        }
+    }
+}
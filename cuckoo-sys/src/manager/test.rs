@@ -0,0 +1,207 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Tests for loading and unloading plugins via the global plugin functions.
+//! These use the pure Rust plugins built as part of the workspace, so should
+//! be run with `cargo test --all`.
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Read;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+use error::CuckooMinerError;
+use super::*;
+
+// The loaded plugin is global, so tests can't run at the same time
+lazy_static!{
+    static ref TEST_LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn plugin_path(name: &str) -> String {
+    format!("../target/debug/{}{}{}", env::consts::DLL_PREFIX, name, env::consts::DLL_SUFFIX)
+}
+
+fn plugin_name() -> Result<String, CuckooMinerError> {
+    let mut name_bytes = Vec::new();
+    let mut description_bytes = Vec::new();
+    call_cuckoo_description(&mut name_bytes, &mut description_bytes)?;
+    Ok(String::from_utf8(name_bytes)?)
+}
+
+fn assert_not_loaded<T: ::std::fmt::Debug>(result: Result<T, CuckooMinerError>) {
+    match result {
+        Err(CuckooMinerError::PluginNotLoadedError(_)) => {},
+        r => panic!("Expected PluginNotLoadedError, got {:?}", r),
+    }
+}
+
+// Every call should fail once a plugin is unloaded
+
+fn assert_all_unloaded() {
+    let mut solution = Vec::new();
+    let mut nonce = [0; 8];
+    let mut value = 0;
+    let mut param_bytes = Vec::new();
+    assert_not_loaded(call_cuckoo_init());
+    assert_not_loaded(call_cuckoo_proof_size());
+    assert_not_loaded(call_cuckoo(&[0; 32], &mut solution));
+    assert_not_loaded(plugin_name());
+    assert_not_loaded(call_cuckoo_parameter_list(&mut param_bytes));
+    assert_not_loaded(call_cuckoo_get_parameter(b"NUM_THREADS", &mut value));
+    assert_not_loaded(call_cuckoo_set_parameter(b"NUM_THREADS", 1));
+    assert_not_loaded(call_cuckoo_is_queue_under_limit());
+    assert_not_loaded(call_cuckoo_push_to_input_queue(&[0; 32], &nonce));
+    assert_not_loaded(call_cuckoo_read_from_output_queue(&mut solution, &mut nonce));
+    assert_not_loaded(call_cuckoo_start_processing());
+    assert_not_loaded(call_cuckoo_stop_processing());
+    assert_not_loaded(call_cuckoo_hashes_since_last_call());
+}
+
+#[test]
+fn load_unload_reload_cycles() {
+    let _lock = TEST_LOCK.lock().unwrap();
+    unload_cuckoo_lib().unwrap();
+    assert_all_unloaded();
+
+    for _ in 0..5 {
+        load_cuckoo_lib(&plugin_path("cuckoo_rust_simple_16")).unwrap();
+        assert_eq!(plugin_name().unwrap(), "rust_simple_16");
+        unload_cuckoo_lib().unwrap();
+        assert_all_unloaded();
+
+        load_cuckoo_lib(&plugin_path("cuckoo_mock")).unwrap();
+        assert_eq!(plugin_name().unwrap(), "mock");
+        unload_cuckoo_lib().unwrap();
+        assert_all_unloaded();
+    }
+
+    //unloading twice is harmless
+    unload_cuckoo_lib().unwrap();
+}
+
+#[test]
+fn unload_stops_processing() {
+    let _lock = TEST_LOCK.lock().unwrap();
+    load_cuckoo_lib(&plugin_path("cuckoo_rust_simple_16")).unwrap();
+    call_cuckoo_start_processing().unwrap();
+    assert_eq!(call_cuckoo_is_queue_under_limit().unwrap(), 1);
+
+    unload_cuckoo_lib().unwrap();
+    assert_all_unloaded();
+
+    //the plugin should have been told to stop, so won't
+    //accept hashes once reloaded
+    load_cuckoo_lib(&plugin_path("cuckoo_rust_simple_16")).unwrap();
+    assert_eq!(call_cuckoo_is_queue_under_limit().unwrap(), 0);
+    unload_cuckoo_lib().unwrap();
+}
+
+#[test]
+fn load_over_processing_plugin() {
+    let _lock = TEST_LOCK.lock().unwrap();
+    unload_cuckoo_lib().unwrap();
+    load_cuckoo_lib(&plugin_path("cuckoo_rust_simple_16")).unwrap();
+    call_cuckoo_start_processing().unwrap();
+    assert_eq!(call_cuckoo_push_to_input_queue(&[1; 32], &[0; 8]).unwrap(), 1);
+
+    //a plugin which fails its checks leaves the old one loaded and processing
+    env::set_var("CUCKOO_MOCK_CONFIG", "{\"proof_size\": 41}");
+    match load_cuckoo_lib(&plugin_path("cuckoo_mock")) {
+        Err(CuckooMinerError::PluginUnsupportedError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    assert_eq!(plugin_name().unwrap(), "rust_simple_16");
+    assert_eq!(call_cuckoo_is_queue_under_limit().unwrap(), 1);
+
+    //otherwise the old plugin is stopped before it's replaced, so the new
+    //one isn't told to stop processing it never started
+    env::set_var("CUCKOO_MOCK_CONFIG", "{\"stop_processing_delay_ms\": 2000}");
+    load_cuckoo_lib(&plugin_path("cuckoo_mock")).unwrap();
+    assert_eq!(plugin_name().unwrap(), "mock");
+    let start = Instant::now();
+    unload_cuckoo_lib().unwrap();
+    assert!(start.elapsed() < Duration::from_millis(1000));
+    assert_all_unloaded();
+
+    //and the old plugin won't accept hashes once reloaded
+    load_cuckoo_lib(&plugin_path("cuckoo_rust_simple_16")).unwrap();
+    assert_eq!(call_cuckoo_is_queue_under_limit().unwrap(), 0);
+    unload_cuckoo_lib().unwrap();
+    env::remove_var("CUCKOO_MOCK_CONFIG");
+}
+
+#[test]
+fn load_trusted_plugin() {
+    let _lock = TEST_LOCK.lock().unwrap();
+    unload_cuckoo_lib().unwrap();
+    let path = plugin_path("cuckoo_rust_simple_16");
+
+    //refused libraries are never loaded
+    for policy in vec![TrustPolicy::new(), TrustPolicy::new().allow_hash(&"0".repeat(64))] {
+        match load_cuckoo_lib_trusted(&path, &policy) {
+            Err(CuckooMinerError::PluginUntrustedError(_)) => {},
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        assert_all_unloaded();
+    }
+
+    let mut contents = Vec::new();
+    File::open(&path).unwrap().read_to_end(&mut contents).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.input(&contents);
+    let policy = TrustPolicy::new().allow_hash(&hasher.result_str().to_uppercase());
+    load_cuckoo_lib_trusted(&path, &policy).unwrap();
+    assert_eq!(plugin_name().unwrap(), "rust_simple_16");
+    unload_cuckoo_lib().unwrap();
+}
+
+#[test]
+fn trusted_plugin_swapped_after_check() {
+    let _lock = TEST_LOCK.lock().unwrap();
+    unload_cuckoo_lib().unwrap();
+    let dir = env::temp_dir().join(format!("cuckoo_trust_swap_{}", ::std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}cuckoo_rust_simple_16{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX));
+    let path = path.to_str().unwrap();
+    let trusted = fs::read(plugin_path("cuckoo_rust_simple_16")).unwrap();
+    fs::write(path, &trusted).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.input(&trusted);
+    let policy = TrustPolicy::new().allow_hash(&hasher.result_str());
+
+    //the original is replaced once it's been checked, but what's
+    //loaded is still what was checked
+    let verified = policy.open_verified(path).unwrap();
+    fs::write(path, fs::read(plugin_path("cuckoo_mock")).unwrap()).unwrap();
+    assert_eq!(fs::read(verified.load_path()).unwrap(), trusted);
+    let plugin = PluginHandle::load(verified.load_path()).unwrap();
+    let (mut name_bytes, mut description_bytes) = (Vec::new(), Vec::new());
+    plugin.call_cuckoo_description(&mut name_bytes, &mut description_bytes).unwrap();
+    assert_eq!(name_bytes, b"rust_simple_16");
+    drop(plugin);
+    drop(verified);
+
+    //and the replacement itself is refused
+    match PluginHandle::load_trusted(path, &policy) {
+        Err(CuckooMinerError::PluginUntrustedError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r.map(|p| p.full_path().to_owned())),
+    }
+    let _ = fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,244 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plugin parameters, as described by a plugin's `cuckoo_parameter_list`,
+//! and the typed values they take. Values cross the plugin interface as
+//! JSON, via `cuckoo_set_parameter_value` and `cuckoo_get_parameter_value`,
+//! and are checked against the parameter's declared type and range first.
+//!
+//! Plugins which only export `cuckoo_set_parameter` and `cuckoo_get_parameter`
+//! can still be given integer and boolean values, as a plain u32.
+
+use std::fmt;
+
+use serde_json;
+
+use error::CuckooMinerError;
+
+/// The type of value a parameter takes
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParameterType {
+    /// An integer between the parameter's `min_value` and `max_value`
+    Int,
+
+    /// true or false
+    Bool,
+
+    /// One of the strings in the parameter's `values`, e.g. a trimming strategy
+    Enum,
+
+    /// Any string, e.g. a list of devices
+    String,
+
+    /// An integer for each device, in device order, each between the
+    /// parameter's `min_value` and `max_value`
+    PerDevice,
+}
+
+impl Default for ParameterType {
+    fn default() -> ParameterType {
+        ParameterType::Int
+    }
+}
+
+impl fmt::Display for ParameterType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            ParameterType::Int => "int",
+            ParameterType::Bool => "bool",
+            ParameterType::Enum => "enum",
+            ParameterType::String => "string",
+            ParameterType::PerDevice => "per_device",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// A parameter value, represented in JSON as a number, boolean, string or
+/// array of numbers respectively
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParameterValue {
+    /// An integer value, for `int` parameters
+    Int(u32),
+
+    /// A boolean value, for `bool` parameters
+    Bool(bool),
+
+    /// A string value, for `enum` and `string` parameters
+    String(String),
+
+    /// A value per device, for `per_device` parameters
+    PerDevice(Vec<u32>),
+}
+
+impl ParameterValue {
+
+    /// The value as passed to a plugin's `cuckoo_set_parameter`, if it
+    /// can be represented as a u32
+
+    pub fn as_u32(&self) -> Option<u32> {
+        match *self {
+            ParameterValue::Int(v) => Some(v),
+            ParameterValue::Bool(b) => Some(b as u32),
+            _ => None,
+        }
+    }
+
+    /// The value as JSON, as passed to a plugin's `cuckoo_set_parameter_value`
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap_or(String::from("null"))
+    }
+
+    /// Reads a value from JSON, as returned by a plugin's `cuckoo_get_parameter_value`
+
+    pub fn from_json(json: &[u8]) -> Result<ParameterValue, CuckooMinerError> {
+        serde_json::from_slice(json).map_err(|e| CuckooMinerError::PluginJsonError(
+            format!("Invalid parameter value {}: {}", String::from_utf8_lossy(json), e)))
+    }
+}
+
+impl fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_json())
+    }
+}
+
+impl From<u32> for ParameterValue {
+    fn from(value: u32) -> ParameterValue {
+        ParameterValue::Int(value)
+    }
+}
+
+impl From<bool> for ParameterValue {
+    fn from(value: bool) -> ParameterValue {
+        ParameterValue::Bool(value)
+    }
+}
+
+impl<'a> From<&'a str> for ParameterValue {
+    fn from(value: &str) -> ParameterValue {
+        ParameterValue::String(String::from(value))
+    }
+}
+
+impl From<Vec<u32>> for ParameterValue {
+    fn from(value: Vec<u32>) -> ParameterValue {
+        ParameterValue::PerDevice(value)
+    }
+}
+
+fn default_max_value() -> u32 {
+    u32::max_value()
+}
+
+/// A plugin parameter, as listed by the plugin's `cuckoo_parameter_list`,
+/// e.g.
+///
+/// ```
+///   [{"name": "NUM_THREADS", "type": "int", "description": "Number of threads",
+///     "default_value": 1, "min_value": 1, "max_value": 32},
+///    {"name": "TRIM_STRATEGY", "type": "enum", "description": "Trimming strategy",
+///     "default_value": "lean", "values": ["lean", "mean"]},
+///    {"name": "DEVICE_THREADS", "type": "per_device", "description": "Threads per device",
+///     "default_value": [1], "min_value": 1, "max_value": 1024}]
+/// ```
+///
+/// Parameters without a type are integers.
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CuckooPluginParameter {
+    /// The name of the parameter
+    pub name:String,
+
+    /// Description of the parameter
+    pub description:String,
+
+    /// The type of value the parameter takes
+    #[serde(rename = "type", default)]
+    pub param_type: ParameterType,
+
+    /// The default value of the parameter, used if none is provided
+    pub default_value: ParameterValue,
+
+    /// The minimum allowed value for `int` and `per_device` parameters
+    #[serde(default)]
+    pub min_value: u32,
+
+    /// The maximum allowed value for `int` and `per_device` parameters
+    #[serde(default = "default_max_value")]
+    pub max_value: u32,
+
+    /// The allowed values of `enum` parameters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+}
+
+impl CuckooPluginParameter {
+
+    /// #Description
+    ///
+    /// Checks a value against the parameter's type and range.
+    ///
+    /// #Arguments
+    ///
+    /// * `value` (IN) The value to check.
+    ///
+    /// #Returns
+    ///
+    /// Ok if the parameter can be set to the value, otherwise a `ParameterError`
+    /// saying why not.
+    ///
+
+    pub fn validate(&self, value: &ParameterValue) -> Result<(), CuckooMinerError> {
+        let in_range = |v: u32| v >= self.min_value && v <= self.max_value;
+        let valid = match (self.param_type, value) {
+            (ParameterType::Int, &ParameterValue::Int(v)) => in_range(v),
+            (ParameterType::Bool, &ParameterValue::Bool(_)) => true,
+            (ParameterType::Enum, &ParameterValue::String(ref s)) => self.values.contains(s),
+            (ParameterType::String, &ParameterValue::String(_)) => true,
+            (ParameterType::PerDevice, &ParameterValue::PerDevice(ref v)) => {
+                !v.is_empty() && v.iter().all(|v| in_range(*v))
+            },
+            _ => {
+                return Err(CuckooMinerError::ParameterError(format!(
+                    "Error setting parameter: {} to {} - Parameter takes a value of type {}",
+                    self.name, value, self.param_type)));
+            },
+        };
+        if !valid {
+            let allowed = match self.param_type {
+                ParameterType::Enum => format!("one of {}", self.values.join(", ")),
+                _ => format!("{} to {}", self.min_value, self.max_value),
+            };
+            return Err(CuckooMinerError::ParameterError(format!(
+                "Error setting parameter: {} to {} - Property outside allowed range, expected {}",
+                self.name, value, allowed)));
+        }
+        Ok(())
+    }
+}
+
+/// Reads a plugin's parameter list, as returned by `cuckoo_parameter_list`
+
+pub fn parse_parameter_list(param_list_bytes: &[u8]) -> Result<Vec<CuckooPluginParameter>, CuckooMinerError> {
+    let param_list_json = String::from_utf8(param_list_bytes.to_vec())?;
+    serde_json::from_str(&param_list_json).map_err(|e| {
+        CuckooMinerError::PluginJsonError(format!("Invalid parameter list: {}", e))
+    })
+}
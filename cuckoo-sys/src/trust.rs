@@ -0,0 +1,386 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Trust policies, which decide whether a plugin library may be loaded at
+//! all. Loading a plugin runs its native code in this process, so a policy
+//! is checked against the library's contents before it's ever opened.
+//!
+//! A library can be checked against an allowlist of SHA-256 hashes, in the
+//! format written by `sha256sum`, and/or a detached ed25519 signature over
+//! the whole library, read from a file next to it with a `.sig` extension
+//! added, e.g. `libcuckoo_mean_30.so.sig`. The signature file holds the 64
+//! byte signature, either raw or hex encoded.
+//!
+//! A library is opened once and its contents read and checked, after which
+//! [open_verified](struct.TrustPolicy.html#method.open_verified) makes a copy
+//! of exactly those bytes which is private to this process, to be loaded in
+//! place of the original. Swapping or rewriting the original after it's been
+//! checked therefore has no effect on what's loaded. On Linux the copy is held
+//! in memory and loaded via `/proc/self/fd`, elsewhere it's written to a new
+//! directory in the temp directory which only the current user can access.
+//! In-memory copies are kept until the process exits, as the loader knows
+//! them by their `/proc/self/fd` path, which mustn't be reused while a library
+//! loaded from it might still be mapped.
+//!
+//! #Example
+//! ```
+//!  let policy = TrustPolicy::new()
+//!      .load_allowlist("/etc/cuckoo-miner/plugins.sha256")?
+//!      .trust_key_hex("3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c")?;
+//!  load_cuckoo_lib_trusted("/path/to/cuckoo/plugins/cuckoo_simple_30.so", &policy)?;
+//! ```
+
+use std::collections::HashSet;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crypto::digest::Digest;
+use crypto::ed25519;
+use crypto::sha2::Sha256;
+
+use error::CuckooMinerError;
+
+// Extension added to a library's file name to find its signature
+
+const SIGNATURE_EXTENSION: &str = "sig";
+
+// How many private directories to try creating before giving up, in case
+// some names are already taken
+
+const MAX_PRIVATE_DIR_ATTEMPTS: usize = 100;
+
+// Distinguishes the private directories created by this process
+
+static PRIVATE_DIR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Which plugin libraries may be loaded. A library must pass every check the
+/// policy has: its hash must be allowlisted if there's an allowlist, and it
+/// must be signed by a trusted key if there are any keys. A policy with neither
+/// refuses every library.
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustPolicy {
+    allowed_hashes: Option<HashSet<String>>,
+    public_keys: Vec<[u8; 32]>,
+}
+
+impl TrustPolicy {
+
+    /// Returns a policy which refuses every library, until hashes or
+    /// keys are added
+
+    pub fn new() -> TrustPolicy {
+        TrustPolicy::default()
+    }
+
+    /// Allows libraries with the given SHA-256 hash, hex encoded
+
+    pub fn allow_hash(mut self, sha256: &str) -> TrustPolicy {
+        self.allowed_hashes.get_or_insert_with(HashSet::new)
+            .insert(sha256.trim().to_lowercase());
+        self
+    }
+
+    /// #Description
+    ///
+    /// Allows every hash in the given allowlist file. Each line holds a hex
+    /// encoded SHA-256 hash, optionally followed by whitespace and a file name
+    /// as written by `sha256sum`, which is ignored. Blank lines and lines
+    /// starting with `#` are skipped.
+    ///
+    /// #Arguments
+    ///
+    /// * `path` (IN) The path to the allowlist file.
+    ///
+    /// #Returns
+    ///
+    /// Ok with the policy, or a `PluginIOError` if the file can't be read
+    /// or holds a line which isn't a hash.
+    ///
+
+    pub fn load_allowlist(mut self, path: &str) -> Result<TrustPolicy, CuckooMinerError> {
+        let mut contents = String::new();
+        File::open(path).and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| CuckooMinerError::PluginIOError(
+                format!("Unable to read plugin allowlist {}: {}", path, e)))?;
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let hash = line.split_whitespace().next().unwrap_or("");
+            match decode_hex(hash) {
+                Some(ref h) if h.len() == 32 => self = self.allow_hash(hash),
+                _ => return Err(CuckooMinerError::PluginIOError(
+                    format!("Invalid hash on line {} of plugin allowlist {}", i + 1, path))),
+            }
+        }
+        Ok(self)
+    }
+
+    /// Trusts libraries signed by the given ed25519 public key
+
+    pub fn trust_key(mut self, public_key: [u8; 32]) -> TrustPolicy {
+        self.public_keys.push(public_key);
+        self
+    }
+
+    /// Trusts libraries signed by the given hex encoded ed25519 public key,
+    /// returning a `PluginIOError` if it isn't a 32 byte key
+
+    pub fn trust_key_hex(self, public_key: &str) -> Result<TrustPolicy, CuckooMinerError> {
+        match decode_hex(public_key.trim()) {
+            Some(ref k) if k.len() == 32 => {
+                let mut key = [0; 32];
+                key.copy_from_slice(k);
+                Ok(self.trust_key(key))
+            },
+            _ => Err(CuckooMinerError::PluginIOError(
+                format!("Invalid ed25519 public key {}", public_key))),
+        }
+    }
+
+    /// #Description
+    ///
+    /// Checks the library at the given path against the policy, without
+    /// loading it.
+    ///
+    /// #Arguments
+    ///
+    /// * `full_path` (IN) The path to the plugin library.
+    ///
+    /// #Returns
+    ///
+    /// Ok if the library may be loaded, otherwise a `PluginUntrustedError`
+    /// saying why not.
+    ///
+
+    pub fn verify(&self, full_path: &str) -> Result<(), CuckooMinerError> {
+        self.read_verified(full_path).map(|_| ())
+    }
+
+    /// #Description
+    ///
+    /// Checks the library at the given path against the policy, and if it
+    /// passes, makes a private copy of the contents which were checked for
+    /// the library to be loaded from.
+    ///
+    /// #Arguments
+    ///
+    /// * `full_path` (IN) The path to the plugin library.
+    ///
+    /// #Returns
+    ///
+    /// Ok with the copy if the library may be loaded, otherwise a
+    /// `PluginUntrustedError` saying why not, or a `PluginIOError` if the
+    /// copy couldn't be made.
+    ///
+
+    pub fn open_verified(&self, full_path: &str) -> Result<VerifiedLibrary, CuckooMinerError> {
+        let contents = self.read_verified(full_path)?;
+        let mut hasher = Sha256::new();
+        hasher.input(&contents);
+        VerifiedLibrary::new(full_path, &contents, hasher.result_str()).map_err(|e| CuckooMinerError::PluginIOError(
+            format!("Unable to copy verified library {}: {}", full_path, e)))
+    }
+
+    // Reads the library through a single handle, returning its contents
+    // if they pass the policy
+
+    fn read_verified(&self, full_path: &str) -> Result<Vec<u8>, CuckooMinerError> {
+        let untrusted = |reason: String| {
+            CuckooMinerError::PluginUntrustedError(format!("{} - {}", full_path, reason))
+        };
+        if self.allowed_hashes.is_none() && self.public_keys.is_empty() {
+            return Err(untrusted(String::from("trust policy allows no libraries")));
+        }
+        let mut contents = Vec::new();
+        File::open(full_path).and_then(|mut f| f.read_to_end(&mut contents))
+            .map_err(|e| untrusted(format!("unable to read library: {}", e)))?;
+
+        if let Some(ref allowed) = self.allowed_hashes {
+            let mut hasher = Sha256::new();
+            hasher.input(&contents);
+            let hash = hasher.result_str();
+            if !allowed.contains(&hash) {
+                return Err(untrusted(format!("SHA-256 {} isn't allowlisted", hash)));
+            }
+        }
+
+        if !self.public_keys.is_empty() {
+            let signature_path = format!("{}.{}", full_path, SIGNATURE_EXTENSION);
+            let signature = read_signature(&signature_path)
+                .ok_or_else(|| untrusted(format!("no valid signature in {}", signature_path)))?;
+            if !self.public_keys.iter().any(|k| ed25519::verify(&contents, k, &signature)) {
+                return Err(untrusted(String::from("not signed by a trusted key")));
+            }
+        }
+        Ok(contents)
+    }
+}
+
+/// A copy of a plugin library which has passed a [TrustPolicy](struct.TrustPolicy.html),
+/// private to this process, so that what's loaded from its
+/// [load_path](#method.load_path) is exactly what was checked. The copy goes
+/// away when this is dropped, so it must outlive the library loaded from it.
+
+#[derive(Debug)]
+pub struct VerifiedLibrary {
+    load_path: String,
+    hash: String,
+    // The in-memory copy, on Linux
+    memfd: Option<File>,
+    // The directory holding the copy otherwise
+    private_dir: Option<PathBuf>,
+}
+
+impl VerifiedLibrary {
+
+    fn new(full_path: &str, contents: &[u8], hash: String) -> io::Result<VerifiedLibrary> {
+        let mut library = match VerifiedLibrary::in_memory(contents) {
+            Some(l) => l,
+            None => VerifiedLibrary::in_private_dir(full_path, contents)?,
+        };
+        library.hash = hash;
+        Ok(library)
+    }
+
+    /// The path to load the library from
+
+    pub fn load_path(&self) -> &str {
+        &self.load_path
+    }
+
+    /// The SHA-256 hash of the contents which were checked, hex encoded
+
+    pub fn hash(&self) -> &str {
+        &self.hash
+    }
+
+    // Copies the contents to a sealed memfd, which can't be changed once
+    // written, falling back to a file if memfds or /proc aren't available
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn in_memory(contents: &[u8]) -> Option<VerifiedLibrary> {
+        use std::os::unix::io::FromRawFd;
+        use libc;
+
+        if !Path::new("/proc/self/fd").is_dir() {
+            return None;
+        }
+        let fd = unsafe {
+            libc::memfd_create(b"cuckoo_plugin\0".as_ptr() as *const libc::c_char,
+                               libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING)
+        };
+        if fd < 0 {
+            return None;
+        }
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        file.write_all(contents).ok()?;
+        let seals = libc::F_SEAL_SEAL | libc::F_SEAL_SHRINK | libc::F_SEAL_GROW | libc::F_SEAL_WRITE;
+        if unsafe { libc::fcntl(fd, libc::F_ADD_SEALS, seals) } < 0 {
+            return None;
+        }
+        Some(VerifiedLibrary {
+            load_path: format!("/proc/self/fd/{}", fd),
+            hash: String::new(),
+            memfd: Some(file),
+            private_dir: None,
+        })
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn in_memory(_contents: &[u8]) -> Option<VerifiedLibrary> {
+        None
+    }
+
+    // Copies the contents to a file in a newly created directory which
+    // only the current user can access
+
+    fn in_private_dir(full_path: &str, contents: &[u8]) -> io::Result<VerifiedLibrary> {
+        let file_name = Path::new(full_path).file_name()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no file name"))?;
+        let mut attempts = 0;
+        let dir = loop {
+            let dir = env::temp_dir().join(format!("cuckoo_plugin_{}_{}", process::id(),
+                                                   PRIVATE_DIR_COUNT.fetch_add(1, Ordering::SeqCst)));
+            match create_private_dir(&dir) {
+                Ok(_) => break dir,
+                Err(ref e) if e.kind() == io::ErrorKind::AlreadyExists &&
+                              attempts < MAX_PRIVATE_DIR_ATTEMPTS => attempts += 1,
+                Err(e) => return Err(e),
+            }
+        };
+        let library = VerifiedLibrary {
+            load_path: dir.join(file_name).to_string_lossy().into_owned(),
+            hash: String::new(),
+            memfd: None,
+            private_dir: Some(dir),
+        };
+        OpenOptions::new().write(true).create_new(true).open(&library.load_path)
+            .and_then(|mut f| f.write_all(contents))?;
+        Ok(library)
+    }
+}
+
+impl Drop for VerifiedLibrary {
+    fn drop(&mut self) {
+        //the loader may not unload the library, and would return it again
+        //for any later library loaded via the same fd number
+        if let Some(f) = self.memfd.take() {
+            mem::forget(f);
+        }
+        if let Some(ref dir) = self.private_dir {
+            let _ = fs::remove_dir_all(dir);
+        }
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new().mode(0o700).create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir(dir)
+}
+
+// Reads a 64 byte signature, stored either raw or hex encoded
+
+fn read_signature(path: &str) -> Option<Vec<u8>> {
+    let mut contents = Vec::new();
+    File::open(path).and_then(|mut f| f.read_to_end(&mut contents)).ok()?;
+    if contents.len() == 64 {
+        return Some(contents);
+    }
+    String::from_utf8(contents).ok()
+        .and_then(|s| decode_hex(s.trim()))
+        .and_then(|s| if s.len() == 64 { Some(s) } else { None })
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|b| u8::from_str_radix(b, 16).ok()))
+        .collect()
+}
@@ -0,0 +1,873 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Out-of-process plugin execution. Rather than loading a plugin straight
+//! into the calling process, a [PluginWorker](struct.PluginWorker.html) spawns
+//! a `cuckoo_plugin_worker` subprocess which loads the plugin, and forwards
+//! each plugin call to it over the subprocess's stdin and stdout. If the plugin
+//! crashes, only the worker dies, and every call to the worker from then on
+//! returns a `PluginCrashError`. A worker can also be restarted, abandoning
+//! whatever call it's in the middle of. Anything the plugin itself writes to
+//! stdout ends up on the worker's stderr, so it can't get mixed up with the
+//! worker's responses.
+//!
+//! The protocol is a simple request/response exchange of frames, each consisting
+//! of two little-endian u32s followed by a payload. Requests contain an opcode
+//! and the payload length, responses contain a status (0 for success, 1 for an
+//! error) and the payload length. Error payloads contain an error kind followed
+//! by a UTF-8 message.
+
+use std::env;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::Mutex;
+
+use serde_json;
+
+use error::CuckooMinerError;
+use manager::{PluginHandle, PluginCapabilities, InUseGuard};
+use trust::TrustPolicy;
+
+/// The name of the worker executable, without any platform suffix
+pub const WORKER_EXECUTABLE: &str = "cuckoo_plugin_worker";
+
+/// Environment variable which can be used to override the location of
+/// the worker executable
+pub const WORKER_PATH_ENV_VAR: &str = "CUCKOO_PLUGIN_WORKER";
+
+// Request opcodes
+
+const OP_LOAD: u32 = 1;
+const OP_CALL: u32 = 2;
+const OP_DESCRIPTION: u32 = 3;
+const OP_PARAMETER_LIST: u32 = 4;
+const OP_GET_PARAMETER: u32 = 5;
+const OP_SET_PARAMETER: u32 = 6;
+const OP_IS_QUEUE_UNDER_LIMIT: u32 = 7;
+const OP_PUSH_TO_INPUT_QUEUE: u32 = 8;
+const OP_READ_FROM_OUTPUT_QUEUE: u32 = 9;
+const OP_START_PROCESSING: u32 = 10;
+const OP_STOP_PROCESSING: u32 = 11;
+const OP_HASHES_SINCE_LAST_CALL: u32 = 12;
+const OP_GET_PARAMETER_VALUE: u32 = 13;
+const OP_SET_PARAMETER_VALUE: u32 = 14;
+const OP_CALL_ALL: u32 = 15;
+const OP_LOAD_TRUSTED: u32 = 16;
+
+// Response statuses
+
+const STATUS_OK: u32 = 0;
+const STATUS_ERROR: u32 = 1;
+
+// Error kinds, so the parent can rebuild the same error variant
+
+const ERROR_NOT_LOADED: u32 = 1;
+const ERROR_NOT_FOUND: u32 = 2;
+const ERROR_UNSUPPORTED: u32 = 3;
+const ERROR_ABI_VERSION: u32 = 4;
+const ERROR_OTHER: u32 = 5;
+const ERROR_SYMBOL: u32 = 6;
+const ERROR_UNTRUSTED: u32 = 7;
+
+// Frame helpers
+
+fn write_frame<W: Write>(out: &mut W, code: u32, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(8 + payload.len());
+    put_u32(&mut frame, code);
+    put_u32(&mut frame, payload.len() as u32);
+    frame.extend_from_slice(payload);
+    out.write_all(&frame)?;
+    out.flush()
+}
+
+// Returns None on a clean end of stream before a new frame
+
+fn read_frame<R: Read>(input: &mut R) -> io::Result<Option<(u32, Vec<u8>)>> {
+    let mut header = [0u8; 8];
+    let mut read = 0;
+    while read < header.len() {
+        let n = input.read(&mut header[read..])?;
+        if n == 0 {
+            if read == 0 {
+                return Ok(None);
+            }
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Truncated frame header"));
+        }
+        read += n;
+    }
+    let code = get_u32(&header, 0);
+    let len = get_u32(&header, 4) as usize;
+    let mut payload = vec![0u8; len];
+    input.read_exact(&mut payload)?;
+    Ok(Some((code, payload)))
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn get_u32(buf: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+// Reads a u32 from a payload, checking its length
+
+fn payload_u32(payload: &[u8], offset: usize) -> Result<u32, CuckooMinerError> {
+    if payload.len() < offset + 4 {
+        return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+    }
+    Ok(get_u32(payload, offset))
+}
+
+fn encode_error(error: &CuckooMinerError) -> Vec<u8> {
+    let (kind, message) = match *error {
+        CuckooMinerError::PluginNotLoadedError(ref s) => (ERROR_NOT_LOADED, s.clone()),
+        CuckooMinerError::PluginNotFoundError(ref s) => (ERROR_NOT_FOUND, s.clone()),
+        CuckooMinerError::PluginUnsupportedError(ref s) => (ERROR_UNSUPPORTED, s.clone()),
+        CuckooMinerError::PluginAbiVersionError(ref s) => (ERROR_ABI_VERSION, s.clone()),
+        CuckooMinerError::PluginSymbolError(ref s) => (ERROR_SYMBOL, s.clone()),
+        CuckooMinerError::PluginUntrustedError(ref s) => (ERROR_UNTRUSTED, s.clone()),
+        ref e => (ERROR_OTHER, format!("{:?}", e)),
+    };
+    let mut payload = Vec::new();
+    put_u32(&mut payload, kind);
+    payload.extend_from_slice(message.as_bytes());
+    payload
+}
+
+fn decode_error(payload: &[u8]) -> CuckooMinerError {
+    let kind = match payload_u32(payload, 0) {
+        Ok(k) => k,
+        Err(e) => return e,
+    };
+    let message = String::from_utf8_lossy(&payload[4..]).into_owned();
+    match kind {
+        ERROR_NOT_LOADED => CuckooMinerError::PluginNotLoadedError(message),
+        ERROR_NOT_FOUND => CuckooMinerError::PluginNotFoundError(message),
+        ERROR_UNSUPPORTED => CuckooMinerError::PluginUnsupportedError(message),
+        ERROR_ABI_VERSION => CuckooMinerError::PluginAbiVersionError(message),
+        ERROR_SYMBOL => CuckooMinerError::PluginSymbolError(message),
+        ERROR_UNTRUSTED => CuckooMinerError::PluginUntrustedError(message),
+        _ => CuckooMinerError::PluginProcessingError(message),
+    }
+}
+
+/// #Description
+///
+/// Returns the default location of the worker executable. This is the value
+/// of the `CUCKOO_PLUGIN_WORKER` environment variable if set, otherwise
+/// `cuckoo_plugin_worker` in the same directory as the current executable,
+/// or its parent directory (to cover test executables in cargo's `deps` dir).
+///
+
+pub fn default_worker_path() -> Option<PathBuf> {
+    if let Ok(p) = env::var(WORKER_PATH_ENV_VAR) {
+        return Some(PathBuf::from(p));
+    }
+    let file_name = format!("{}{}", WORKER_EXECUTABLE, env::consts::EXE_SUFFIX);
+    let exe = match env::current_exe() {
+        Ok(e) => e,
+        Err(_) => return None,
+    };
+    let dir = match exe.parent() {
+        Some(d) => d,
+        None => return None,
+    };
+    let candidate = dir.join(&file_name);
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    if dir.ends_with("deps") {
+        if let Some(parent) = dir.parent() {
+            let candidate = parent.join(&file_name);
+            if candidate.is_file() {
+                return Some(candidate);
+            }
+        }
+    }
+    None
+}
+
+// The subprocess's pipes, only ever accessed while locked so that
+// requests and responses can't interleave
+
+struct WorkerProcess {
+    stdin: ChildStdin,
+    stdout: ChildStdout,
+    // Set once the worker has died, returned for every later call
+    crashed: Option<String>,
+}
+
+/// #Description
+///
+/// A plugin running in a separate worker subprocess. Provides the same
+/// set of calls as a [PluginHandle](../manager/struct.PluginHandle.html), each
+/// of which is forwarded to the worker. If the worker process dies, e.g.
+/// because of a segfault within the plugin, the call in progress and all
+/// subsequent calls return a `PluginCrashError` rather than taking down
+/// the calling process. The worker is killed when this is dropped.
+///
+/// #Example
+///
+/// ```
+///  let worker_path = default_worker_path().unwrap();
+///  let plugin = PluginWorker::spawn(&worker_path, "/path/to/cuckoo_simple_30.so")?;
+///  let mut solution = Vec::new();
+///  match plugin.call_cuckoo(&header, &mut solution) {
+///      Err(CuckooMinerError::PluginCrashError(e)) => println!("Plugin crashed: {}", e),
+///      ...
+///  }
+/// ```
+///
+
+pub struct PluginWorker {
+    full_path: String,
+    worker_path: PathBuf,
+    abi_version: u32,
+    proof_size: u32,
+    capabilities: PluginCapabilities,
+    // Locked separately from the pipes, so the worker can be killed
+    // while a request is waiting on it
+    child: Mutex<Child>,
+    process: Mutex<WorkerProcess>,
+    // Parameter requests which succeeded, by parameter name, to repeat
+    // on restart
+    parameters: Mutex<Vec<(Vec<u8>, u32, Vec<u8>)>>,
+    // The policy the worker checks the plugin against before loading it
+    trust_policy: Option<TrustPolicy>,
+    // The hash of the library contents the worker checked and loaded
+    verified_hash: Option<String>,
+    _in_use: InUseGuard,
+}
+
+impl PluginWorker {
+
+    /// #Description
+    ///
+    /// Spawns a worker process and has it load the plugin at the given path.
+    ///
+    /// #Arguments
+    ///
+    /// * `worker_path` The path to the `cuckoo_plugin_worker` executable
+    ///
+    /// * `full_path` The full path to the plugin library .so/.dylib
+    ///
+    /// #Returns
+    ///
+    /// Ok(PluginWorker) once the worker has successfully loaded the plugin, or
+    /// a [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) with
+    /// the reason the worker couldn't be started or the plugin couldn't be loaded.
+    ///
+
+    pub fn spawn<P: AsRef<Path>>(worker_path: P, full_path: &str) -> Result<PluginWorker, CuckooMinerError> {
+        PluginWorker::spawn_with_policy(worker_path.as_ref(), full_path, None)
+    }
+
+    /// As [spawn](#method.spawn), but the worker checks the plugin against the given
+    /// [TrustPolicy](../trust/struct.TrustPolicy.html) and loads it as
+    /// [PluginHandle::load_trusted](../manager/struct.PluginHandle.html#method.load_trusted)
+    /// does, returning a `PluginUntrustedError` if the policy refuses it. The
+    /// check is repeated whenever the worker is restarted.
+
+    pub fn spawn_trusted<P: AsRef<Path>>(worker_path: P, full_path: &str, trust_policy: &TrustPolicy)
+        -> Result<PluginWorker, CuckooMinerError> {
+        PluginWorker::spawn_with_policy(worker_path.as_ref(), full_path, Some(trust_policy.clone()))
+    }
+
+    fn spawn_with_policy(worker_path: &Path, full_path: &str, trust_policy: Option<TrustPolicy>)
+        -> Result<PluginWorker, CuckooMinerError> {
+        let (child, process) = start_process(worker_path, full_path)?;
+        let mut worker = PluginWorker {
+            full_path: String::from(full_path),
+            worker_path: worker_path.to_path_buf(),
+            abi_version: 0,
+            proof_size: 0,
+            capabilities: PluginCapabilities::empty(),
+            child: Mutex::new(child),
+            process: Mutex::new(process),
+            parameters: Mutex::new(Vec::new()),
+            trust_policy: trust_policy,
+            verified_hash: None,
+            _in_use: InUseGuard::new(full_path),
+        };
+        let response = worker.load()?;
+        worker.abi_version = payload_u32(&response, 0)?;
+        worker.capabilities = PluginCapabilities::from_bits(payload_u32(&response, 4)?);
+        worker.proof_size = payload_u32(&response, 8)?;
+        worker.verified_hash = load_response_hash(&response)?;
+        Ok(worker)
+    }
+
+    /// #Description
+    ///
+    /// Kills the worker process and starts a new one with the plugin loaded
+    /// again, and any parameters successfully set on the old worker set again.
+    /// A call in progress on another thread is abandoned, and returns a
+    /// `PluginCrashError`. Can be called from any thread, and also brings
+    /// back a worker which has crashed.
+    ///
+    /// #Returns
+    ///
+    /// Ok once the new worker has loaded the plugin, or a
+    /// [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) with
+    /// the reason it couldn't be started, in which case every call returns
+    /// a `PluginCrashError`.
+    ///
+
+    pub fn restart(&self) -> Result<(), CuckooMinerError> {
+        debug!("Restarting plugin worker for {}", self.full_path);
+        {
+            //a request waiting on the worker sees its pipes close
+            let mut child = self.child.lock().unwrap();
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let mut process = self.process.lock().unwrap();
+        match start_process(&self.worker_path, &self.full_path) {
+            Ok((child, p)) => {
+                *self.child.lock().unwrap() = child;
+                *process = p;
+            },
+            Err(e) => {
+                process.crashed = Some(format!("Unable to restart plugin worker for {}: {:?}", self.full_path, e));
+                return Err(e);
+            },
+        }
+        drop(process);
+        let response = self.load()?;
+        if PluginCapabilities::from_bits(payload_u32(&response, 4)?) != self.capabilities ||
+           payload_u32(&response, 8)? != self.proof_size ||
+           load_response_hash(&response)? != self.verified_hash {
+            return Err(CuckooMinerError::PluginIOError(format!(
+                "Plugin {} changed while its worker was restarted", self.full_path)));
+        }
+        for &(_, opcode, ref payload) in self.parameters.lock().unwrap().iter() {
+            self.request(opcode, payload)?;
+        }
+        Ok(())
+    }
+
+    // Has the worker load the plugin, checking it against the trust
+    // policy if there is one
+
+    fn load(&self) -> Result<Vec<u8>, CuckooMinerError> {
+        match self.trust_policy {
+            Some(ref p) => {
+                let policy = serde_json::to_vec(p).map_err(|e| CuckooMinerError::PluginIOError(
+                    format!("Unable to encode trust policy: {}", e)))?;
+                let mut payload = Vec::new();
+                put_u32(&mut payload, policy.len() as u32);
+                payload.extend_from_slice(&policy);
+                payload.extend_from_slice(self.full_path.as_bytes());
+                self.request(OP_LOAD_TRUSTED, &payload)
+            },
+            None => self.request(OP_LOAD, self.full_path.as_bytes()),
+        }
+    }
+
+    // Remembers a parameter request which succeeded, replacing any earlier
+    // one for the same parameter
+
+    fn record_parameter(&self, opcode: u32, name_bytes: &[u8], payload: Vec<u8>) {
+        let mut parameters = self.parameters.lock().unwrap();
+        parameters.retain(|&(ref n, _, _)| n.as_slice() != name_bytes);
+        parameters.push((name_bytes.to_vec(), opcode, payload));
+    }
+
+    /// Returns the full path of the library the worker has loaded
+
+    pub fn full_path(&self) -> &str {
+        &self.full_path
+    }
+
+    /// Returns the version of the plugin interface the plugin implements
+
+    pub fn abi_version(&self) -> u32 {
+        self.abi_version
+    }
+
+    /// Returns the number of nonces in each solution the plugin finds
+
+    pub fn proof_size(&self) -> u32 {
+        self.proof_size
+    }
+
+    /// Returns the set of capabilities supported by the plugin
+
+    pub fn capabilities(&self) -> PluginCapabilities {
+        self.capabilities
+    }
+
+    /// Returns the SHA-256 hash of the library contents the worker checked
+    /// against the trust policy and loaded, hex encoded, if the worker was
+    /// spawned via [spawn_trusted](#method.spawn_trusted)
+
+    pub fn verified_hash(&self) -> Option<&str> {
+        self.verified_hash.as_deref()
+    }
+
+    /// Returns the process id of the worker
+
+    pub fn id(&self) -> u32 {
+        self.child.lock().unwrap().id()
+    }
+
+    // Sends a request and waits for its response, translating a dead
+    // worker into a PluginCrashError
+
+    fn request(&self, opcode: u32, payload: &[u8]) -> Result<Vec<u8>, CuckooMinerError> {
+        let mut process = self.process.lock().unwrap();
+        if let Some(ref reason) = process.crashed {
+            return Err(CuckooMinerError::PluginCrashError(reason.clone()));
+        }
+        let result = {
+            let p = &mut *process;
+            match write_frame(&mut p.stdin, opcode, payload) {
+                Ok(_) => read_frame(&mut p.stdout),
+                Err(e) => Err(e),
+            }
+        };
+        match result {
+            Ok(Some((STATUS_OK, response))) => Ok(response),
+            Ok(Some((STATUS_ERROR, response))) => Err(decode_error(&response)),
+            Ok(Some((status, _))) => Err(CuckooMinerError::UnexpectedResultError(status)),
+            Ok(None) | Err(_) => {
+                // Pipe closed, so the worker has gone away
+                let status = match self.child.lock().unwrap().wait() {
+                    Ok(s) => format!("{}", s),
+                    Err(e) => format!("unknown status ({})", e),
+                };
+                let reason = format!("Plugin worker for {} died: {}", self.full_path, status);
+                error!("{}", reason);
+                process.crashed = Some(reason.clone());
+                Err(CuckooMinerError::PluginCrashError(reason))
+            }
+        }
+    }
+
+    // Reads the plugin's proof size worth of solution nonces from a response
+
+    fn read_solution(&self, response: &[u8], offset: usize, solutions: &mut Vec<u32>)
+        -> Result<(), CuckooMinerError> {
+        solutions.resize(self.proof_size as usize, 0);
+        for i in 0..solutions.len() {
+            solutions[i] = payload_u32(response, offset + i * 4)?;
+        }
+        Ok(())
+    }
+
+    /// Forwards to the plugin's cuckoo_call function, see
+    /// [call_cuckoo](../manager/fn.call_cuckoo.html) for details
+
+    pub fn call_cuckoo(&self, header: &[u8], solutions:&mut Vec<u32>) -> Result<u32, CuckooMinerError> {
+        let response = self.request(OP_CALL, header)?;
+        self.read_solution(&response, 4, solutions)?;
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_call_all function, see
+    /// [PluginHandle::call_cuckoo_all](../manager/struct.PluginHandle.html#method.call_cuckoo_all)
+    /// for details
+
+    pub fn call_cuckoo_all(&self, header: &[u8], solutions: &mut Vec<Vec<u32>>)
+        -> Result<u32, CuckooMinerError> {
+        let response = self.request(OP_CALL_ALL, header)?;
+        let count = payload_u32(&response, 0)?;
+        let solution_len = self.proof_size as usize * 4;
+        solutions.clear();
+        for i in 0..count as usize {
+            let mut solution = Vec::new();
+            self.read_solution(&response, 4 + i * solution_len, &mut solution)?;
+            solutions.push(solution);
+        }
+        Ok(count)
+    }
+
+    /// Forwards to the plugin's cuckoo_description function, see
+    /// [call_cuckoo_description](../manager/fn.call_cuckoo_description.html) for details
+
+    pub fn call_cuckoo_description(&self, name_bytes: &mut Vec<u8>, description_bytes: &mut Vec<u8>)
+        -> Result<(), CuckooMinerError>{
+        let response = self.request(OP_DESCRIPTION, &[])?;
+        let name_len = payload_u32(&response, 0)? as usize;
+        let desc_len = payload_u32(&response, 4 + name_len)? as usize;
+        if response.len() < 8 + name_len + desc_len {
+            return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+        }
+        *name_bytes = response[4..4 + name_len].to_vec();
+        *description_bytes = response[8 + name_len..8 + name_len + desc_len].to_vec();
+        Ok(())
+    }
+
+    /// Forwards to the plugin's cuckoo_parameter_list function, see
+    /// [call_cuckoo_parameter_list](../manager/fn.call_cuckoo_parameter_list.html) for details
+
+    pub fn call_cuckoo_parameter_list(&self, param_list_bytes: &mut Vec<u8>)
+        -> Result<u32, CuckooMinerError>{
+        let response = self.request(OP_PARAMETER_LIST, &[])?;
+        let len = payload_u32(&response, 4)? as usize;
+        if response.len() < 8 + len {
+            return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+        }
+        *param_list_bytes = response[8..8 + len].to_vec();
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_get_parameter function, see
+    /// [call_cuckoo_get_parameter](../manager/fn.call_cuckoo_get_parameter.html) for details
+
+    pub fn call_cuckoo_get_parameter(&self, name_bytes: &[u8], value:&mut u32)
+        -> Result<u32, CuckooMinerError>{
+        let response = self.request(OP_GET_PARAMETER, name_bytes)?;
+        *value = payload_u32(&response, 4)?;
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_set_parameter function, see
+    /// [call_cuckoo_set_parameter](../manager/fn.call_cuckoo_set_parameter.html) for details
+
+    pub fn call_cuckoo_set_parameter(&self, name_bytes: &[u8], value:u32)
+        -> Result<u32, CuckooMinerError>{
+        let mut payload = Vec::new();
+        put_u32(&mut payload, value);
+        payload.extend_from_slice(name_bytes);
+        let response = self.request(OP_SET_PARAMETER, &payload)?;
+        let result = payload_u32(&response, 0)?;
+        if result == 0 {
+            self.record_parameter(OP_SET_PARAMETER, name_bytes, payload);
+        }
+        Ok(result)
+    }
+
+    /// Forwards to the plugin's cuckoo_get_parameter_value function, see
+    /// [PluginHandle::call_cuckoo_get_parameter_value](../manager/struct.PluginHandle.html#method.call_cuckoo_get_parameter_value)
+    /// for details
+
+    pub fn call_cuckoo_get_parameter_value(&self, name_bytes: &[u8], value_bytes: &mut Vec<u8>)
+        -> Result<u32, CuckooMinerError>{
+        let response = self.request(OP_GET_PARAMETER_VALUE, name_bytes)?;
+        let len = payload_u32(&response, 4)? as usize;
+        if response.len() < 8 + len {
+            return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+        }
+        *value_bytes = response[8..8 + len].to_vec();
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_set_parameter_value function, see
+    /// [PluginHandle::call_cuckoo_set_parameter_value](../manager/struct.PluginHandle.html#method.call_cuckoo_set_parameter_value)
+    /// for details
+
+    pub fn call_cuckoo_set_parameter_value(&self, name_bytes: &[u8], value_bytes: &[u8])
+        -> Result<u32, CuckooMinerError>{
+        let mut payload = Vec::new();
+        put_u32(&mut payload, name_bytes.len() as u32);
+        payload.extend_from_slice(name_bytes);
+        payload.extend_from_slice(value_bytes);
+        let response = self.request(OP_SET_PARAMETER_VALUE, &payload)?;
+        let result = payload_u32(&response, 0)?;
+        if result == 0 {
+            self.record_parameter(OP_SET_PARAMETER_VALUE, name_bytes, payload);
+        }
+        Ok(result)
+    }
+
+    /// Forwards to the plugin's cuckoo_is_queue_under_limit function, see
+    /// [call_cuckoo_is_queue_under_limit](../manager/fn.call_cuckoo_is_queue_under_limit.html) for details
+
+    pub fn call_cuckoo_is_queue_under_limit(&self) -> Result<u32, CuckooMinerError>{
+        let response = self.request(OP_IS_QUEUE_UNDER_LIMIT, &[])?;
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_push_to_input_queue function, see
+    /// [call_cuckoo_push_to_input_queue](../manager/fn.call_cuckoo_push_to_input_queue.html) for details.
+    /// Returns a `PluginProcessingError` if `nonce` is shorter than 8 bytes.
+
+    pub fn call_cuckoo_push_to_input_queue(&self, hash: &[u8], nonce:&[u8])
+        -> Result<u32, CuckooMinerError>{
+        if nonce.len() < 8 {
+            return Err(CuckooMinerError::PluginProcessingError(format!(
+                "Nonce must be 8 bytes, but is {}", nonce.len())));
+        }
+        let mut payload = Vec::with_capacity(8 + hash.len());
+        payload.extend_from_slice(&nonce[..8]);
+        payload.extend_from_slice(hash);
+        let response = self.request(OP_PUSH_TO_INPUT_QUEUE, &payload)?;
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_read_from_output_queue function, see
+    /// [call_cuckoo_read_from_output_queue](../manager/fn.call_cuckoo_read_from_output_queue.html) for details
+
+    pub fn call_cuckoo_read_from_output_queue(&self, solutions:&mut Vec<u32>, nonce:&mut[u8; 8])
+        -> Result<u32, CuckooMinerError> {
+        let response = self.request(OP_READ_FROM_OUTPUT_QUEUE, &[])?;
+        let result = payload_u32(&response, 0)?;
+        if result != 0 {
+            self.read_solution(&response, 4, solutions)?;
+            let offset = 4 + solutions.len() * 4;
+            if response.len() < offset + 8 {
+                return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+            }
+            nonce.copy_from_slice(&response[offset..offset + 8]);
+        }
+        Ok(result)
+    }
+
+    /// Forwards to the plugin's cuckoo_start_processing function, see
+    /// [call_cuckoo_start_processing](../manager/fn.call_cuckoo_start_processing.html) for details
+
+    pub fn call_cuckoo_start_processing(&self) -> Result<u32, CuckooMinerError>{
+        let response = self.request(OP_START_PROCESSING, &[])?;
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_stop_processing function, see
+    /// [call_cuckoo_stop_processing](../manager/fn.call_cuckoo_stop_processing.html) for details
+
+    pub fn call_cuckoo_stop_processing(&self) -> Result<u32, CuckooMinerError>{
+        let response = self.request(OP_STOP_PROCESSING, &[])?;
+        payload_u32(&response, 0)
+    }
+
+    /// Forwards to the plugin's cuckoo_hashes_since_last_call function, see
+    /// [call_cuckoo_hashes_since_last_call](../manager/fn.call_cuckoo_hashes_since_last_call.html) for details
+
+    pub fn call_cuckoo_hashes_since_last_call(&self) -> Result<u32, CuckooMinerError>{
+        let response = self.request(OP_HASHES_SINCE_LAST_CALL, &[])?;
+        payload_u32(&response, 0)
+    }
+}
+
+impl Drop for PluginWorker {
+    fn drop(&mut self) {
+        let process = self.process.lock().unwrap();
+        if process.crashed.is_none() {
+            let mut child = self.child.lock().unwrap();
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+// Starts a worker process, without loading anything into it yet
+
+fn start_process(worker_path: &Path, full_path: &str) -> Result<(Child, WorkerProcess), CuckooMinerError> {
+    debug!("Spawning plugin worker {} for {}", worker_path.display(), full_path);
+    let mut child = match Command::new(worker_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn() {
+        Ok(c) => c,
+        Err(e) => {
+            return Err(CuckooMinerError::PluginIOError(format!(
+                "Error starting plugin worker {}: {}", worker_path.display(), e)));
+        }
+    };
+    let stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    Ok((child, WorkerProcess {
+        stdin: stdin,
+        stdout: stdout,
+        crashed: None,
+    }))
+}
+
+// Worker side
+
+// Performs a single request against the loaded plugin, returning the
+// response payload
+
+fn handle_request(plugin: &PluginHandle, opcode: u32, payload: &[u8])
+    -> Result<Vec<u8>, CuckooMinerError> {
+    let mut response = Vec::new();
+    match opcode {
+        OP_CALL => {
+            let mut solutions = Vec::new();
+            let result = plugin.call_cuckoo(payload, &mut solutions)?;
+            put_u32(&mut response, result);
+            for s in solutions.iter() {
+                put_u32(&mut response, *s);
+            }
+        },
+        OP_CALL_ALL => {
+            let mut solutions = Vec::new();
+            let result = plugin.call_cuckoo_all(payload, &mut solutions)?;
+            put_u32(&mut response, result);
+            for s in solutions.iter().flat_map(|s| s.iter()) {
+                put_u32(&mut response, *s);
+            }
+        },
+        OP_DESCRIPTION => {
+            let mut name_bytes = Vec::new();
+            let mut description_bytes = Vec::new();
+            plugin.call_cuckoo_description(&mut name_bytes, &mut description_bytes)?;
+            put_u32(&mut response, name_bytes.len() as u32);
+            response.extend_from_slice(&name_bytes);
+            put_u32(&mut response, description_bytes.len() as u32);
+            response.extend_from_slice(&description_bytes);
+        },
+        OP_PARAMETER_LIST => {
+            let mut param_list_bytes = Vec::new();
+            let result = plugin.call_cuckoo_parameter_list(&mut param_list_bytes)?;
+            put_u32(&mut response, result);
+            put_u32(&mut response, param_list_bytes.len() as u32);
+            response.extend_from_slice(&param_list_bytes);
+        },
+        OP_GET_PARAMETER => {
+            let mut value = 0;
+            let result = plugin.call_cuckoo_get_parameter(payload, &mut value)?;
+            put_u32(&mut response, result);
+            put_u32(&mut response, value);
+        },
+        OP_SET_PARAMETER => {
+            let value = payload_u32(payload, 0)?;
+            put_u32(&mut response, plugin.call_cuckoo_set_parameter(&payload[4..], value)?);
+        },
+        OP_GET_PARAMETER_VALUE => {
+            let mut value_bytes = Vec::new();
+            let result = plugin.call_cuckoo_get_parameter_value(payload, &mut value_bytes)?;
+            put_u32(&mut response, result);
+            put_u32(&mut response, value_bytes.len() as u32);
+            response.extend_from_slice(&value_bytes);
+        },
+        OP_SET_PARAMETER_VALUE => {
+            let name_len = payload_u32(payload, 0)? as usize;
+            if payload.len() < 4 + name_len {
+                return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+            }
+            let (name_bytes, value_bytes) = payload[4..].split_at(name_len);
+            put_u32(&mut response, plugin.call_cuckoo_set_parameter_value(name_bytes, value_bytes)?);
+        },
+        OP_IS_QUEUE_UNDER_LIMIT => {
+            put_u32(&mut response, plugin.call_cuckoo_is_queue_under_limit()?);
+        },
+        OP_PUSH_TO_INPUT_QUEUE => {
+            if payload.len() < 8 {
+                return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+            }
+            put_u32(&mut response, plugin.call_cuckoo_push_to_input_queue(&payload[8..], &payload[..8])?);
+        },
+        OP_READ_FROM_OUTPUT_QUEUE => {
+            let mut solutions = Vec::new();
+            let mut nonce = [0u8; 8];
+            let result = plugin.call_cuckoo_read_from_output_queue(&mut solutions, &mut nonce)?;
+            put_u32(&mut response, result);
+            if result != 0 {
+                for s in solutions.iter() {
+                    put_u32(&mut response, *s);
+                }
+                response.extend_from_slice(&nonce);
+            }
+        },
+        OP_START_PROCESSING => {
+            put_u32(&mut response, plugin.call_cuckoo_start_processing()?);
+        },
+        OP_STOP_PROCESSING => {
+            put_u32(&mut response, plugin.call_cuckoo_stop_processing()?);
+        },
+        OP_HASHES_SINCE_LAST_CALL => {
+            put_u32(&mut response, plugin.call_cuckoo_hashes_since_last_call()?);
+        },
+        _ => {
+            return Err(CuckooMinerError::PluginProcessingError(format!("Unknown worker request {}", opcode)));
+        }
+    }
+    Ok(response)
+}
+
+// Reads the hash of the verified library from the end of a load
+// response, which is empty unless the plugin was loaded trusted
+
+fn load_response_hash(response: &[u8]) -> Result<Option<String>, CuckooMinerError> {
+    match response.get(12..) {
+        Some(hash) if !hash.is_empty() => Ok(Some(String::from_utf8(hash.to_vec())?)),
+        _ => Ok(None),
+    }
+}
+
+// Loads a plugin as asked by an OP_LOAD_TRUSTED request, which holds the
+// policy as JSON followed by the plugin's path
+
+fn load_trusted_request(payload: &[u8]) -> Result<PluginHandle, CuckooMinerError> {
+    let policy_len = payload_u32(payload, 0)? as usize;
+    if payload.len() < 4 + policy_len {
+        return Err(CuckooMinerError::PluginIOError(String::from("Malformed plugin worker message")));
+    }
+    let (policy, full_path) = payload[4..].split_at(policy_len);
+    let policy: TrustPolicy = serde_json::from_slice(policy).map_err(|e| {
+        CuckooMinerError::PluginIOError(format!("Invalid trust policy: {}", e))
+    })?;
+    PluginHandle::load_trusted(&String::from_utf8(full_path.to_vec())?, &policy)
+}
+
+/// #Description
+///
+/// The main loop of the worker process. Reads requests from `input`, the
+/// first of which must be a request to load a plugin, performs them against
+/// the loaded plugin and writes responses to `output`. Returns once `input`
+/// is closed.
+///
+/// #Arguments
+///
+/// * `input` The stream to read requests from, normally the worker's stdin
+///
+/// * `output` The stream to write responses to, normally the worker's stdout
+///
+/// #Returns
+///
+/// Ok once `input` is closed, or an error if either stream fails
+///
+
+pub fn run_worker<R: Read, W: Write>(mut input: R, mut output: W) -> Result<(), CuckooMinerError> {
+    let mut plugin: Option<PluginHandle> = None;
+    while let Some((opcode, payload)) = read_frame(&mut input)? {
+        let result = match (opcode, plugin.as_ref()) {
+            (OP_LOAD, _) | (OP_LOAD_TRUSTED, _) => {
+                let loaded = if opcode == OP_LOAD_TRUSTED {
+                    load_trusted_request(&payload)
+                } else {
+                    PluginHandle::load(&String::from_utf8(payload)?)
+                };
+                match loaded {
+                    Ok(p) => {
+                        let mut response = Vec::new();
+                        put_u32(&mut response, p.abi_version());
+                        put_u32(&mut response, p.capabilities().bits());
+                        put_u32(&mut response, p.proof_size());
+                        if let Some(hash) = p.verified_hash() {
+                            response.extend_from_slice(hash.as_bytes());
+                        }
+                        plugin = Some(p);
+                        Ok(response)
+                    },
+                    Err(e) => Err(e),
+                }
+            },
+            (_, Some(p)) => handle_request(p, opcode, &payload),
+            (_, None) => Err(CuckooMinerError::PluginNotLoadedError(
+                String::from("No plugin has been loaded into the worker"))),
+        };
+        match result {
+            Ok(response) => write_frame(&mut output, STATUS_OK, &response)?,
+            Err(e) => write_frame(&mut output, STATUS_ERROR, &encode_error(&e))?,
+        }
+    }
+    Ok(())
+}
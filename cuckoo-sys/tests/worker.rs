@@ -0,0 +1,162 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Tests for running plugins in a worker process. These live alongside the
+//! worker executable so that cargo builds it for them. They use the mock
+//! plugin, so the workspace should be built first with `cargo build --all`
+
+extern crate cuckoo_sys;
+extern crate crypto;
+extern crate miner;
+extern crate error;
+#[macro_use]
+extern crate lazy_static;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+use cuckoo_sys::{PluginWorker, TrustPolicy};
+use error::CuckooMinerError;
+use miner::{CuckooMinerConfig, CuckooMinerSolution, CuckooMiner};
+
+lazy_static!{
+    static ref MOCK_LOCK: Mutex<()> = Mutex::new(());
+}
+
+static WORKER_PATH: &str = env!("CARGO_BIN_EXE_cuckoo_plugin_worker");
+
+// The mock plugin, built alongside the worker
+
+fn mock_path() -> String {
+    let path = PathBuf::from(WORKER_PATH).with_file_name(
+        format!("{}cuckoo_mock{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX));
+    assert!(path.is_file(), "{} not found, build the workspace first", path.display());
+    path.to_string_lossy().into_owned()
+}
+
+// Helper to build a config for the mock in a worker, with the given script
+
+fn mock_config(script: &str) -> CuckooMinerConfig {
+    env::set_var("CUCKOO_MOCK_CONFIG", script);
+    let mut config = CuckooMinerConfig::new();
+    config.plugin_full_path = mock_path();
+    config.use_worker_process = true;
+    config.worker_path = String::from(WORKER_PATH);
+    config
+}
+
+//Errors loading the plugin inside the worker should be
+//reported back to the caller as if the plugin were loaded
+//in process
+#[test]
+fn worker_reports_load_errors() {
+    let mut config = CuckooMinerConfig::new();
+    config.plugin_full_path = String::from("target/debug/libcuckoo_does_not_exist.so");
+    config.use_worker_process = true;
+    config.worker_path = String::from(WORKER_PATH);
+    match CuckooMiner::new(config) {
+        Err(CuckooMinerError::PluginNotFoundError(_)) => {},
+        Err(e) => panic!("Unexpected error: {:?}", e),
+        Ok(_) => panic!("Loaded a plugin that doesn't exist"),
+    }
+}
+
+//Plugins printing to stdout mid call shouldn't
+//interfere with the worker's responses
+#[test]
+fn worker_ignores_plugin_output() {
+    let _lock = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let nonces: Vec<u32> = (0..42).collect();
+    let script = format!("{{\"default_solution\": {:?}, \"print_on_call\": \"Graph 0 edges trimmed\\n\"}}",
+                         nonces);
+    let miner = CuckooMiner::new(mock_config(&script)).unwrap();
+    for _ in 0..3 {
+        let mut solution = CuckooMinerSolution::new();
+        assert!(miner.mine(&[1u8; 32], &mut solution).unwrap());
+        assert_eq!(&solution.solution_nonces[..], &nonces[..]);
+    }
+}
+
+//A plugin crashing should only take down its worker, which
+//can be restarted
+#[test]
+fn worker_reports_plugin_crash() {
+    let _lock = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let miner = CuckooMiner::new(mock_config("{\"crash_on_call\": true}")).unwrap();
+    let mut solution = CuckooMinerSolution::new();
+    for _ in 0..2 {
+        match miner.mine(&[1u8; 32], &mut solution) {
+            Err(CuckooMinerError::PluginCrashError(_)) => {},
+            r => panic!("Unexpected result: {:?}", r),
+        }
+    }
+
+    let worker = PluginWorker::spawn(WORKER_PATH, &mock_path()).unwrap();
+    let mut nonces = Vec::new();
+    match worker.call_cuckoo(&[1u8; 32], &mut nonces) {
+        Err(CuckooMinerError::PluginCrashError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    let crashed_id = worker.id();
+
+    //the restarted worker picks up the new script
+    env::set_var("CUCKOO_MOCK_CONFIG", "{\"default_solution\": [1, 2, 3]}");
+    worker.restart().unwrap();
+    assert!(worker.id() != crashed_id);
+    assert_eq!(worker.call_cuckoo(&[1u8; 32], &mut nonces).unwrap(), 1);
+    assert_eq!(&nonces[..3], &[1, 2, 3]);
+}
+
+//Workers check plugins against the trust policy themselves
+#[test]
+fn worker_checks_trust_policy() {
+    let _lock = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    env::set_var("CUCKOO_MOCK_CONFIG", "{\"default_solution\": [1, 2, 3]}");
+    match PluginWorker::spawn_trusted(WORKER_PATH, &mock_path(), &TrustPolicy::new()) {
+        Err(CuckooMinerError::PluginUntrustedError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r.map(|w| w.id())),
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.input(&fs::read(mock_path()).unwrap());
+    let hash = hasher.result_str();
+    let policy = TrustPolicy::new().allow_hash(&hash);
+    let worker = PluginWorker::spawn_trusted(WORKER_PATH, &mock_path(), &policy).unwrap();
+    assert_eq!(worker.verified_hash(), Some(hash.as_str()));
+    let mut nonces = Vec::new();
+    assert_eq!(worker.call_cuckoo(&[1u8; 32], &mut nonces).unwrap(), 1);
+    worker.restart().unwrap();
+    assert_eq!(worker.call_cuckoo(&[1u8; 32], &mut nonces).unwrap(), 1);
+}
+
+//Short nonces are refused rather than sent to the worker
+#[test]
+fn worker_refuses_short_nonce() {
+    let _lock = MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    env::set_var("CUCKOO_MOCK_CONFIG", "{}");
+    let worker = PluginWorker::spawn(WORKER_PATH, &mock_path()).unwrap();
+    assert_eq!(worker.call_cuckoo_start_processing().unwrap(), 1);
+    match worker.call_cuckoo_push_to_input_queue(&[1u8; 32], &[0u8; 4]) {
+        Err(CuckooMinerError::PluginProcessingError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    assert_eq!(worker.call_cuckoo_push_to_input_queue(&[1u8; 32], &[0u8; 8]).unwrap(), 1);
+    worker.call_cuckoo_stop_processing().unwrap();
+}
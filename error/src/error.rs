@@ -50,6 +50,46 @@ pub enum CuckooMinerError {
 
     /// Plugin processing can't start
     PluginProcessingError(String),
+
+    /// Occurs when calling a function that the loaded
+    /// plugin doesn't implement
+    PluginUnsupportedError(String),
+
+    /// Occurs when attempting to load a plugin which implements
+    /// an unsupported version of the plugin interface
+    PluginAbiVersionError(String),
+
+    /// Occurs when a plugin running in a worker process
+    /// crashes or its worker otherwise dies
+    PluginCrashError(String),
+
+    /// Occurs when a plugin returns malformed JSON, e.g.
+    /// from cuckoo_parameter_list
+    PluginJsonError(String),
+
+    /// Occurs when a plugin's manifest file can't be read
+    /// or doesn't describe a valid plugin
+    PluginManifestError(String),
+
+    /// Occurs when a plugin library doesn't export the
+    /// functions every plugin must
+    PluginSymbolError(String),
+
+    /// Occurs when a plugin returns a string which isn't
+    /// valid UTF-8, e.g. from cuckoo_description
+    PluginUtf8Error(String),
+
+    /// Occurs when a plugin library is refused by the
+    /// trust policy, before it's loaded
+    PluginUntrustedError(String),
+
+    /// Occurs when mining is cancelled, or its deadline
+    /// passes, before the plugin returns
+    MiningCancelledError(String),
+
+    /// Occurs when a solution doesn't form a valid cycle
+    /// in the graph generated from its header
+    InvalidSolutionError(String),
 }
 
 impl From<io::Error> for CuckooMinerError {
@@ -60,7 +100,7 @@ impl From<io::Error> for CuckooMinerError {
 
 impl From<string::FromUtf8Error> for CuckooMinerError {
     fn from(error: string::FromUtf8Error) -> Self {
-        CuckooMinerError::PluginIOError(String::from(format!("Error loading plugin description: {}",error)))
+        CuckooMinerError::PluginUtf8Error(String::from(format!("Error loading plugin description: {}",error)))
     }
 }
 
@@ -0,0 +1,422 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Benchmarks of how plugins actually perform on this host, for choosing
+//! between builds of the same size (e.g. simple, edgetrim or tomato) without
+//! guessing. Each plugin is run for a bounded time on a set of standard seeds,
+//! through the calls behind both `mine` (`cuckoo_call`) and `notify` (the
+//! async queue), measuring graphs per second, time to the first solution and
+//! peak resident memory.
+//!
+//! Results are run and kept by the
+//! [CuckooPluginManager](../manager/struct.CuckooPluginManager.html), which
+//! persists them per host and ranks the plugins returned by
+//! [get_available_plugins](../manager/struct.CuckooPluginManager.html#method.get_available_plugins)
+//! by them.
+//!
+//! #Example
+//! ```
+//!  plugin_manager.set_benchmark_file("/var/cache/cuckoo-miner/benchmarks.json");
+//!  plugin_manager.load_plugin_dir(String::from("target/debug"))?;
+//!  for b in plugin_manager.benchmark_plugins("30", &BenchmarkConfig::default())? {
+//!      println!("{}", b);
+//!  }
+//!  //fastest first
+//!  let caps = plugin_manager.get_available_plugins("30")?;
+//! ```
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use cuckoo_sys::{PluginHandle, PluginWorker, PluginCapabilities, TrustPolicy, default_worker_path,
+                 plugin_in_use};
+use error::CuckooMinerError;
+use manager::content_hash;
+
+/// The seeds plugins are benchmarked on by default, which are known to have
+/// solutions on graphs of size 16, 20, 25 and 28 respectively
+
+pub const STANDARD_SEEDS: [[u8; 32]; 4] = [
+    [0xd9, 0x93, 0xac, 0x4a, 0xe3, 0xc7, 0xf9, 0xeb, 0x34, 0xb2, 0x2e, 0x86, 0x85, 0x25, 0x64, 0xa9,
+     0xc1, 0x67, 0x2a, 0x35, 0x7a, 0x0a, 0x81, 0x80, 0x82, 0xc6, 0x0f, 0x2a, 0xb1, 0x5f, 0x6f, 0x67],
+    [0xa7, 0x02, 0x9c, 0xe6, 0x70, 0xe7, 0x81, 0xc3, 0xa4, 0xe7, 0x55, 0x68, 0x3a, 0x3b, 0x6f, 0xb9,
+     0xaa, 0x94, 0x05, 0x1b, 0x33, 0xd6, 0x36, 0x2a, 0x3e, 0x9e, 0x55, 0x90, 0x09, 0xb4, 0xf4, 0xfe],
+    [0xae, 0x71, 0xf3, 0x6d, 0xe6, 0x4c, 0x2d, 0xde, 0x50, 0xbb, 0x29, 0x93, 0xb3, 0x4e, 0x61, 0xd6,
+     0xfb, 0xa2, 0xbe, 0xe0, 0xd0, 0x52, 0xcb, 0x2d, 0xc9, 0x56, 0x06, 0x4f, 0x8a, 0x8a, 0xcd, 0x54],
+    [0x16, 0x18, 0x5a, 0x0b, 0xa6, 0x69, 0x79, 0xee, 0x7b, 0xbe, 0x90, 0x69, 0xb2, 0x59, 0xa7, 0x72,
+     0x43, 0x47, 0x23, 0x84, 0x70, 0x56, 0x80, 0xb0, 0x41, 0x16, 0x25, 0x9b, 0x9a, 0xda, 0x8a, 0xa7],
+];
+
+// Version of the benchmark file format, bumped whenever it changes
+
+pub(crate) const BENCHMARK_FILE_VERSION: u32 = 1;
+
+// How long to wait before polling the async queue again when there's
+// nothing to do
+
+const QUEUE_POLL_INTERVAL_MS: u64 = 1;
+
+/// How plugins are benchmarked
+
+#[derive(Debug, Clone)]
+pub struct BenchmarkConfig {
+    /// How long to run each plugin for, in each of sync and async mode.
+    /// A `cuckoo_call` in progress isn't interrupted, so a plugin which takes
+    /// longer than this per graph still completes one. Defaults to 10 seconds.
+    pub duration: Duration,
+
+    /// The seeds to mine on, in turn. Defaults to
+    /// [STANDARD_SEEDS](constant.STANDARD_SEEDS.html).
+    pub seeds: Vec<[u8; 32]>,
+
+    /// Whether to run each plugin in its own worker process, so its peak
+    /// memory can be measured on its own and a crashing plugin can't take
+    /// the caller down. If no worker executable can be found, plugins are
+    /// run in this process instead. Defaults to true.
+    pub use_worker_process: bool,
+
+    /// The path to the `cuckoo_plugin_worker` executable. Defaults to empty
+    /// string, in which case the default worker location is used.
+    pub worker_path: String,
+}
+
+impl Default for BenchmarkConfig {
+    fn default() -> BenchmarkConfig {
+        BenchmarkConfig {
+            duration: Duration::from_secs(10),
+            seeds: STANDARD_SEEDS.to_vec(),
+            use_worker_process: true,
+            worker_path: String::from(""),
+        }
+    }
+}
+
+/// What one plugin did in one mode during a benchmark
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchmarkMeasurement {
+    /// The number of graphs searched
+    pub graphs: u64,
+
+    /// How long the graphs took, in milliseconds
+    pub elapsed_ms: u64,
+
+    /// How long the first solution took to find, in milliseconds, if any
+    /// solution was found
+    pub first_solution_ms: Option<u64>,
+}
+
+impl BenchmarkMeasurement {
+    /// The number of graphs searched per second
+    pub fn graphs_per_sec(&self) -> f64 {
+        if self.elapsed_ms == 0 {
+            return 0.0;
+        }
+        self.graphs as f64 * 1000.0 / self.elapsed_ms as f64
+    }
+}
+
+/// The result of benchmarking a plugin
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginBenchmark {
+    /// The full path of the plugin
+    pub full_path: String,
+
+    /// The SHA-256 of the library benchmarked, so results for a plugin
+    /// which has since been rebuilt aren't used
+    pub hash: String,
+
+    /// Results of mining via `cuckoo_call`, if the plugin supports it
+    pub sync: Option<BenchmarkMeasurement>,
+
+    /// Results of mining via the async queue, if the plugin supports it
+    pub async_queue: Option<BenchmarkMeasurement>,
+
+    /// The peak resident memory while benchmarking, in bytes, if it could be
+    /// measured. When run in this process rather than a worker, this includes
+    /// the memory used by the caller
+    pub peak_rss: Option<u64>,
+
+    /// When the benchmark was run, in seconds since the Unix epoch
+    pub timestamp: u64,
+}
+
+impl PluginBenchmark {
+    /// The graphs per second of the faster of the plugin's modes, used to
+    /// rank plugins
+    pub fn graphs_per_sec(&self) -> f64 {
+        self.sync.iter().chain(self.async_queue.iter())
+            .map(|m| m.graphs_per_sec())
+            .fold(0.0, f64::max)
+    }
+
+    /// The time taken to find the first solution in either mode, if any
+    pub fn time_to_first_solution(&self) -> Option<Duration> {
+        self.sync.iter().chain(self.async_queue.iter())
+            .filter_map(|m| m.first_solution_ms)
+            .min()
+            .map(Duration::from_millis)
+    }
+}
+
+impl fmt::Display for PluginBenchmark {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {:.2} graphs/sec", self.full_path, self.graphs_per_sec())?;
+        for &(mode, ref m) in &[("sync", &self.sync), ("async", &self.async_queue)] {
+            if let Some(ref m) = **m {
+                write!(f, ", {} {:.2} graphs/sec", mode, m.graphs_per_sec())?;
+            }
+        }
+        match self.time_to_first_solution() {
+            Some(t) => write!(f, ", first solution {}ms", millis(t))?,
+            None => write!(f, ", no solution")?,
+        }
+        if let Some(rss) = self.peak_rss {
+            write!(f, ", peak RSS {} MB", rss >> 20)?;
+        }
+        Ok(())
+    }
+}
+
+// The benchmark results saved between runs, for every host sharing the file
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct BenchmarkFile {
+    pub version: u32,
+
+    // Results by host, then by plugin path
+    pub hosts: HashMap<String, HashMap<String, PluginBenchmark>>,
+}
+
+/// Returns the name results for this host are saved under, which is its
+/// hostname where that can be found
+
+pub fn host_id() -> String {
+    let mut hostname = String::new();
+    let _ = File::open("/proc/sys/kernel/hostname")
+        .or_else(|_| File::open("/etc/hostname"))
+        .and_then(|mut f| f.read_to_string(&mut hostname));
+    let hostname = String::from(hostname.trim());
+    if !hostname.is_empty() {
+        return hostname;
+    }
+    env::var("HOSTNAME").or_else(|_| env::var("COMPUTERNAME"))
+        .unwrap_or(String::from("localhost"))
+}
+
+// A plugin being benchmarked, either in this process or in a worker
+
+enum BenchmarkTarget {
+    InProcess(PluginHandle),
+    Worker(PluginWorker),
+}
+
+// Forwards a call to whichever kind of plugin is loaded
+
+macro_rules! forward {
+    ($self_:ident, $call:ident ( $($arg:expr),* )) => {
+        match *$self_ {
+            BenchmarkTarget::InProcess(ref p) => p.$call($($arg),*),
+            BenchmarkTarget::Worker(ref p) => p.$call($($arg),*),
+        }
+    }
+}
+
+impl BenchmarkTarget {
+    fn capabilities(&self) -> PluginCapabilities {
+        forward!(self, capabilities())
+    }
+
+    fn verified_hash(&self) -> Option<&str> {
+        forward!(self, verified_hash())
+    }
+
+    fn peak_rss(&self) -> Option<u64> {
+        match *self {
+            BenchmarkTarget::InProcess(_) => peak_rss("/proc/self/status"),
+            BenchmarkTarget::Worker(ref w) => peak_rss(&format!("/proc/{}/status", w.id())),
+        }
+    }
+}
+
+// Reads the peak resident memory from a Linux /proc status file
+
+fn peak_rss(status_path: &str) -> Option<u64> {
+    let mut status = String::new();
+    File::open(status_path).and_then(|mut f| f.read_to_string(&mut status)).ok()?;
+    status.lines()
+        .find(|l| l.starts_with("VmHWM:"))
+        .and_then(|l| l.split_whitespace().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb * 1024)
+}
+
+fn millis(d: Duration) -> u64 {
+    d.as_secs() * 1000 + d.subsec_nanos() as u64 / 1_000_000
+}
+
+// Mines on each seed in turn via cuckoo_call until the time is up
+
+fn benchmark_sync(target: &BenchmarkTarget, config: &BenchmarkConfig)
+    -> Result<BenchmarkMeasurement, CuckooMinerError> {
+    let mut solution = Vec::new();
+    let mut graphs = 0;
+    let mut first_solution_ms = None;
+    let start = Instant::now();
+    for seed in config.seeds.iter().cycle() {
+        if graphs > 0 && start.elapsed() >= config.duration {
+            break;
+        }
+        match forward!(target, call_cuckoo(seed, &mut solution))? {
+            1 => {
+                first_solution_ms.get_or_insert(millis(start.elapsed()));
+            },
+            0 => {},
+            r => return Err(CuckooMinerError::UnexpectedResultError(r)),
+        }
+        graphs += 1;
+    }
+    Ok(BenchmarkMeasurement {
+        graphs: graphs,
+        elapsed_ms: millis(start.elapsed()),
+        first_solution_ms: first_solution_ms,
+    })
+}
+
+// Keeps the async queue topped up with each seed in turn until the time is
+// up. Graphs are counted via cuckoo_hashes_since_last_call if the plugin
+// reports it, otherwise each hash accepted by the queue is counted
+
+fn benchmark_async(target: &BenchmarkTarget, config: &BenchmarkConfig)
+    -> Result<BenchmarkMeasurement, CuckooMinerError> {
+    let hash_metrics = target.capabilities().contains(PluginCapabilities::HASH_METRICS);
+    forward!(target, call_cuckoo_start_processing())?;
+    if hash_metrics {
+        forward!(target, call_cuckoo_hashes_since_last_call())?;
+    }
+    let mut solution = Vec::new();
+    let mut nonce = [0; 8];
+    let mut pushed = 0u64;
+    let mut graphs = 0u64;
+    let mut first_solution_ms = None;
+    let start = Instant::now();
+    while !config.seeds.is_empty() && start.elapsed() < config.duration {
+        let mut idle = true;
+        if forward!(target, call_cuckoo_is_queue_under_limit())? == 1 {
+            let seed = &config.seeds[(pushed % config.seeds.len() as u64) as usize];
+            for (i, b) in nonce.iter_mut().enumerate() {
+                *b = (pushed >> (56 - 8 * i)) as u8;
+            }
+            if forward!(target, call_cuckoo_push_to_input_queue(seed, &nonce))? != 0 {
+                pushed += 1;
+                idle = false;
+            }
+        }
+        while forward!(target, call_cuckoo_read_from_output_queue(&mut solution, &mut nonce))? != 0 {
+            first_solution_ms.get_or_insert(millis(start.elapsed()));
+            idle = false;
+        }
+        if hash_metrics {
+            graphs += forward!(target, call_cuckoo_hashes_since_last_call())? as u64;
+        }
+        if idle {
+            thread::sleep(Duration::from_millis(QUEUE_POLL_INTERVAL_MS));
+        }
+    }
+    let elapsed_ms = millis(start.elapsed());
+    forward!(target, call_cuckoo_stop_processing())?;
+    Ok(BenchmarkMeasurement {
+        graphs: if hash_metrics { graphs } else { pushed },
+        elapsed_ms: elapsed_ms,
+        first_solution_ms: first_solution_ms,
+    })
+}
+
+// Loads the plugin in a worker if asked and one can be found, otherwise
+// in this process, checking it against the trust policy if there is one
+
+fn load_target(full_path: &str, config: &BenchmarkConfig, trust_policy: Option<&TrustPolicy>)
+    -> Result<BenchmarkTarget, CuckooMinerError> {
+    if config.use_worker_process {
+        let worker_path = if config.worker_path.is_empty() {
+            default_worker_path()
+        } else {
+            Some(PathBuf::from(&config.worker_path))
+        };
+        match worker_path {
+            Some(p) => {
+                let worker = match trust_policy {
+                    Some(t) => PluginWorker::spawn_trusted(p, full_path, t)?,
+                    None => PluginWorker::spawn(p, full_path)?,
+                };
+                return Ok(BenchmarkTarget::Worker(worker));
+            },
+            None => warn!("No plugin worker found, benchmarking {} in process", full_path),
+        }
+    }
+    //plugins share their global state within a process, so one in use
+    //can't be run alongside, unless loaded from a private copy
+    if trust_policy.is_none() && plugin_in_use(full_path) {
+        return Err(CuckooMinerError::PluginUnsupportedError(format!(
+            "{} is in use, so can only be benchmarked in a worker process", full_path)));
+    }
+    //reset the peak memory of this process, on Linux
+    let _ = OpenOptions::new().write(true).open("/proc/self/clear_refs")
+        .and_then(|mut f| f.write_all(b"5"));
+    let handle = match trust_policy {
+        Some(t) => PluginHandle::load_trusted(full_path, t)?,
+        None => PluginHandle::load(full_path)?,
+    };
+    Ok(BenchmarkTarget::InProcess(handle))
+}
+
+// Benchmarks a single plugin in each mode it supports. A plugin checked
+// against a trust policy is identified by the hash of the contents which
+// were checked and loaded
+
+pub(crate) fn run_benchmark(full_path: &str, config: &BenchmarkConfig, trust_policy: Option<&TrustPolicy>)
+    -> Result<PluginBenchmark, CuckooMinerError> {
+    let target = load_target(full_path, config, trust_policy)?;
+    let hash = match target.verified_hash() {
+        Some(h) => String::from(h),
+        None => content_hash(full_path).ok_or_else(|| CuckooMinerError::PluginIOError(
+            format!("Unable to read {}", full_path)))?,
+    };
+    let caps = target.capabilities();
+    let sync = if caps.contains(PluginCapabilities::SYNC) {
+        Some(benchmark_sync(&target, config)?)
+    } else {
+        None
+    };
+    let async_queue = if caps.contains(PluginCapabilities::ASYNC_QUEUE) {
+        Some(benchmark_async(&target, config)?)
+    } else {
+        None
+    };
+    Ok(PluginBenchmark {
+        full_path: String::from(full_path),
+        hash: hash,
+        sync: sync,
+        async_queue: async_queue,
+        peak_rss: target.peak_rss(),
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+    })
+}
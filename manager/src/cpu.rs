@@ -0,0 +1,89 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Runtime detection of the host's CPU features, used to check whether
+//! plugins built for particular instruction sets (e.g. the mean miner,
+//! built with avx2) can run on this machine before they're ever loaded.
+//!
+//! Feature names follow the names used by rustc and gcc, e.g. `sse4.1`,
+//! `avx2` or `bmi2`, and are compared case-insensitively.
+
+// Detects which of the given features the host supports. The detection
+// macro needs literal feature names, hence the macro here.
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+macro_rules! detect_features {
+    ($($feature:tt),*) => {
+        vec![$(($feature, is_x86_feature_detected!($feature))),*]
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn detected_features() -> Vec<(&'static str, bool)> {
+    detect_features!("aes", "pclmulqdq", "rdrand", "rdseed", "tsc", "mmx",
+                     "sse", "sse2", "sse3", "ssse3", "sse4.1", "sse4.2", "sse4a",
+                     "sha", "avx", "avx2", "avx512f", "avx512cd", "avx512er",
+                     "avx512pf", "avx512bw", "avx512dq", "avx512vl", "fma",
+                     "bmi1", "bmi2", "abm", "lzcnt", "tbm", "popcnt", "fxsr",
+                     "xsave", "xsaveopt", "xsaves", "xsavec", "adx")
+}
+
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn detected_features() -> Vec<(&'static str, bool)> {
+    Vec::new()
+}
+
+/// #Description
+///
+/// Returns the CPU features supported by the host, out of those that can
+/// be detected at runtime on this architecture.
+///
+/// #Arguments
+///
+/// None
+///
+/// #Returns
+///
+/// The names of the supported features.
+///
+
+pub fn host_cpu_features() -> Vec<String> {
+    detected_features().into_iter()
+        .filter(|&(_, supported)| supported)
+        .map(|(name, _)| String::from(name))
+        .collect()
+}
+
+/// #Description
+///
+/// Checks a plugin's required CPU features against the host. Features which
+/// can't be detected on this architecture are treated as missing.
+///
+/// #Arguments
+///
+/// * `required` (IN) The names of the features the plugin requires.
+///
+/// #Returns
+///
+/// The names of the required features the host doesn't support, empty if
+/// the plugin can run here.
+///
+
+pub fn missing_cpu_features(required: &[String]) -> Vec<String> {
+    let host = host_cpu_features();
+    required.iter()
+        .filter(|r| !host.iter().any(|h| h.eq_ignore_ascii_case(r)))
+        .cloned()
+        .collect()
+}
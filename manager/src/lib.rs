@@ -42,8 +42,28 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
+extern crate toml;
+extern crate crypto;
 
+pub mod benchmark;
+pub mod cpu;
 pub mod manager;
+pub mod manifest;
+pub mod query;
+pub mod report;
+pub mod watch;
 pub use manager::{CuckooPluginManager,
-                  CuckooPluginCapabilities};
+                  CuckooPluginCapabilities,
+                  CuckooVariation,
+                  CuckooPluginParameter,
+                  PluginSource,
+                  PluginEvent,
+                  PLUGIN_PATH_ENV_VAR};
+pub use benchmark::{BenchmarkConfig, BenchmarkMeasurement, PluginBenchmark, STANDARD_SEEDS};
+pub use manifest::CuckooPluginManifest;
+pub use query::{PluginQuery, PluginSortKey};
+pub use report::{DiscoveryReport, RejectedPlugin, RejectionReason};
+pub use watch::PluginWatcher;
+pub use cpu::{host_cpu_features, missing_cpu_features};
+pub use cuckoo_sys::{PluginCapabilities, TrustPolicy, ParameterType, ParameterValue};
 
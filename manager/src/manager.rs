@@ -30,19 +30,33 @@
 /// ```
 
 use std::{fmt};
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
 use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
 use std::path::{Path};
+use std::time::SystemTime;
 
 use regex::Regex;
 use glob::glob;
 
+use serde::{Serialize, Serializer, Deserialize, Deserializer};
+use serde::de::Error;
 use serde_json;
 
-use cuckoo_sys::{call_cuckoo_description, 
-                 call_cuckoo_parameter_list,
-                 load_cuckoo_lib,
-                 unload_cuckoo_lib};
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+use cuckoo_sys::{PluginHandle, PluginCapabilities, TrustPolicy, DEFAULT_PROOF_SIZE, plugin_in_use};
+pub use cuckoo_sys::CuckooPluginParameter;
 use error::CuckooMinerError;
+use manifest::CuckooPluginManifest;
+use cpu::missing_cpu_features;
+use query::PluginQuery;
+use report::{DiscoveryReport, RejectedPlugin, RejectionReason};
+use benchmark::{BenchmarkConfig, BenchmarkFile, PluginBenchmark, BENCHMARK_FILE_VERSION,
+                host_id, run_benchmark};
 
 
 
@@ -56,6 +70,18 @@ static DLL_SUFFIX: &str=".dylib";
 #[cfg(target_os = "windows")]
 static DLL_SUFFIX: &str=".dll";
 
+/// Environment variable holding extra plugin directories, separated as in
+/// `PATH`, which take precedence over any other directory on the search path
+
+pub const PLUGIN_PATH_ENV_VAR: &str = "CUCKOO_PLUGIN_PATH";
+
+// System-wide plugin directories, searched after any others
+
+#[cfg(unix)]
+const SYSTEM_PLUGIN_DIRS: [&str; 2] = ["/usr/local/lib/cuckoo-miner", "/usr/lib/cuckoo-miner"];
+#[cfg(not(unix))]
+const SYSTEM_PLUGIN_DIRS: [&str; 0] = [];
+
 // Helper function to get the absolute path from a relative path
 
 fn abspath<P: AsRef<Path> + ?Sized>(relpath: &P) -> String { 
@@ -64,10 +90,151 @@ fn abspath<P: AsRef<Path> + ?Sized>(relpath: &P) -> String {
     String::from(full_path.to_str().unwrap())
 }
 
+// Names of each capability, as used in manifests and serialised capabilities
+
+const CAPABILITY_NAMES: [(&str, PluginCapabilities); 7] =
+    [("sync", PluginCapabilities::SYNC),
+     ("async_queue", PluginCapabilities::ASYNC_QUEUE),
+     ("parameters", PluginCapabilities::PARAMETERS),
+     ("hash_metrics", PluginCapabilities::HASH_METRICS),
+     ("typed_parameters", PluginCapabilities::TYPED_PARAMETERS),
+     ("abort", PluginCapabilities::ABORT),
+     ("all_cycles", PluginCapabilities::ALL_CYCLES)];
+
+// Builds a set of capabilities from their names, or returns the
+// first unknown name
+
+pub(crate) fn parse_capabilities(names: &[String]) -> Result<PluginCapabilities, String> {
+    let mut caps = PluginCapabilities::empty();
+    for name in names {
+        match CAPABILITY_NAMES.iter().find(|&&(n, _)| n == name) {
+            Some(&(_, c)) => caps.insert(c),
+            None => return Err(format!("unknown capability {}", name)),
+        }
+    }
+    Ok(caps)
+}
+
+// Capabilities are serialised as a list of their names
+
+fn serialize_capabilities<S: Serializer>(caps: &PluginCapabilities, serializer: S)
+    -> Result<S::Ok, S::Error> {
+    CAPABILITY_NAMES.iter()
+        .filter(|&&(_, c)| caps.contains(c))
+        .map(|&(n, _)| n)
+        .collect::<Vec<_>>()
+        .serialize(serializer)
+}
+
+fn deserialize_capabilities<'de, D: Deserializer<'de>>(deserializer: D)
+    -> Result<PluginCapabilities, D::Error> {
+    let names = Vec::<String>::deserialize(deserializer)?;
+    parse_capabilities(&names).map_err(D::Error::custom)
+}
+
+/// The cuckoo algorithm variation a plugin implements. Converted from
+/// and displayed as its lowercase name, e.g. `"tomato"`.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CuckooVariation {
+    /// The plugin doesn't say
+    Unknown,
+
+    /// The simple miner
+    Simple,
+
+    /// The edge trimming miner
+    Edgetrim,
+
+    /// The tomato miner
+    Tomato,
+
+    /// The mean miner
+    Mean,
+
+    /// Any other variation, by name
+    Other(String),
+}
+
+impl Default for CuckooVariation {
+    fn default() -> CuckooVariation {
+        CuckooVariation::Unknown
+    }
+}
+
+impl<'a> From<&'a str> for CuckooVariation {
+    fn from(name: &'a str) -> CuckooVariation {
+        match name.to_lowercase().as_str() {
+            "" | "unknown" => CuckooVariation::Unknown,
+            "simple" => CuckooVariation::Simple,
+            "edgetrim" => CuckooVariation::Edgetrim,
+            "tomato" => CuckooVariation::Tomato,
+            "mean" => CuckooVariation::Mean,
+            other => CuckooVariation::Other(String::from(other)),
+        }
+    }
+}
+
+impl fmt::Display for CuckooVariation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CuckooVariation::Unknown => write!(f, "unknown"),
+            CuckooVariation::Simple => write!(f, "simple"),
+            CuckooVariation::Edgetrim => write!(f, "edgetrim"),
+            CuckooVariation::Tomato => write!(f, "tomato"),
+            CuckooVariation::Mean => write!(f, "mean"),
+            CuckooVariation::Other(ref n) => write!(f, "{}", n),
+        }
+    }
+}
+
+impl Serialize for CuckooVariation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CuckooVariation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<CuckooVariation, D::Error> {
+        String::deserialize(deserializer).map(|n| CuckooVariation::from(n.as_str()))
+    }
+}
+
+/// Where on the plugin search path a plugin's directory came from
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PluginSource {
+    /// A directory passed in by the caller
+    Configured,
+
+    /// A directory from the `CUCKOO_PLUGIN_PATH` environment variable
+    Environment,
+
+    /// A system-wide plugin directory
+    System,
+}
+
+impl Default for PluginSource {
+    fn default() -> PluginSource {
+        PluginSource::Configured
+    }
+}
+
+// The JSON object returned by a plugin's cuckoo_metadata function
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CuckooPluginMetadata {
+    variation: CuckooVariation,
+    edge_bits: u32,
+    easiness: u32,
+}
+
 /// A wrapper for details that a plugin can report via it's cuckoo_description
-/// function. Basic at the moment, but will be extended.
+/// and cuckoo_metadata functions, or its manifest. Serialises to and from JSON.
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct CuckooPluginCapabilities {
     /// The plugin's descriptive name
     /// As reported by the plugin
@@ -84,6 +251,52 @@ pub struct CuckooPluginCapabilities {
 
     /// The plugin's reported parameters
     pub parameters: Vec<CuckooPluginParameter>,
+
+    /// Which modes and functions the plugin supports, based on
+    /// the plugin functions it exports
+    #[serde(serialize_with = "serialize_capabilities", deserialize_with = "deserialize_capabilities")]
+    pub capabilities: PluginCapabilities,
+
+    /// The version of the plugin interface the plugin implements,
+    /// or 0 if the plugin was described by a manifest
+    pub abi_version: u32,
+
+    /// The cuckoo algorithm variation the plugin implements
+    pub variation: CuckooVariation,
+
+    /// The size of the cuckoo graph, as a power of 2, or 0 if unknown.
+    /// This is one more than the graph's edge bits
+    pub cuckoo_size: u32,
+
+    /// The number of nonces in each solution
+    pub proof_size: u32,
+
+    /// The percentage of the graph's nodes used as edges, or 0 if unknown
+    pub easiness: u32,
+
+    /// The memory the plugin needs, in bytes, or 0 if unknown
+    pub memory_required: u64,
+
+    /// CPU features the plugin was built to use
+    pub cpu_features: Vec<String>,
+
+    /// The path to the manifest the plugin was described by, or None
+    /// if the plugin library itself was loaded and queried
+    pub manifest_path: Option<String>,
+
+    /// Why the plugin can't run on this host, e.g. missing CPU features,
+    /// or None if it can
+    pub unsupported_reason: Option<String>,
+
+    /// The full path of the directory the plugin was found in
+    pub plugin_dir: String,
+
+    /// Where the plugin's directory came from on the search path
+    pub source: PluginSource,
+
+    /// Full paths of plugins with the same name further down the search
+    /// path, which this one takes precedence over
+    pub shadowed: Vec<String>,
 }
 
 impl Default for CuckooPluginCapabilities {
@@ -94,38 +307,147 @@ impl Default for CuckooPluginCapabilities {
 			full_path: String::from(""),
             file_name: String::from(""),
             parameters: Vec::new(),
+            capabilities: PluginCapabilities::empty(),
+            abi_version: 0,
+            variation: CuckooVariation::Unknown,
+            cuckoo_size: 0,
+            proof_size: DEFAULT_PROOF_SIZE,
+            easiness: 0,
+            memory_required: 0,
+            cpu_features: Vec::new(),
+            manifest_path: None,
+            unsupported_reason: None,
+            plugin_dir: String::from(""),
+            source: PluginSource::Configured,
+            shadowed: Vec::new(),
 		}
 	}
 }
 
+impl CuckooPluginCapabilities {
+    /// The number of edges in the plugin's graph, as a power of 2,
+    /// or 0 if unknown
+    pub fn edge_bits(&self) -> u32 {
+        self.cuckoo_size.saturating_sub(1)
+    }
+}
+
 impl fmt::Display for CuckooPluginCapabilities{
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f,"Name: {}\nDescription:{}\nPath:{}\nParameters:{}\n", self.name, self.description, self.full_path,
-            serde_json::to_string(&self.parameters).unwrap())
+        write!(f,"Name: {}\nDescription:{}\nPath:{}\nCapabilities:{}\nParameters:{}\n", self.name, self.description, self.full_path,
+            self.capabilities, serde_json::to_string(&self.parameters).unwrap())?;
+        write!(f,"Variation:{}\nCuckoo Size:{}\nProof Size:{}\nEasiness:{}\n", self.variation, self.cuckoo_size,
+            self.proof_size, self.easiness)?;
+        if let Some(ref r) = self.unsupported_reason {
+            write!(f,"Unsupported:{}\n", r)?;
+        }
+        Ok(())
     }
 }
 
-/// Holds a set of plugin parameter descriptions returned from a plugin
-/// as deserialised from json
-/// 
-///
+// Fills out the variation and size from a plugin file name following the
+// cuckoo_<variation>_<size> convention, e.g. libcuckoo_tomato_28.so
+
+fn parse_file_name(full_path:&str, caps:&mut CuckooPluginCapabilities) {
+    let stem = match Path::new(full_path).file_stem() {
+        Some(s) => s.to_string_lossy().into_owned(),
+        None => return,
+    };
+    let parts = stem.split('_').collect::<Vec<_>>();
+    if parts.len() < 3 {
+        return;
+    }
+    if let Ok(size) = parts[parts.len()-1].parse::<u32>() {
+        caps.variation = CuckooVariation::from(parts[parts.len()-2]);
+        caps.cuckoo_size = size;
+    }
+}
+
+/// A change to the set of available plugins, as found by
+/// [refresh](struct.CuckooPluginManager.html#method.refresh)
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct CuckooPluginParameter {
-    /// The name of the parameter
-    pub name:String,
+#[derive(Debug, Clone)]
+pub enum PluginEvent {
+    /// A new plugin has appeared
+    Added(CuckooPluginCapabilities),
 
-    /// Description of the parameter
-    pub description:String,
+    /// A plugin's library or manifest has changed, and it has been queried again
+    Changed(CuckooPluginCapabilities),
 
-    /// The default value of the parameter, used if none is provided
-    pub default_value:u32,
+    /// A plugin has disappeared, identified by its full path
+    Removed(String),
 
-    /// The minimum allowed value for the parameter
-    pub min_value: u32,
+    /// A plugin's library has changed while in use, e.g. by a running miner,
+    /// so its old capabilities are kept until it's released. Identified
+    /// by its full path
+    ChangeDeferred(String),
 
-    /// The maximum allowed value for the parameter
-    pub max_value: u32,
+    /// Rescanning the plugin directories failed
+    ScanFailed(String),
+}
+
+// What a plugin library and its manifest looked like when last queried,
+// used to tell whether either has changed since
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PluginStamp {
+    size: u64,
+    modified: Option<SystemTime>,
+    manifest_modified: Option<SystemTime>,
+}
+
+// A plugin as last queried
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct KnownPlugin {
+    stamp: PluginStamp,
+
+    // SHA-256 of the library, so a library that's been touched or
+    // reinstalled without changing isn't queried again
+    hash: String,
+
+    caps: CuckooPluginCapabilities,
+
+    // Which query this was, to tell when a plugin has been queried again
+    #[serde(skip)]
+    generation: u64,
+}
+
+// The version of the capability cache file format
+
+const CACHE_VERSION: u32 = 1;
+
+// The contents of a capability cache file
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PluginCache {
+    version: u32,
+    plugins: HashMap<String, KnownPlugin>,
+}
+
+// Returns the SHA-256 of the file at the given path, as hex
+
+pub(crate) fn content_hash(full_path:&str) -> Option<String> {
+    let mut contents = Vec::new();
+    File::open(full_path).and_then(|mut f| f.read_to_end(&mut contents)).ok()?;
+    let mut hasher = Sha256::new();
+    hasher.input(&contents);
+    Some(hasher.result_str())
+}
+
+// Returns the current stamp of the plugin at the given path, or None
+// if it can't be read
+
+fn plugin_stamp(full_path:&str) -> Option<PluginStamp> {
+    let metadata = fs::metadata(full_path).ok()?;
+    let manifest_modified = CuckooPluginManifest::find(Path::new(full_path))
+        .and_then(|p| fs::metadata(p).ok())
+        .and_then(|m| m.modified().ok());
+    Some(PluginStamp {
+        size: metadata.len(),
+        modified: metadata.modified().ok(),
+        manifest_modified: manifest_modified,
+    })
 }
 
 /// A structure that loads and queries all of the plugins in a particular directory via their
@@ -133,20 +455,76 @@ pub struct CuckooPluginParameter {
 /// 
 
 pub struct CuckooPluginManager {
-    // The current directory
-    plugin_dir: String,
+    // The directories searched by the last load, in order
+    search_path: Vec<(String, PluginSource)>,
 
     // Holds the current set of plugin capabilities, as returned
     // from all of the plugins in the plugin directory
 
     current_plugin_caps: Option<Vec<CuckooPluginCapabilities>>,
+
+    // The configured directories of the last search path load, or None
+    // if a single directory was loaded
+
+    plugin_dirs: Option<Vec<String>>,
+
+    // Every plugin queried so far, by full path, so unchanged plugins
+    // aren't queried again
+
+    known_plugins: HashMap<String, KnownPlugin>,
+
+    // How many plugins have been queried
+
+    query_count: u64,
+
+    // Where known plugins are saved between runs, if anywhere
+
+    cache_file: Option<String>,
+
+    // Plugins whose change has been reported as deferred
+
+    deferred_plugins: HashSet<String>,
+
+    // Files rejected so far, by full path, so they aren't loaded again
+    // until they change
+
+    rejected_plugins: HashMap<String, (Option<PluginStamp>, RejectionReason)>,
+
+    // The files rejected by the last load
+
+    rejected: Vec<RejectedPlugin>,
+
+    // Which libraries may be loaded, if restricted
+
+    trust_policy: Option<TrustPolicy>,
+
+    // Benchmark results for every host, by plugin path
+
+    benchmarks: BenchmarkFile,
+
+    // Where benchmark results are saved between runs, if anywhere
+
+    benchmark_file: Option<String>,
 }
 
 impl Default for CuckooPluginManager {
 	fn default() -> CuckooPluginManager {
 		CuckooPluginManager {
-            plugin_dir: String::from("target/debug"),
+            search_path: vec![(String::from("target/debug"), PluginSource::Configured)],
             current_plugin_caps: None,
+            plugin_dirs: None,
+            known_plugins: HashMap::new(),
+            query_count: 0,
+            cache_file: None,
+            deferred_plugins: HashSet::new(),
+            rejected_plugins: HashMap::new(),
+            rejected: Vec::new(),
+            trust_policy: None,
+            benchmarks: BenchmarkFile {
+                version: BENCHMARK_FILE_VERSION,
+                hosts: HashMap::new(),
+            },
+            benchmark_file: None,
 		}
 	}
 }
@@ -182,6 +560,9 @@ impl CuckooPluginManager {
     /// installed plugins on the system. This will parse any dll
     /// with the name 'cuckoo' in it, with suffix depending on the host os 
     /// (.so on unix, .dylib on mac, .dll on windows(not implemented as of yet))
+    /// Plugins with a [manifest](../manifest/index.html) next to them are
+    /// described from the manifest instead, and aren't loaded. A plugin which
+    /// can't be loaded or queried is rejected, and the rest are still loaded.
     ///
     /// #Arguments
     ///
@@ -191,17 +572,345 @@ impl CuckooPluginManager {
     ///
     /// #Returns
     ///
-    /// Ok if successful a [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
-    /// with specific detail if an error is encountered. Populates the internal
-    /// list of plugins for the given directory.
+    /// Ok with a [DiscoveryReport](../report/struct.DiscoveryReport.html) of the
+    /// plugins found and the files rejected, or a `NoPluginsFoundError` if there
+    /// are no good plugins in the directory, in which case the rejected files are
+    /// still available from [get_discovery_report](#method.get_discovery_report).
+    /// Populates the internal list of plugins for the given directory.
     ///
 
     pub fn load_plugin_dir (&mut self, plugin_dir:String) 
-        -> Result<(), CuckooMinerError> {
-        self.plugin_dir = plugin_dir.clone();
-        let caps=self.load_all_plugin_caps(&plugin_dir)?;
-        self.current_plugin_caps=Some(caps);
-        Ok(())
+        -> Result<DiscoveryReport, CuckooMinerError> {
+        self.search_path = vec![(abspath(Path::new(&plugin_dir)), PluginSource::Configured)];
+        self.plugin_dirs = None;
+        let report=self.load_all_plugin_caps(&plugin_dir, PluginSource::Configured)?;
+        self.rejected=report.rejected.clone();
+        if report.plugins.is_empty() {
+            return Err(CuckooMinerError::NoPluginsFoundError(
+                format!("No plugins found in plugin directory {}, {} rejected",
+                    self.search_path[0].0, report.rejected.len())));
+        }
+        self.current_plugin_caps=Some(report.plugins.clone());
+        self.save_cache();
+        Ok(report)
+    }
+
+    /// #Description 
+    ///
+    /// Builds the ordered plugin search path. Directories listed in the
+    /// `CUCKOO_PLUGIN_PATH` environment variable come first, then the given
+    /// directories, then the system plugin directories (`/usr/local/lib/cuckoo-miner`
+    /// and `/usr/lib/cuckoo-miner` on unix). Directories are resolved to full
+    /// paths, and any appearing more than once are only kept in their first place.
+    ///
+    /// #Arguments
+    ///
+    /// * `plugin_dirs` (IN) The configured plugin directories, in order of preference.
+    ///
+    /// #Returns
+    ///
+    /// The full path of each directory on the search path, with where it came from.
+    ///
+
+    pub fn build_search_path(plugin_dirs:&[String]) -> Vec<(String, PluginSource)> {
+        let env_dirs=env::var_os(PLUGIN_PATH_ENV_VAR)
+            .map(|p| env::split_paths(&p).collect::<Vec<_>>())
+            .unwrap_or(Vec::new());
+        let candidates=env_dirs.iter()
+            .filter(|d| !d.as_os_str().is_empty())
+            .map(|d| (abspath(d), PluginSource::Environment))
+            .chain(plugin_dirs.iter().map(|d| (abspath(Path::new(d)), PluginSource::Configured)))
+            .chain(SYSTEM_PLUGIN_DIRS.iter().map(|d| (String::from(*d), PluginSource::System)));
+
+        let mut search_path:Vec<(String, PluginSource)>=Vec::new();
+        for (dir, source) in candidates {
+            if !search_path.iter().any(|&(ref d, _)| *d==dir) {
+                search_path.push((dir, source));
+            }
+        }
+        search_path
+    }
+
+    /// #Description 
+    ///
+    /// Loads all available plugins from every directory on the search path
+    /// (see [build_search_path](#method.build_search_path)), as
+    /// [load_plugin_dir](#method.load_plugin_dir) does for a single directory.
+    /// Directories which don't exist or hold no plugins are skipped. Where plugins
+    /// in different directories have the same name, only the one found first is
+    /// kept, with the full paths of the others recorded in its `shadowed` list.
+    ///
+    /// #Arguments
+    ///
+    /// * `plugin_dirs` (IN) The configured plugin directories, in order of preference.
+    ///
+    /// #Returns
+    ///
+    /// Ok with a [DiscoveryReport](../report/struct.DiscoveryReport.html) covering
+    /// every directory, or a `NoPluginsFoundError` if no directory on the search
+    /// path holds any good plugins. Populates the internal list of plugins, each
+    /// recording the directory it was found in and where that directory came from.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// //e.g. with CUCKOO_PLUGIN_PATH=/opt/cuckoo/plugins
+    /// manager.load_plugin_search_path(vec![String::from("plugins")])?;
+    /// for c in manager.get_available_plugins("")? {
+    ///     println!("{} from {} ({:?})", c.name, c.plugin_dir, c.source);
+    /// }
+    /// ```
+    ///
+
+    pub fn load_plugin_search_path(&mut self, plugin_dirs:Vec<String>)
+        -> Result<DiscoveryReport, CuckooMinerError> {
+        let search_path=CuckooPluginManager::build_search_path(&plugin_dirs);
+        let mut result_vec:Vec<CuckooPluginCapabilities>=Vec::new();
+        let mut rejected:Vec<RejectedPlugin>=Vec::new();
+        for &(ref dir, source) in &search_path {
+            if !Path::new(dir).is_dir() {
+                debug!("Skipping missing plugin directory {}", dir);
+                continue;
+            }
+            let report=self.load_all_plugin_caps(dir, source)?;
+            rejected.extend(report.rejected);
+            for c in report.plugins {
+                match result_vec.iter_mut().find(|r| r.name==c.name) {
+                    Some(r) => {
+                        debug!("Plugin {} is shadowed by {}", c.full_path, r.full_path);
+                        r.shadowed.push(c.full_path);
+                    },
+                    None => result_vec.push(c),
+                }
+            }
+        }
+
+        self.rejected=rejected.clone();
+        if result_vec.len()==0 {
+            return Err(CuckooMinerError::NoPluginsFoundError(
+                format!("No plugins found on plugin search path {:?}, {} rejected",
+                    search_path.iter().map(|&(ref d, _)| d).collect::<Vec<_>>(), rejected.len())));
+        }
+        self.search_path=search_path;
+        self.plugin_dirs=Some(plugin_dirs);
+        self.current_plugin_caps=Some(result_vec.clone());
+        self.save_cache();
+        Ok(DiscoveryReport {
+            plugins: result_vec,
+            rejected: rejected,
+        })
+    }
+
+    /// #Description 
+    ///
+    /// Rescans the directories of the last load, whether a single directory or
+    /// the search path, for plugins which have appeared, changed or disappeared
+    /// since. Only new plugins, and plugins whose library or manifest has changed,
+    /// are queried. A plugin which has changed while in use in this process, e.g.
+    /// by a running miner, is never loaded again underneath it; its old
+    /// capabilities are kept until it's released, and picked up by a later refresh.
+    ///
+    /// See [PluginWatcher](../watch/struct.PluginWatcher.html) to refresh
+    /// periodically in the background.
+    ///
+    /// #Arguments
+    ///
+    /// None
+    ///
+    /// #Returns
+    ///
+    /// Ok with a [PluginEvent](enum.PluginEvent.html) for each change, or a
+    /// [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) if
+    /// a directory couldn't be scanned, in which case the current plugins are kept.
+    /// A plugin which can no longer be queried is rejected, and reported as removed.
+    ///
+
+    pub fn refresh(&mut self) -> Result<Vec<PluginEvent>, CuckooMinerError> {
+        let old_caps=self.current_plugin_caps.clone().unwrap_or(Vec::new());
+        let old_generations=self.known_plugins.iter()
+            .map(|(p, k)| (p.clone(), k.generation))
+            .collect::<HashMap<_, _>>();
+
+        let result=match self.plugin_dirs.clone() {
+            Some(dirs) => self.load_plugin_search_path(dirs),
+            None => {
+                let dir=self.search_path[0].0.clone();
+                self.load_plugin_dir(dir)
+            },
+        };
+        match result {
+            Ok(_) => {},
+            Err(CuckooMinerError::NoPluginsFoundError(_)) => self.current_plugin_caps=Some(Vec::new()),
+            Err(e) => return Err(e),
+        }
+
+        let new_caps=self.current_plugin_caps.clone().unwrap_or(Vec::new());
+        let mut events=Vec::new();
+        for c in &new_caps {
+            if !old_caps.iter().any(|o| o.full_path==c.full_path) {
+                events.push(PluginEvent::Added(c.clone()));
+                continue;
+            }
+            let known=self.known_plugins.get(&c.full_path);
+            if known.map(|k| k.generation)!=old_generations.get(&c.full_path).cloned() {
+                self.deferred_plugins.remove(&c.full_path);
+                events.push(PluginEvent::Changed(c.clone()));
+            } else if known.map(|k| &k.stamp)!=plugin_stamp(&c.full_path).as_ref() &&
+                      self.deferred_plugins.insert(c.full_path.clone()) {
+                events.push(PluginEvent::ChangeDeferred(c.full_path.clone()));
+            }
+        }
+        for o in old_caps {
+            if !new_caps.iter().any(|c| c.full_path==o.full_path) {
+                self.known_plugins.remove(&o.full_path);
+                self.deferred_plugins.remove(&o.full_path);
+                events.push(PluginEvent::Removed(o.full_path));
+            }
+        }
+        self.save_cache();
+        Ok(events)
+    }
+
+    /// #Description 
+    ///
+    /// Forgets the capabilities of every plugin not currently in use, then
+    /// [refreshes](#method.refresh), so every plugin is queried again whether
+    /// or not it appears to have changed. The cache file, if any, is rewritten.
+    ///
+    /// #Arguments
+    ///
+    /// None
+    ///
+    /// #Returns
+    ///
+    /// As for [refresh](#method.refresh), with every plugin queried again
+    /// reported as changed.
+    ///
+
+    pub fn force_refresh(&mut self) -> Result<Vec<PluginEvent>, CuckooMinerError> {
+        self.known_plugins.retain(|p, _| plugin_in_use(p));
+        self.refresh()
+    }
+
+    /// #Description 
+    ///
+    /// Caches plugin capabilities in the given JSON file between runs, so
+    /// plugins are only loaded and queried when they're new or have changed.
+    /// Plugins are keyed by full path, and checked against the size, modification
+    /// time and SHA-256 of their library and the modification time of their manifest.
+    /// Any capabilities already in the file are read, and the file is rewritten after
+    /// every load or refresh. A missing or unreadable cache file is treated as empty.
+    /// Use [force_refresh](#method.force_refresh) to query every plugin again.
+    ///
+    /// #Arguments
+    ///
+    /// * `cache_file` (IN) The path of the cache file, created if it doesn't exist.
+    ///
+    /// #Returns
+    ///
+    /// Nothing
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// let mut manager = CuckooPluginManager::new()?;
+    /// manager.set_cache_file("/var/cache/cuckoo-miner/plugins.json");
+    /// //only plugins added or rebuilt since the last run are loaded here
+    /// manager.load_plugin_search_path(vec![String::from("plugins")])?;
+    /// ```
+    ///
+
+    pub fn set_cache_file(&mut self, cache_file:&str) {
+        self.cache_file=Some(String::from(cache_file));
+        let mut contents=String::new();
+        if let Err(e) = File::open(cache_file).and_then(|mut f| f.read_to_string(&mut contents)) {
+            debug!("Unable to read plugin cache {}: {}", cache_file, e);
+            return;
+        }
+        match serde_json::from_str::<PluginCache>(&contents) {
+            Ok(ref c) if c.version!=CACHE_VERSION => {
+                debug!("Ignoring plugin cache {} with version {}", cache_file, c.version);
+            },
+            Ok(c) => {
+                for (path, mut known) in c.plugins {
+                    if !self.known_plugins.contains_key(&path) {
+                        self.query_count+=1;
+                        known.generation=self.query_count;
+                        self.known_plugins.insert(path, known);
+                    }
+                }
+            },
+            Err(e) => warn!("Ignoring invalid plugin cache {}: {}", cache_file, e),
+        }
+    }
+
+    // Writes the known plugins which still exist to the cache file, if
+    // there is one. Failing to write the cache doesn't stop discovery,
+    // so is only logged
+
+    fn save_cache(&self) {
+        let cache_file=match self.cache_file {
+            Some(ref f) => f,
+            None => return,
+        };
+        let cache=PluginCache {
+            version: CACHE_VERSION,
+            plugins: self.known_plugins.iter()
+                .filter(|&(p, _)| Path::new(p).is_file())
+                .map(|(p, k)| (p.clone(), k.clone()))
+                .collect(),
+        };
+        //write alongside and rename, so readers never see a partial file
+        let temp_file=format!("{}.tmp", cache_file);
+        let result=serde_json::to_string(&cache)
+            .map_err(|e| e.to_string())
+            .and_then(|json| File::create(&temp_file)
+                .and_then(|mut f| f.write_all(json.as_bytes()))
+                .and_then(|_| fs::rename(&temp_file, cache_file))
+                .map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Unable to write plugin cache {}: {}", cache_file, e);
+        }
+    }
+
+    /// Returns the directories searched by the last load, in order, with
+    /// where each came from
+
+    pub fn get_search_path(&self) -> Vec<(String, PluginSource)> {
+        self.search_path.clone()
+    }
+
+    /// #Description 
+    ///
+    /// Restricts which plugin libraries may be loaded. Every library found by
+    /// later loads and refreshes is checked against the policy, whether or not
+    /// it has a manifest or cached capabilities, and libraries the policy refuses
+    /// are rejected with `RejectionReason::Untrusted` without ever being opened.
+    ///
+    /// #Arguments
+    ///
+    /// * `trust_policy` (IN) The [TrustPolicy](../../cuckoo_sys/trust/struct.TrustPolicy.html)
+    /// libraries must pass, or None to load any library.
+    ///
+    /// #Returns
+    ///
+    /// Nothing
+    ///
+
+    pub fn set_trust_policy(&mut self, trust_policy:Option<TrustPolicy>) {
+        self.trust_policy=trust_policy;
+        //rejections may no longer apply
+        self.rejected_plugins.clear();
+    }
+
+    /// Returns a report of the plugins found by the last load or refresh,
+    /// and the files it rejected, even if no good plugins were found
+
+    pub fn get_discovery_report(&self) -> DiscoveryReport {
+        DiscoveryReport {
+            plugins: self.current_plugin_caps.clone().unwrap_or(Vec::new()),
+            rejected: self.rejected.clone(),
+        }
     }
 
     /// #Description 
@@ -211,7 +920,8 @@ impl CuckooPluginManager {
     /// representing the currently
     /// installed plugins in the currently loaded directory. Can optionally take a filter,
     /// which will limit the returned plugins to those with the occurrence of a
-    /// particular string in their name.
+    /// particular string in their name. Plugins which can't run on this host are
+    /// left out, see [get_unsupported_plugins](#method.get_unsupported_plugins).
     ///
     /// #Arguments
     ///
@@ -224,6 +934,8 @@ impl CuckooPluginManager {
     /// If successful, a Result containing a vector of 
     /// [CuckooPluginCapabilities](struct.CuckooPluginCapabilities.html) , 
     /// one for each plugin successfully read from the plugin directory, filtered as requested.
+    /// Plugins benchmarked on this host by [benchmark_plugins](#method.benchmark_plugins)
+    /// come first, fastest first, followed by the rest in the order they were found.
     /// If there is an error loading plugins from the given directory,
     /// a [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
     /// will be returned outlining more specific details.
@@ -242,10 +954,14 @@ impl CuckooPluginManager {
 
     pub fn get_available_plugins(&mut self, filter:&str) -> 
         Result<Vec<CuckooPluginCapabilities>, CuckooMinerError>{
+            let supported = self.current_plugin_caps.as_mut().unwrap().clone().into_iter()
+                .filter(|i| i.unsupported_reason.is_none());
             if filter.len()==0 {
-                return Ok(self.current_plugin_caps.as_mut().unwrap().clone());
+                let mut result = supported.collect();
+                self.rank_by_benchmark(&mut result);
+                return Ok(result);
             } else {
-                let result = self.current_plugin_caps.as_mut().unwrap().clone().into_iter().filter(
+                let mut result = supported.filter(
                     |ref i| {
                         let re = Regex::new(&format!(r"{}",filter)).unwrap();
                         let caps = re.captures(&i.full_path);
@@ -257,85 +973,466 @@ impl CuckooPluginManager {
                 if result.len()==0{
                     return Err(CuckooMinerError::NoPluginsFoundError(format!("For given filter: {}", filter)));
                 }
+                self.rank_by_benchmark(&mut result);
                 return Ok(result);
             }
     }
 
+    // Sorts benchmarked plugins first, fastest first, leaving the rest
+    // in their current order
+
+    fn rank_by_benchmark(&self, caps:&mut Vec<CuckooPluginCapabilities>) {
+        caps.sort_by(|a, b| {
+            let score_a = self.get_benchmark(&a.full_path).map(|r| r.graphs_per_sec());
+            let score_b = self.get_benchmark(&b.full_path).map(|r| r.graphs_per_sec());
+            match (score_a, score_b) {
+                (Some(a), Some(b)) => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            }
+        });
+    }
+
+    /// #Description 
+    ///
+    /// Benchmarks the currently available plugins on this host, running each
+    /// for a bounded time via both `cuckoo_call` and the async queue, as used
+    /// by `mine` and `notify` respectively. The results are kept, and saved to
+    /// the benchmark file if there is one, so that later calls to
+    /// [get_available_plugins](#method.get_available_plugins) rank plugins by
+    /// how fast they actually are here. Results for a plugin are used until
+    /// the plugin is rebuilt or benchmarked again. If the manager has a trust
+    /// policy, each plugin is checked against it as it's loaded to be benchmarked,
+    /// and its results are kept against the contents which were checked.
+    ///
+    /// #Arguments
+    ///
+    /// * `filter` As for [get_available_plugins](#method.get_available_plugins),
+    /// which plugins to benchmark.
+    ///
+    /// * `config` (IN) How to benchmark, see
+    /// [BenchmarkConfig](../benchmark/struct.BenchmarkConfig.html).
+    ///
+    /// #Returns
+    ///
+    /// The results for each plugin benchmarked. Plugins which fail while being
+    /// benchmarked are logged and left out, or a `NoPluginsFoundError` is returned
+    /// if no plugins match the filter.
+    ///
+
+    pub fn benchmark_plugins(&mut self, filter:&str, config:&BenchmarkConfig)
+        -> Result<Vec<PluginBenchmark>, CuckooMinerError> {
+        let caps=self.get_available_plugins(filter)?;
+        let host=host_id();
+        let mut results=Vec::new();
+        for c in caps {
+            match run_benchmark(&c.full_path, config, self.trust_policy.as_ref()) {
+                Ok(b) => {
+                    debug!("Benchmarked {}", b);
+                    self.benchmarks.hosts.entry(host.clone()).or_insert_with(HashMap::new)
+                        .insert(c.full_path.clone(), b.clone());
+                    results.push(b);
+                },
+                Err(e) => warn!("Unable to benchmark plugin {}: {:?}", c.full_path, e),
+            }
+        }
+        self.save_benchmarks();
+        Ok(results)
+    }
+
+    /// Returns the result of the last benchmark of the given plugin on this
+    /// host, if it's been benchmarked since it was last changed
+
+    pub fn get_benchmark(&self, full_path:&str) -> Option<&PluginBenchmark> {
+        let result=self.benchmarks.hosts.get(&host_id())?.get(full_path)?;
+        match self.known_plugins.get(full_path) {
+            Some(k) if k.hash==result.hash => Some(result),
+            _ => None,
+        }
+    }
+
+    /// #Description 
+    ///
+    /// Sets the file benchmark results are kept in between runs, and loads any
+    /// results already in it. The file can be shared between hosts, as results
+    /// are kept separately for each. A missing or invalid file is ignored, and
+    /// replaced the next time plugins are benchmarked.
+    ///
+    /// #Arguments
+    ///
+    /// * `benchmark_file` (IN) The path to the benchmark file.
+    ///
+    /// #Returns
+    ///
+    /// Nothing
+    ///
+
+    pub fn set_benchmark_file(&mut self, benchmark_file:&str) {
+        self.benchmark_file=Some(String::from(benchmark_file));
+        let mut contents=String::new();
+        if let Err(e) = File::open(benchmark_file).and_then(|mut f| f.read_to_string(&mut contents)) {
+            debug!("Unable to read plugin benchmarks {}: {}", benchmark_file, e);
+            return;
+        }
+        match serde_json::from_str::<BenchmarkFile>(&contents) {
+            Ok(ref b) if b.version!=BENCHMARK_FILE_VERSION => {
+                debug!("Ignoring plugin benchmarks {} with version {}", benchmark_file, b.version);
+            },
+            Ok(b) => {
+                for (host, results) in b.hosts {
+                    let known=self.benchmarks.hosts.entry(host).or_insert_with(HashMap::new);
+                    for (path, result) in results {
+                        known.entry(path).or_insert(result);
+                    }
+                }
+            },
+            Err(e) => warn!("Ignoring invalid plugin benchmarks {}: {}", benchmark_file, e),
+        }
+    }
+
+    // Writes the benchmark results to the benchmark file, if there is one
+
+    fn save_benchmarks(&self) {
+        let benchmark_file=match self.benchmark_file {
+            Some(ref f) => f,
+            None => return,
+        };
+        //write alongside and rename, so readers never see a partial file
+        let temp_file=format!("{}.tmp", benchmark_file);
+        let result=serde_json::to_string(&self.benchmarks)
+            .map_err(|e| e.to_string())
+            .and_then(|json| File::create(&temp_file)
+                .and_then(|mut f| f.write_all(json.as_bytes()))
+                .and_then(|_| fs::rename(&temp_file, benchmark_file))
+                .map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            warn!("Unable to write plugin benchmarks {}: {}", benchmark_file, e);
+        }
+    }
+
+    /// #Description 
+    ///
+    /// Returns the plugins in the currently loaded directory matching a
+    /// structured [PluginQuery](../query/struct.PluginQuery.html), in the
+    /// order the query asks for. As with
+    /// [get_available_plugins](#method.get_available_plugins), plugins which
+    /// can't run on this host are left out.
+    ///
+    /// #Arguments
+    ///
+    /// * `query` (IN) The conditions plugins must meet, and how to sort them.
+    ///
+    /// #Returns
+    ///
+    /// If successful, a Result containing a vector of matching
+    /// [CuckooPluginCapabilities](struct.CuckooPluginCapabilities.html).
+    /// If no plugins match, a `NoPluginsFoundError` is returned.
+    ///
+    /// #Example
+    ///
+    /// ```
+    /// let query = PluginQuery::new()
+    ///     .variation("tomato")
+    ///     .requires_capabilities(PluginCapabilities::ASYNC_QUEUE)
+    ///     .sort_by_descending(PluginSortKey::CuckooSize);
+    /// let caps = manager.query_plugins(&query)?;
+    /// ```
+    ///
+
+    pub fn query_plugins(&self, query:&PluginQuery)
+        -> Result<Vec<CuckooPluginCapabilities>, CuckooMinerError> {
+            let supported = self.current_plugin_caps.iter().flat_map(|c| c.iter())
+                .filter(|c| c.unsupported_reason.is_none())
+                .cloned()
+                .collect();
+            let result = query.apply(supported);
+            if result.len()==0 {
+                return Err(CuckooMinerError::NoPluginsFoundError(
+                    format!("For given query: {:?}", query)));
+            }
+            Ok(result)
+    }
+
+    /// #Description 
+    ///
+    /// Returns the plugins in the currently loaded directory which can't run on
+    /// this host, each with the reason in its `unsupported_reason`. These are
+    /// never loaded, and aren't returned by
+    /// [get_available_plugins](#method.get_available_plugins).
+    ///
+    /// #Arguments
+    ///
+    /// None
+    ///
+    /// #Returns
+    ///
+    /// A vector of [CuckooPluginCapabilities](struct.CuckooPluginCapabilities.html),
+    /// empty if every plugin can run.
+    ///
+
+    pub fn get_unsupported_plugins(&self) -> Vec<CuckooPluginCapabilities> {
+        self.current_plugin_caps.iter().flat_map(|c| c.iter())
+            .filter(|c| c.unsupported_reason.is_some())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the capabilities of the plugin at the given path as last queried,
+    /// if it hasn't changed since or is in use, otherwise queries it
+
+    fn cached_plugin_caps(&mut self, full_path:String)
+        -> Result<CuckooPluginCapabilities, CuckooMinerError> {
+            let stamp=match plugin_stamp(&full_path) {
+                Some(s) => s,
+                None => return self.load_plugin_caps(full_path),
+            };
+            let mut hash=None;
+            if let Some(k) = self.known_plugins.get_mut(&full_path) {
+                if k.stamp==stamp {
+                    return Ok(k.caps.clone());
+                }
+                //touched or reinstalled, but the same library
+                if k.stamp.size==stamp.size && k.stamp.manifest_modified==stamp.manifest_modified {
+                    hash=content_hash(&full_path);
+                    if hash.as_ref()==Some(&k.hash) {
+                        k.stamp=stamp;
+                        return Ok(k.caps.clone());
+                    }
+                }
+                if plugin_in_use(&full_path) {
+                    debug!("Plugin {} has changed but is in use, not querying it again", full_path);
+                    return Ok(k.caps.clone());
+                }
+            }
+            let caps=self.load_plugin_caps(full_path.clone())?;
+            self.query_count+=1;
+            self.known_plugins.insert(full_path.clone(), KnownPlugin {
+                stamp: stamp,
+                hash: hash.or_else(|| content_hash(&full_path)).unwrap_or(String::new()),
+                caps: caps.clone(),
+                generation: self.query_count,
+            });
+            Ok(caps)
+    }
+
     /// Fills out and Returns a CuckooPluginCapabilities structure parsed from a
-    /// call to cuckoo_description in the currently loaded plugin
+    /// call to cuckoo_description in the plugin at the given path, which is
+    /// loaded via its own handle for the duration of the query. Plugins
+    /// implementing an unsupported interface version are rejected before
+    /// any of their functions are called. If the plugin has a manifest, it's
+    /// read from that instead, and the plugin itself isn't loaded at all.
 
     fn load_plugin_caps(&mut self, full_path:String) 
         -> Result<CuckooPluginCapabilities, CuckooMinerError> {
+            if let Some(manifest_path) = CuckooPluginManifest::find(Path::new(&full_path)) {
+                return self.manifest_plugin_caps(full_path, &manifest_path);
+            }
             debug!("Querying plugin at {}", full_path );
             let mut caps=CuckooPluginCapabilities::default();
-            load_cuckoo_lib(&full_path)?;
-            let mut name_bytes:[u8;256]=[0;256];
-            let mut description_bytes:[u8;256]=[0;256];
-            let mut name_len=name_bytes.len() as u32;
-            let mut desc_len=description_bytes.len() as u32;
-            call_cuckoo_description(&mut name_bytes, &mut name_len, 
-                                    &mut description_bytes, &mut desc_len)?;
-            
-            let mut name_vec:Vec<u8> = Vec::new();
-            for i in 0..name_len {
-                name_vec.push(name_bytes[i as usize].clone());
-            }
-            
-            let mut desc_vec:Vec<u8> = Vec::new();
-            for i in 0..desc_len {
-                desc_vec.push(description_bytes[i as usize].clone());
-            }
-            
-            caps.name=String::from_utf8(name_vec)?;
-            caps.description=String::from_utf8(desc_vec)?;
+            //a plugin in use, e.g. by a running miner, isn't initialised again
+            let plugin = PluginHandle::query(&full_path, self.trust_policy.as_ref())?;
+            caps.capabilities=plugin.capabilities();
+            caps.abi_version=plugin.abi_version();
+            caps.proof_size=plugin.proof_size();
             caps.full_path=full_path.clone();
             caps.file_name=String::from("");
 
-            let mut param_list_bytes:[u8;1024]=[0;1024];
-            let mut param_list_len=param_list_bytes.len() as u32;
-            //get a list of parameters
-            call_cuckoo_parameter_list(&mut param_list_bytes, &mut param_list_len)?;
-            let mut param_list_vec:Vec<u8> = Vec::new();
-            //result contains null zero
-            for i in 0..param_list_len {
-                param_list_vec.push(param_list_bytes[i as usize].clone());
+            //description is optional, fall back to the library's file name
+            if !self.describe_plugin(&plugin, &mut caps)? {
+                caps.name=Path::new(&full_path).file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or(String::new());
             }
-            let param_list_json=String::from_utf8(param_list_vec)?;
-            caps.parameters = serde_json::from_str(&param_list_json).unwrap();
 
-            unload_cuckoo_lib();
+            //parameters are optional too
+            if plugin.capabilities().contains(PluginCapabilities::PARAMETERS) {
+                caps.parameters=self.plugin_parameters(&plugin)?;
+            }
+
+            //as is metadata, otherwise go by the library's file name
+            if !self.read_plugin_metadata(&plugin, &mut caps)? {
+                parse_file_name(&full_path, &mut caps);
+            }
+
+            //unloads the plugin
+            drop(plugin);
 
             return Ok(caps);
     }
 
-    /// Loads and fills out the internal plugin capabilites vector from the
-    /// given directory.
+    /// Fills out a CuckooPluginCapabilities structure from the manifest
+    /// at the given path, without loading the plugin
+
+    fn manifest_plugin_caps(&self, full_path:String, manifest_path:&Path)
+        -> Result<CuckooPluginCapabilities, CuckooMinerError> {
+            debug!("Reading plugin manifest at {}", manifest_path.display());
+            let manifest=CuckooPluginManifest::from_file(manifest_path)?;
+            let mut caps=CuckooPluginCapabilities::default();
+            caps.name=manifest.name.clone();
+            if caps.name.is_empty() {
+                caps.name=Path::new(&full_path).file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or(String::new());
+            }
+            caps.description=manifest.description.clone();
+            caps.full_path=full_path;
+            caps.parameters=manifest.parameters.clone();
+            caps.capabilities=manifest.plugin_capabilities()
+                .map_err(CuckooMinerError::PluginManifestError)?;
+            caps.variation=manifest.variation;
+            caps.cuckoo_size=manifest.cuckoo_size;
+            caps.proof_size=manifest.proof_size;
+            caps.easiness=manifest.easiness;
+            caps.memory_required=manifest.memory_required;
+            caps.cpu_features=manifest.cpu_features;
+            caps.manifest_path=Some(manifest_path.to_string_lossy().into_owned());
+            Ok(caps)
+    }
+
+    /// Fills out the name and description from the plugin's cuckoo_description
+    /// function, returning false if the plugin doesn't provide one
+
+    fn describe_plugin(&self, plugin:&PluginHandle, caps:&mut CuckooPluginCapabilities) 
+        -> Result<bool, CuckooMinerError> {
+            let mut name_bytes:Vec<u8>=Vec::new();
+            let mut description_bytes:Vec<u8>=Vec::new();
+            match plugin.call_cuckoo_description(&mut name_bytes, &mut description_bytes) {
+                Err(CuckooMinerError::PluginUnsupportedError(_)) => return Ok(false),
+                Err(e) => return Err(e),
+                Ok(_) => {},
+            }
+            
+            caps.name=String::from_utf8(name_bytes)?;
+            caps.description=String::from_utf8(description_bytes)?;
+            Ok(true)
+    }
+
+    /// Fills out the variation, size and easiness from the plugin's cuckoo_metadata
+    /// function, returning false if the plugin doesn't provide one
+
+    fn read_plugin_metadata(&self, plugin:&PluginHandle, caps:&mut CuckooPluginCapabilities)
+        -> Result<bool, CuckooMinerError> {
+            let mut metadata_bytes:Vec<u8>=Vec::new();
+            let result=match plugin.call_cuckoo_metadata(&mut metadata_bytes) {
+                Err(CuckooMinerError::PluginUnsupportedError(_)) => return Ok(false),
+                Err(e) => return Err(e),
+                Ok(r) => r,
+            };
+            if result!=0 {
+                return Err(CuckooMinerError::UnexpectedResultError(result));
+            }
+            let metadata_json=String::from_utf8(metadata_bytes)?;
+            let metadata:CuckooPluginMetadata=serde_json::from_str(&metadata_json).map_err(|e| {
+                CuckooMinerError::PluginJsonError(format!(
+                    "Invalid metadata from plugin {}: {}", plugin.full_path(), e))
+            })?;
+            caps.variation=metadata.variation;
+            if metadata.edge_bits>0 {
+                caps.cuckoo_size=metadata.edge_bits+1;
+            }
+            caps.easiness=metadata.easiness;
+            Ok(true)
+    }
+
+    /// Reads the plugin's list of parameters via its cuckoo_parameter_list function
+
+    fn plugin_parameters(&self, plugin:&PluginHandle) 
+        -> Result<Vec<CuckooPluginParameter>, CuckooMinerError> {
+            let mut param_list_bytes:Vec<u8>=Vec::new();
+            //get a list of parameters
+            let result=plugin.call_cuckoo_parameter_list(&mut param_list_bytes)?;
+            if result!=0 {
+                return Err(CuckooMinerError::UnexpectedResultError(result));
+            }
+            let param_list_json=String::from_utf8(param_list_bytes)?;
+            serde_json::from_str(&param_list_json).map_err(|e| {
+                CuckooMinerError::PluginJsonError(format!(
+                    "Invalid parameter list from plugin {}: {}", plugin.full_path(), e))
+            })
+    }
+
+    /// Loads the capabilities of every plugin in the given directory,
+    /// rejecting any which can't be loaded or queried
     ///
     ///
 
-    fn load_all_plugin_caps(&mut self, plugin_dir: &str) 
-            -> Result<Vec<CuckooPluginCapabilities>,CuckooMinerError>{
+    fn load_all_plugin_caps(&mut self, plugin_dir: &str, source: PluginSource) 
+            -> Result<DiscoveryReport,CuckooMinerError>{
         let lib_full_path = abspath(Path::new(&plugin_dir));
         let glob_search_path = format!("{}/*cuckoo*{}", lib_full_path, DLL_SUFFIX);
 
-        let mut result_vec:Vec<CuckooPluginCapabilities> = Vec::new();
-        
-        for entry in glob(&glob_search_path).expect("Failed to read glob pattern") {
-            match entry {
-                Ok(path) => {
-                    let caps = self.load_plugin_caps(String::from(path.to_str().unwrap()))?;
-                    result_vec.push(caps);
+        let mut report = DiscoveryReport::default();
+        let entries = glob(&glob_search_path).map_err(|e| {
+            CuckooMinerError::PluginIOError(format!("Can't search plugin directory {}: {}", lib_full_path, e))
+        })?;
+        for entry in entries {
+            let full_path = match entry {
+                Ok(ref path) => match path.to_str() {
+                    Some(p) => String::from(p),
+                    None => {
+                        report.rejected.push(self.reject(path.to_string_lossy().into_owned(),
+                            RejectionReason::BadUtf8(String::from("file name isn't valid UTF-8"))));
+                        continue;
+                    },
                 },
-                Err(e) => error!("{:?}", e),
+                Err(e) => {
+                    report.rejected.push(self.reject(e.path().to_string_lossy().into_owned(),
+                        RejectionReason::Unreadable(e.error().to_string())));
+                    continue;
+                },
+            };
+
+            //don't load a rejected plugin again until it changes
+            let stamp = plugin_stamp(&full_path);
+            if let Some(&(ref s, ref r)) = self.rejected_plugins.get(&full_path) {
+                if *s==stamp {
+                    debug!("Plugin {} is unchanged since it was rejected: {}", full_path, r);
+                    report.rejected.push(RejectedPlugin {
+                        full_path: full_path.clone(),
+                        reason: r.clone(),
+                    });
+                    continue;
+                }
             }
+            let trusted = match self.trust_policy {
+                Some(ref p) => p.verify(&full_path),
+                None => Ok(()),
+            };
+            let caps = trusted.and_then(|_| self.cached_plugin_caps(full_path.clone()));
+            let mut caps = match caps {
+                Ok(c) => c,
+                Err(e) => {
+                    let rejected = self.reject(full_path.clone(), RejectionReason::from(e));
+                    self.rejected_plugins.insert(full_path, (stamp, rejected.reason.clone()));
+                    report.rejected.push(rejected);
+                    continue;
+                },
+            };
+            self.rejected_plugins.remove(&full_path);
+            caps.plugin_dir = lib_full_path.clone();
+            caps.source = source;
+            caps.shadowed = Vec::new();
+            //checked every time, as cached capabilities may be from another host
+            let missing = missing_cpu_features(&caps.cpu_features);
+            caps.unsupported_reason = if missing.is_empty() {
+                None
+            } else {
+                Some(format!("Host CPU doesn't support required features: {}", missing.join(", ")))
+            };
+            report.plugins.push(caps);
         }
 
-        if result_vec.len()==0 {
-            return Err(CuckooMinerError::NoPluginsFoundError(
-                format!("No plugins found in plugin directory {}", lib_full_path.clone())
-                ));
-        }
+        Ok(report)
+    }
 
-        Ok(result_vec)
+    // Logs a rejected plugin
+
+    fn reject(&self, full_path: String, reason: RejectionReason) -> RejectedPlugin {
+        warn!("Rejected plugin {}: {}", full_path, reason);
+        RejectedPlugin {
+            full_path: full_path,
+            reason: reason,
+        }
     }
 }
@@ -0,0 +1,184 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Plugin manifests, sidecar files which describe a plugin so that the
+//! plugin manager can discover it without loading the library itself (and
+//! so without running any of the plugin's initialisation code).
+//!
+//! A manifest sits next to its plugin, with the same file name but a `.toml`
+//! or `.json` extension in place of the library suffix, e.g.
+//! `libcuckoo_mean_30.toml` next to `libcuckoo_mean_30.so`. Every field is
+//! optional, and a plugin with no name is named after its file.
+//!
+//! #Example
+//! ```toml
+//!  name = "cuckoo_mean_30"
+//!  description = "Mean miner, Cuckoo size 30"
+//!  variation = "mean"
+//!  cuckoo_size = 30
+//!  proof_size = 42
+//!  easiness = 50
+//!  memory_required = 3221225472
+//!  cpu_features = ["avx2"]
+//!  capabilities = ["sync", "async_queue", "parameters", "hash_metrics"]
+//!
+//!  [[parameters]]
+//!  name = "NUM_THREADS"
+//!  description = "Number of threads"
+//!  default_value = 1
+//!  min_value = 1
+//!  max_value = 32
+//! ```
+
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json;
+use toml;
+
+use cuckoo_sys::{PluginCapabilities, DEFAULT_PROOF_SIZE, MAX_PROOF_SIZE};
+use error::CuckooMinerError;
+use manager::{CuckooPluginParameter, CuckooVariation, parse_capabilities};
+
+// Manifest file extensions, in the order they're looked for
+
+const MANIFEST_EXTENSIONS: [&str; 2] = ["toml", "json"];
+
+/// A plugin's description, as read from its manifest file
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct CuckooPluginManifest {
+    /// The plugin's descriptive name
+    pub name: String,
+
+    /// The plugin's description
+    pub description: String,
+
+    /// The cuckoo algorithm variation the plugin implements,
+    /// e.g. simple, edgetrim, tomato or mean
+    pub variation: CuckooVariation,
+
+    /// The size of the cuckoo graph, as a power of 2
+    pub cuckoo_size: u32,
+
+    /// The number of nonces in each solution
+    pub proof_size: u32,
+
+    /// The percentage of the graph's nodes used as edges
+    pub easiness: u32,
+
+    /// The memory the plugin needs, in bytes
+    pub memory_required: u64,
+
+    /// CPU features the plugin was built to use, e.g. avx2. Plugins
+    /// requiring features the host doesn't support are never loaded
+    pub cpu_features: Vec<String>,
+
+    /// Which modes and functions the plugin supports, named as in
+    /// [PluginCapabilities](../../cuckoo_sys/struct.PluginCapabilities.html)
+    pub capabilities: Vec<String>,
+
+    /// The plugin's parameters
+    pub parameters: Vec<CuckooPluginParameter>,
+}
+
+impl Default for CuckooPluginManifest {
+    fn default() -> CuckooPluginManifest {
+        CuckooPluginManifest {
+            name: String::from(""),
+            description: String::from(""),
+            variation: CuckooVariation::Unknown,
+            cuckoo_size: 0,
+            proof_size: DEFAULT_PROOF_SIZE,
+            easiness: 0,
+            memory_required: 0,
+            cpu_features: Vec::new(),
+            capabilities: Vec::new(),
+            parameters: Vec::new(),
+        }
+    }
+}
+
+impl CuckooPluginManifest {
+
+    /// #Description
+    ///
+    /// Looks for the manifest of the plugin library at the given path.
+    ///
+    /// #Arguments
+    ///
+    /// * `plugin_path` (IN) The path to the plugin library.
+    ///
+    /// #Returns
+    ///
+    /// The path to the plugin's manifest, or None if it doesn't have one.
+    ///
+
+    pub fn find(plugin_path: &Path) -> Option<PathBuf> {
+        MANIFEST_EXTENSIONS.iter()
+            .map(|e| plugin_path.with_extension(e))
+            .find(|p| p.is_file())
+    }
+
+    /// #Description
+    ///
+    /// Reads and validates a manifest file, parsed as TOML or JSON depending
+    /// on its extension.
+    ///
+    /// #Arguments
+    ///
+    /// * `path` (IN) The path to the manifest file.
+    ///
+    /// #Returns
+    ///
+    /// Ok with the manifest if successful, or a `PluginManifestError` if the
+    /// file can't be read or parsed, or describes an invalid plugin.
+    ///
+
+    pub fn from_file(path: &Path) -> Result<CuckooPluginManifest, CuckooMinerError> {
+        let mut contents = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut contents))
+            .map_err(|e| manifest_error(path, &e.to_string()))?;
+
+        let manifest: CuckooPluginManifest = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => serde_json::from_str(&contents)
+                .map_err(|e| manifest_error(path, &e.to_string()))?,
+            _ => toml::from_str(&contents)
+                .map_err(|e| manifest_error(path, &e.to_string()))?,
+        };
+
+        if manifest.proof_size == 0 || manifest.proof_size % 2 != 0
+            || manifest.proof_size > MAX_PROOF_SIZE {
+            return Err(manifest_error(path,
+                &format!("unsupported proof size {}", manifest.proof_size)));
+        }
+        manifest.plugin_capabilities().map_err(|e| manifest_error(path, &e))?;
+        Ok(manifest)
+    }
+
+    /// Returns the set of capabilities named in the manifest, or the
+    /// first unknown name
+
+    pub fn plugin_capabilities(&self) -> Result<PluginCapabilities, String> {
+        parse_capabilities(&self.capabilities)
+    }
+}
+
+fn manifest_error(path: &Path, detail: &str) -> CuckooMinerError {
+    CuckooMinerError::PluginManifestError(
+        format!("Invalid plugin manifest {}: {}", path.display(), detail))
+}
@@ -0,0 +1,197 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Structured queries over the plugins found by the
+//! [CuckooPluginManager](../manager/struct.CuckooPluginManager.html), for
+//! picking a plugin by what it does rather than by its file name.
+//!
+//! #Example
+//! ```
+//!  //async-capable cuckoo30 plugins that fit in 4 GB, smallest first
+//!  let query = PluginQuery::new()
+//!      .cuckoo_size_range(30, 30)
+//!      .requires_capabilities(PluginCapabilities::ASYNC_QUEUE)
+//!      .max_memory(4 << 30)
+//!      .sort_by(PluginSortKey::MemoryRequired);
+//!  let caps = plugin_manager.query_plugins(&query)?;
+//! ```
+
+use std::cmp::Ordering;
+
+use cuckoo_sys::PluginCapabilities;
+use manager::{CuckooPluginCapabilities, CuckooVariation};
+
+/// Keys that query results can be sorted by
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginSortKey {
+    /// The plugin's name
+    Name,
+
+    /// The size of the cuckoo graph
+    CuckooSize,
+
+    /// The memory the plugin needs
+    MemoryRequired,
+}
+
+/// A set of conditions that plugins must all meet, and the order to
+/// return them in. Built up by chaining calls from
+/// [new](#method.new), which matches every plugin.
+
+#[derive(Debug, Clone)]
+pub struct PluginQuery {
+    variation: Option<CuckooVariation>,
+    min_cuckoo_size: Option<u32>,
+    max_cuckoo_size: Option<u32>,
+    parameters: Vec<String>,
+    capabilities: PluginCapabilities,
+    max_memory: Option<u64>,
+    sort_key: PluginSortKey,
+    descending: bool,
+}
+
+impl Default for PluginQuery {
+    fn default() -> PluginQuery {
+        PluginQuery {
+            variation: None,
+            min_cuckoo_size: None,
+            max_cuckoo_size: None,
+            parameters: Vec::new(),
+            capabilities: PluginCapabilities::empty(),
+            max_memory: None,
+            sort_key: PluginSortKey::Name,
+            descending: false,
+        }
+    }
+}
+
+impl PluginQuery {
+
+    /// Returns a query matching every plugin, sorted by name
+
+    pub fn new() -> PluginQuery {
+        PluginQuery::default()
+    }
+
+    /// Only match plugins implementing the given algorithm variation,
+    /// e.g. simple, edgetrim, tomato or mean
+
+    pub fn variation<V: Into<CuckooVariation>>(mut self, variation: V) -> PluginQuery {
+        self.variation = Some(variation.into());
+        self
+    }
+
+    /// Only match plugins whose cuckoo size is within the given range,
+    /// inclusive. Plugins whose size isn't known never match
+
+    pub fn cuckoo_size_range(mut self, min: u32, max: u32) -> PluginQuery {
+        self.min_cuckoo_size = Some(min);
+        self.max_cuckoo_size = Some(max);
+        self
+    }
+
+    /// Only match plugins which take the given parameter. Can be called
+    /// more than once to require several
+
+    pub fn requires_parameter(mut self, name: &str) -> PluginQuery {
+        self.parameters.push(String::from(name));
+        self
+    }
+
+    /// Only match plugins supporting all of the given capabilities, e.g.
+    /// `PluginCapabilities::ASYNC_QUEUE` for plugins which can be used with
+    /// `notify`
+
+    pub fn requires_capabilities(mut self, capabilities: PluginCapabilities) -> PluginQuery {
+        self.capabilities.insert(capabilities);
+        self
+    }
+
+    /// Only match plugins needing at most the given memory, in bytes.
+    /// Plugins whose memory requirement isn't known never match
+
+    pub fn max_memory(mut self, bytes: u64) -> PluginQuery {
+        self.max_memory = Some(bytes);
+        self
+    }
+
+    /// Sorts results by the given key, in ascending order
+
+    pub fn sort_by(mut self, key: PluginSortKey) -> PluginQuery {
+        self.sort_key = key;
+        self.descending = false;
+        self
+    }
+
+    /// Sorts results by the given key, in descending order
+
+    pub fn sort_by_descending(mut self, key: PluginSortKey) -> PluginQuery {
+        self.sort_key = key;
+        self.descending = true;
+        self
+    }
+
+    /// Whether the given plugin meets every condition of the query
+
+    pub fn matches(&self, caps: &CuckooPluginCapabilities) -> bool {
+        if let Some(ref v) = self.variation {
+            if caps.variation != *v {
+                return false;
+            }
+        }
+        if self.min_cuckoo_size.is_some() || self.max_cuckoo_size.is_some() {
+            if caps.cuckoo_size == 0
+                || caps.cuckoo_size < self.min_cuckoo_size.unwrap_or(0)
+                || caps.cuckoo_size > self.max_cuckoo_size.unwrap_or(u32::max_value()) {
+                return false;
+            }
+        }
+        if !self.parameters.iter().all(|p| caps.parameters.iter().any(|cp| &cp.name == p)) {
+            return false;
+        }
+        if !caps.capabilities.contains(self.capabilities) {
+            return false;
+        }
+        if let Some(m) = self.max_memory {
+            if caps.memory_required == 0 || caps.memory_required > m {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Filters and sorts the given plugins according to the query
+
+    pub fn apply(&self, caps: Vec<CuckooPluginCapabilities>) -> Vec<CuckooPluginCapabilities> {
+        let mut result = caps.into_iter().filter(|c| self.matches(c)).collect::<Vec<_>>();
+        result.sort_by(|a, b| {
+            let order = self.compare(a, b);
+            if self.descending { order.reverse() } else { order }
+        });
+        result
+    }
+
+    // Compares two plugins by the sort key, falling back to their
+    // names so the order is stable
+
+    fn compare(&self, a: &CuckooPluginCapabilities, b: &CuckooPluginCapabilities) -> Ordering {
+        let order = match self.sort_key {
+            PluginSortKey::Name => Ordering::Equal,
+            PluginSortKey::CuckooSize => a.cuckoo_size.cmp(&b.cuckoo_size),
+            PluginSortKey::MemoryRequired => a.memory_required.cmp(&b.memory_required),
+        };
+        order.then_with(|| a.name.cmp(&b.name))
+    }
+}
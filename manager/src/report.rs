@@ -0,0 +1,132 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Reports of what plugin discovery found. A plugin which can't be loaded
+//! or queried doesn't stop discovery; it's left out of the available plugins
+//! and listed in the report with the reason it was rejected.
+//!
+//! #Example
+//! ```
+//!  let report = plugin_manager.load_plugin_dir(String::from("target/debug"))?;
+//!  for r in &report.rejected {
+//!      println!("Skipped {}: {}", r.full_path, r.reason);
+//!  }
+//! ```
+
+use std::fmt;
+
+use error::CuckooMinerError;
+use manager::CuckooPluginCapabilities;
+
+/// Why a plugin was rejected during discovery
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RejectionReason {
+    /// The library doesn't export the functions every plugin must
+    MissingSymbol(String),
+
+    /// The plugin returned a string which isn't valid UTF-8, or its
+    /// file name isn't valid UTF-8
+    BadUtf8(String),
+
+    /// The plugin returned malformed JSON, e.g. its parameter list
+    BadJson(String),
+
+    /// The library couldn't be loaded or initialised, e.g. because it
+    /// isn't a library, implements an unsupported interface version or
+    /// returned an unexpected result while being queried
+    InitFailed(String),
+
+    /// The plugin's manifest couldn't be read or is invalid
+    InvalidManifest(String),
+
+    /// The file couldn't be read
+    Unreadable(String),
+
+    /// The library was refused by the trust policy, and never loaded
+    Untrusted(String),
+}
+
+impl From<CuckooMinerError> for RejectionReason {
+    fn from(error: CuckooMinerError) -> Self {
+        match error {
+            CuckooMinerError::PluginSymbolError(s) => RejectionReason::MissingSymbol(s),
+            CuckooMinerError::PluginUtf8Error(s) => RejectionReason::BadUtf8(s),
+            CuckooMinerError::PluginJsonError(s) => RejectionReason::BadJson(s),
+            CuckooMinerError::PluginManifestError(s) => RejectionReason::InvalidManifest(s),
+            CuckooMinerError::PluginIOError(s) => RejectionReason::Unreadable(s),
+            CuckooMinerError::PluginUntrustedError(s) => RejectionReason::Untrusted(s),
+            CuckooMinerError::PluginNotFoundError(s) |
+            CuckooMinerError::PluginAbiVersionError(s) |
+            CuckooMinerError::PluginUnsupportedError(s) => RejectionReason::InitFailed(s),
+            e => RejectionReason::InitFailed(format!("{:?}", e)),
+        }
+    }
+}
+
+impl fmt::Display for RejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RejectionReason::MissingSymbol(ref s) => write!(f, "missing symbol: {}", s),
+            RejectionReason::BadUtf8(ref s) => write!(f, "bad UTF-8: {}", s),
+            RejectionReason::BadJson(ref s) => write!(f, "bad JSON: {}", s),
+            RejectionReason::InitFailed(ref s) => write!(f, "init failure: {}", s),
+            RejectionReason::InvalidManifest(ref s) => write!(f, "invalid manifest: {}", s),
+            RejectionReason::Unreadable(ref s) => write!(f, "unreadable: {}", s),
+            RejectionReason::Untrusted(ref s) => write!(f, "untrusted: {}", s),
+        }
+    }
+}
+
+/// A file which looked like a plugin, but was rejected
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedPlugin {
+    /// The full path of the file
+    pub full_path: String,
+
+    /// Why it was rejected
+    pub reason: RejectionReason,
+}
+
+/// What a plugin directory or search path scan found
+
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryReport {
+    /// The plugins found, including any which can't run on this host
+    pub plugins: Vec<CuckooPluginCapabilities>,
+
+    /// Every file rejected, in the order found
+    pub rejected: Vec<RejectedPlugin>,
+}
+
+impl DiscoveryReport {
+    /// Whether every file found was a good plugin
+    pub fn is_clean(&self) -> bool {
+        self.rejected.is_empty()
+    }
+}
+
+impl fmt::Display for DiscoveryReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} plugin(s) found, {} rejected", self.plugins.len(), self.rejected.len())?;
+        for p in &self.plugins {
+            write!(f, "\n  ok       {} ({})", p.name, p.full_path)?;
+        }
+        for r in &self.rejected {
+            write!(f, "\n  rejected {}: {}", r.full_path, r.reason)?;
+        }
+        Ok(())
+    }
+}
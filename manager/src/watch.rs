@@ -0,0 +1,132 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Watches plugin directories for plugins appearing, changing or
+//! disappearing, by polling a
+//! [CuckooPluginManager](../manager/struct.CuckooPluginManager.html)'s
+//! [refresh](../manager/struct.CuckooPluginManager.html#method.refresh)
+//! on a background thread, so long-running nodes can pick up new or rebuilt
+//! plugins without restarting.
+//!
+//! #Example
+//! ```
+//!  let mut manager = CuckooPluginManager::new()?;
+//!  manager.load_plugin_search_path(vec![String::from("plugins")])?;
+//!  let (watcher, events) = PluginWatcher::start(manager, Duration::from_secs(5));
+//!  for e in events.iter() {
+//!      println!("Plugins changed: {:?}", e);
+//!      let caps = watcher.manager().get_available_plugins("")?;
+//!  }
+//! ```
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use manager::{CuckooPluginManager, PluginEvent};
+
+// How often the watcher thread checks whether it's been stopped while
+// waiting for the next refresh
+
+const STOP_CHECK_INTERVAL_MS: u64 = 10;
+
+/// Owns a plugin manager and refreshes it periodically on a background
+/// thread, publishing each change as a [PluginEvent](../manager/enum.PluginEvent.html).
+/// The thread is stopped when the watcher is dropped.
+
+pub struct PluginWatcher {
+    manager: Arc<Mutex<CuckooPluginManager>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PluginWatcher {
+
+    /// #Description
+    ///
+    /// Starts watching the directories the given manager last loaded from.
+    ///
+    /// #Arguments
+    ///
+    /// * `manager` (IN) A plugin manager which has already loaded a plugin
+    /// directory or search path.
+    ///
+    /// * `interval` (IN) How long to wait between refreshes.
+    ///
+    /// #Returns
+    ///
+    /// The watcher, and the receiving end of the channel events are sent to.
+    /// Failed refreshes are sent as `PluginEvent::ScanFailed`, and the watcher
+    /// carries on.
+    ///
+
+    pub fn start(manager: CuckooPluginManager, interval: Duration)
+        -> (PluginWatcher, Receiver<PluginEvent>) {
+        let manager = Arc::new(Mutex::new(manager));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = channel();
+
+        let thread_manager = manager.clone();
+        let thread_stop = stop.clone();
+        let thread = thread::spawn(move || {
+            loop {
+                let next = Instant::now() + interval;
+                while Instant::now() < next {
+                    if thread_stop.load(Ordering::SeqCst) {
+                        return;
+                    }
+                    thread::sleep(Duration::from_millis(STOP_CHECK_INTERVAL_MS));
+                }
+                let events = match thread_manager.lock().unwrap().refresh() {
+                    Ok(e) => e,
+                    Err(e) => vec![PluginEvent::ScanFailed(format!("{:?}", e))],
+                };
+                for e in events {
+                    debug!("Plugin watcher: {:?}", e);
+                    let _ = sender.send(e);
+                }
+            }
+        });
+
+        (PluginWatcher {
+            manager: manager,
+            stop: stop,
+            thread: Some(thread),
+        }, receiver)
+    }
+
+    /// Locks and returns the watched manager, e.g. to list the currently
+    /// available plugins. Refreshes wait while the lock is held
+
+    pub fn manager(&self) -> MutexGuard<CuckooPluginManager> {
+        self.manager.lock().unwrap()
+    }
+
+    /// Stops watching, waiting for any refresh in progress to finish
+
+    pub fn stop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(t) = self.thread.take() {
+            let _ = t.join();
+        }
+    }
+}
+
+impl Drop for PluginWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Cancellation of synchronous mining. A
+//! [CancellationToken](struct.CancellationToken.html) is handed to
+//! [CuckooMiner::mine_cancellable](../miner/struct.CuckooMiner.html#method.mine_cancellable),
+//! and any clone of it can then be used to give up on the call from another
+//! thread, e.g. when a new block arrives. A token can also carry a deadline,
+//! after which it counts as cancelled.
+//!
+//! #Example
+//! ```
+//!  let token = CancellationToken::new();
+//!  let new_block_token = token.clone();
+//!  thread::spawn(move || {
+//!      wait_for_new_block();
+//!      new_block_token.cancel();
+//!  });
+//!  match miner.mine_cancellable(&header, &mut solution, &token) {
+//!      Err(CuckooMinerError::MiningCancelledError(_)) => println!("Moving on to the new block"),
+//!      ...
+//!  }
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+/// A flag shared between clones, which once set stays set, and an
+/// optional deadline
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+
+    /// Returns a token which is cancelled only when asked
+
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Returns a token which is also cancelled once the given time passes
+
+    pub fn with_deadline(mut self, deadline: Instant) -> CancellationToken {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Cancels this token and every clone of it
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the token has been cancelled or its deadline has passed
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.deadline_passed()
+    }
+
+    /// Whether the token has a deadline which has passed
+
+    pub fn deadline_passed(&self) -> bool {
+        self.deadline.map_or(false, |d| Instant::now() >= d)
+    }
+}
@@ -25,14 +25,11 @@ use rand::{self, Rng};
 use byteorder::{ByteOrder, BigEndian};
 use blake2::blake2b::Blake2b;
 
-use cuckoo_sys::{call_cuckoo_is_queue_under_limit,
-                 call_cuckoo_push_to_input_queue,
-                 call_cuckoo_read_from_output_queue,
-                 call_cuckoo_start_processing,
-                 call_cuckoo_stop_processing};
 use error::CuckooMinerError;
 use CuckooMinerJobHandle;
 use CuckooMinerSolution;
+use solver::CuckooSolver;
+use verify::SolutionVerifier;
 
 /// From grin
 /// The target is the 8-bytes hash block hashes must be lower than.
@@ -99,6 +96,9 @@ pub struct JobControlData {
     /// Whether the mining job is in the 
     /// process of shutting down
     pub is_stopping: bool,
+
+    /// The error which stopped the job, if any
+    pub error: Option<CuckooMinerError>,
 }
 
 impl Default for JobControlData {
@@ -106,6 +106,7 @@ impl Default for JobControlData {
 		JobControlData {
             is_running: false,
             is_stopping: false,
+            error: None,
 		}
 	}
 }
@@ -114,20 +115,27 @@ impl Default for JobControlData {
 ///
 ///
 
-pub struct Delegator {
+pub struct Delegator<S: CuckooSolver + ?Sized> {
     
     /// Data which is shared across all threads
     shared_data: JobSharedDataType,
 
     /// Job control flags which are shared across threads
     control_data: JobControlDataType,
+
+    /// The solver which performs the actual processing
+    solver: Arc<S>,
+
+    /// Checks each solution before it's put in the output queue
+    verifier: SolutionVerifier,
 }
 
-impl Delegator {
+impl<S: CuckooSolver + ?Sized + 'static> Delegator<S> {
 
     /// Create a new job delegator
 
-    pub fn new(job_id:u32, pre_nonce: &str, post_nonce: &str, difficulty:u64)->Delegator{
+    pub fn new(solver: Arc<S>, verifier: SolutionVerifier, job_id:u32, pre_nonce: &str, post_nonce: &str,
+               difficulty:u64)->Delegator<S>{
         Delegator {
             shared_data: Arc::new(RwLock::new(JobSharedData::new(
                 job_id, 
@@ -135,27 +143,44 @@ impl Delegator {
                 post_nonce,
                 difficulty))),
             control_data: Arc::new(RwLock::new(JobControlData::default())),
+            solver: solver,
+            verifier: verifier,
         }
     }
 
     /// Starts the job loop, and initialises the internal plugin
 
-    pub fn start_job_loop (self) -> Result<CuckooMinerJobHandle, CuckooMinerError> {
+    pub fn start_job_loop (self) -> Result<CuckooMinerJobHandle<S>, CuckooMinerError> {
         //this will block, waiting until previous job is cleared
         //call_cuckoo_stop_processing();
 
         let shared_data=self.shared_data.clone();
         let control_data=self.control_data.clone();
+        let solver=self.solver.clone();
+        let invalid_solutions=self.verifier.invalid_count();
+        let loop_control_data=self.control_data.clone();
+
+        //mark the job as running before the loop starts, so a stop_jobs
+        //call made straight away isn't overwritten by the job loop
+        {
+            let mut s = self.control_data.write().unwrap();
+            s.is_running=true;
+        }
 
         thread::spawn(move || {
             let result=self.job_loop();
             if let Err(e) = result {
                 error!("Error in job loop: {:?}", e);
+                let mut c=loop_control_data.write().unwrap();
+                c.is_running=false;
+                c.error=Some(e);
             }
         });
         Ok(CuckooMinerJobHandle {
             shared_data: shared_data, 
             control_data: control_data,
+            solver: solver,
+            invalid_solutions: invalid_solutions,
         })
     }
 
@@ -213,7 +238,7 @@ impl Delegator {
     /// Helper to determing whether a solution meets a target difficulty
     /// based on same algorithm from grin
 
-    fn meets_difficulty(&self, in_difficulty: u64, sol:CuckooMinerSolution)->bool {
+    fn meets_difficulty(&self, in_difficulty: u64, sol:&CuckooMinerSolution)->bool {
         let max_target = BigEndian::read_u64(&MAX_TARGET);
 		let num = BigEndian::read_u64(&sol.hash()[0..8]);
 		max_target / num > in_difficulty
@@ -236,42 +261,69 @@ impl Delegator {
             difficulty=s.difficulty;
         }
         debug!("Cuckoo-miner: Searching for solution >= difficulty {}", difficulty);
-        {
-            let mut s = self.control_data.write().unwrap();
-            s.is_running=true;
-        }
 
-        if let Err(e) = call_cuckoo_start_processing() {
+        if let Err(e) = self.solver.start_processing() {
             return Err(CuckooMinerError::PluginProcessingError(
                     String::from(format!("Error starting processing plugin: {:?}", e))));
         }
 
         debug!("Cuckoo Miner Job loop processing");
+        //the plugin has to be stopped whether or not processing failed,
+        //otherwise its threads carry on with nothing left to stop them
+        let result = self.process_queues(&pre_nonce, &post_nonce, difficulty);
+
+        //Do any cleanup
+        debug!("Telling job thread to stop... ");
+        if let Err(e) = self.solver.stop_processing() {
+            if result.is_ok() {
+                return Err(CuckooMinerError::PluginProcessingError(
+                        String::from(format!("Error stopping processing plugin: {:?}", e))));
+            }
+            error!("Error stopping processing plugin: {:?}", e);
+        }
+        debug!("Cuckoo-Miner: Job loop has exited.");
+        result
+    }
+
+    /// Pushes hashes to the plugin and reads solutions from the queue,
+    /// until another thread sets the is_running flag to false or the
+    /// plugin returns an error
+
+    fn process_queues(&self, pre_nonce: &str, post_nonce: &str, difficulty: u64)
+        -> Result<(), CuckooMinerError> {
         let mut solution=CuckooMinerSolution::new();
 
         loop {
             //Check if it's time to stop
             
-            let s = self.control_data.read().unwrap();
-            if !s.is_running {
+            //Only hold the lock while checking, so stop_jobs isn't
+            //blocked for the rest of the iteration
+            if !self.control_data.read().unwrap().is_running {
                 break;
             }
             
-            while call_cuckoo_is_queue_under_limit().unwrap()==1{
+            while self.solver.is_queue_under_limit()? {
 
-                let (nonce, hash) = self.get_next_hash(&pre_nonce, &post_nonce);
+                let (nonce, hash) = self.get_next_hash(pre_nonce, post_nonce);
                 //println!("Hash thread 1: {:?}", hash);
                 //TODO: make this a serialise operation instead
                 let nonce_bytes:[u8;8] = unsafe{transmute(nonce.to_be())};
-                call_cuckoo_push_to_input_queue(&hash, &nonce_bytes)?;
+                self.solver.push_to_input_queue(&hash, &nonce_bytes)?;
             }
 
             
-            while call_cuckoo_read_from_output_queue(&mut solution.solution_nonces, &mut solution.nonce).unwrap()!=0 {
+            while self.solver.read_from_output_queue(&mut solution.solution_nonces, &mut solution.nonce)? {
                 //TODO: make this a serialise operation instead
                 let nonce = unsafe{transmute::<[u8;8], u64>(solution.nonce)}.to_be();
+
+                if self.verifier.is_enabled() {
+                    let hash = self.get_hash(pre_nonce, post_nonce, nonce);
+                    if !self.verifier.check(self.solver.name(), &hash, &solution.solution_nonces) {
+                        continue;
+                    }
+                }
                 
-                if self.meets_difficulty(difficulty, solution) {    
+                if self.meets_difficulty(difficulty, &solution) {    
                     debug!("Cuckoo-miner: Solution Found for Nonce:({}), {:?}", nonce, solution);
                     let mut s = self.shared_data.write().unwrap();
                     s.solutions.push(solution.clone());
@@ -280,14 +332,6 @@ impl Delegator {
                 
             }
         }
-
-        //Do any cleanup
-        debug!("Telling job thread to stop... ");
-        if let Err(e) = call_cuckoo_stop_processing() {
-            return Err(CuckooMinerError::PluginProcessingError(
-                    String::from(format!("Error stopping processing plugin: {:?}", e))));
-        }
-        debug!("Cuckoo-Miner: Job loop has exited.");
         Ok(())
     }
 }
\ No newline at end of file
@@ -33,11 +33,22 @@ extern crate crypto;
 extern crate blake2_rfc as blake2;
 
 mod delegator;
+mod plugin;
 
+pub mod cancel;
 pub mod miner;
+pub mod solver;
+pub mod verify;
 pub use miner::{CuckooMiner,
                 CuckooMinerConfig,
                 CuckooMinerSolution,
+                CuckooMinerBatchResult,
                 CuckooMinerJobHandle};
+pub use solver::CuckooSolver;
+pub use cancel::CancellationToken;
+pub use verify::{GraphParams, verify};
+
+pub use cuckoo_sys::{PluginCapabilities, TrustPolicy, CuckooPluginParameter, ParameterType, ParameterValue,
+                     default_worker_path};
 
 
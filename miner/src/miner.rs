@@ -110,7 +110,7 @@
 //!             break;    
 //!                
 //!         }
-//!         if time::get_time().sec < deadline {
+//!         if time::get_time().sec >= deadline {
 //!             job_handle.stop_jobs();
 //!             break;
 //!         }
@@ -119,6 +119,8 @@
 //! ```
 
 use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError};
 use std::{thread, time};
 use std::{fmt,cmp};
 use std::collections::HashMap;
@@ -127,33 +129,163 @@ use byteorder::{ByteOrder, BigEndian};
 
 use blake2::blake2b::Blake2b;
 
-use cuckoo_sys::{call_cuckoo, 
-                 load_cuckoo_lib,
-                 call_cuckoo_set_parameter,
-                 call_cuckoo_hashes_since_last_call};
+use cuckoo_sys::{PluginCapabilities, ParameterValue, TrustPolicy, DEFAULT_PROOF_SIZE};
 
 use error::CuckooMinerError;
 
+use cancel::CancellationToken;
 use delegator::{Delegator, JobControlData, JobSharedData};
+use plugin::LoadedPlugin;
+use solver::CuckooSolver;
+use verify::{GraphParams, SolutionVerifier};
 
-// Hardcoded assumption for now that the solution size will be 42 will be
-// maintained, to avoid having to allocate memory within the called C functions
+// How often a cancellable mining call checks whether it's been cancelled
 
-const CUCKOO_SOLUTION_SIZE:usize = 42;
+const CANCEL_POLL_INTERVAL_MS: u64 = 10;
 
-/// A simple struct to hold a cuckoo miner solution. Currently,
-/// it's assumed that a solution will be 42 bytes. The `solution_nonces`
-/// member is statically allocated here, and will be filled in 
-/// by a plugin upon finding a solution.
+// How long to wait for a plugin to return once asked to abort
+
+const ABORT_WAIT_MS: u64 = 1000;
+
+// How long a batch mined via the plugin's queue may go without progress
+
+const DEFAULT_BATCH_STALL_TIMEOUT_SECS: u64 = 60;
+
+// The error returned by a cancelled mining call
+
+fn cancelled_error(cancel: &CancellationToken) -> CuckooMinerError {
+    if cancel.deadline_passed() {
+        CuckooMinerError::MiningCancelledError(String::from("Mining deadline passed"))
+    } else {
+        CuckooMinerError::MiningCancelledError(String::from("Mining cancelled"))
+    }
+}
+
+// Identifies a header in a batch by its index, in place of its nonce
+
+fn batch_nonce(index: usize) -> [u8; 8] {
+    let mut nonce = [0; 8];
+    BigEndian::write_u64(&mut nonce, index as u64);
+    nonce
+}
+
+// Mines a batch one header at a time
+
+fn mine_batch_sync<S: CuckooSolver + ?Sized, H: AsRef<[u8]>>(solver: &S, headers: &[H])
+    -> Result<Vec<CuckooMinerBatchResult>, CuckooMinerError> {
+    let mut results = Vec::with_capacity(headers.len());
+    for (index, header) in headers.iter().enumerate() {
+        let start = time::Instant::now();
+        let solutions = solver.mine_all(header.as_ref())?.into_iter().map(|nonces| {
+            CuckooMinerSolution {
+                solution_nonces: nonces,
+                nonce: batch_nonce(index),
+            }
+        }).collect();
+        results.push(CuckooMinerBatchResult {
+            solutions: solutions,
+            elapsed: start.elapsed(),
+        });
+    }
+    Ok(results)
+}
+
+// Mines a batch via the solver's queue, stopping processing again
+// whether or not it succeeds
+
+fn mine_batch_queued<S: CuckooSolver + ?Sized, H: AsRef<[u8]>>(solver: &S, headers: &[H],
+                                                               stall_timeout: time::Duration)
+    -> Result<Vec<CuckooMinerBatchResult>, CuckooMinerError> {
+    //clear the count of any hashes processed before this batch
+    solver.hashes_since_last_call()?;
+    solver.start_processing()?;
+    let mut results = vec![CuckooMinerBatchResult::default(); headers.len()];
+    let result = run_batch_queue(solver, headers, &mut results, stall_timeout);
+    let stopped = solver.stop_processing();
+    result?;
+    stopped?;
+    //pick up anything the plugin output while it was stopping
+    read_batch_solutions(solver, &mut results)?;
+    Ok(results)
+}
+
+// Moves every solution in the solver's output queue into the result
+// for its header, returning whether there were any
+
+fn read_batch_solutions<S: CuckooSolver + ?Sized>(solver: &S, results: &mut [CuckooMinerBatchResult])
+    -> Result<bool, CuckooMinerError> {
+    let mut solution = CuckooMinerSolution::new();
+    let mut found = false;
+    while solver.read_from_output_queue(&mut solution.solution_nonces, &mut solution.nonce)? {
+        match results.get_mut(solution.get_nonce_as_u64() as usize) {
+            Some(r) => r.solutions.push(solution.clone()),
+            None => warn!("Ignoring solution for unknown batch nonce {}", solution.get_nonce_as_u64()),
+        }
+        found = true;
+    }
+    Ok(found)
+}
+
+fn run_batch_queue<S: CuckooSolver + ?Sized, H: AsRef<[u8]>>(solver: &S, headers: &[H],
+                                                             results: &mut [CuckooMinerBatchResult],
+                                                             stall_timeout: time::Duration)
+    -> Result<(), CuckooMinerError> {
+    let mut queued_at = Vec::with_capacity(headers.len());
+    let mut processed = 0;
+    let mut last_progress = time::Instant::now();
+    loop {
+        let mut progressed = false;
+        while queued_at.len() < headers.len() && solver.is_queue_under_limit()? {
+            let index = queued_at.len();
+            if !solver.push_to_input_queue(headers[index].as_ref(), &batch_nonce(index))? {
+                break;
+            }
+            queued_at.push(time::Instant::now());
+            progressed = true;
+        }
+
+        if read_batch_solutions(solver, results)? {
+            progressed = true;
+        }
+
+        //once the solutions are read, the headers the plugin has finished with are done
+        if processed == headers.len() {
+            break;
+        }
+        let newly_processed = solver.hashes_since_last_call()? as usize;
+        let done = cmp::min(processed + newly_processed, queued_at.len());
+        for i in processed..done {
+            results[i].elapsed = queued_at[i].elapsed();
+        }
+        if done > processed {
+            processed = done;
+            progressed = true;
+        }
+        if progressed {
+            last_progress = time::Instant::now();
+        } else if last_progress.elapsed() >= stall_timeout {
+            return Err(CuckooMinerError::PluginProcessingError(format!(
+                "Plugin stalled after processing {} of {} headers in the batch",
+                processed, headers.len())));
+        } else {
+            thread::sleep(time::Duration::from_millis(1));
+        }
+    }
+    Ok(())
+}
+
+/// A simple struct to hold a cuckoo miner solution. The number of
+/// `solution_nonces` is the proof size of the plugin that found the
+/// solution, conventionally 42, and they're filled in by a plugin upon
+/// finding a solution.
 ///
 
-#[derive(Copy)]
+#[derive(Clone)]
 pub struct CuckooMinerSolution {
-    /// An array allocated in rust that will be filled
-    /// by the called plugin upon successfully finding
-    /// a solution
+    /// The solution nonces, as filled in by the called
+    /// plugin upon successfully finding a solution
 
-    pub solution_nonces:[u32; CUCKOO_SOLUTION_SIZE],
+    pub solution_nonces:Vec<u32>,
 
     /// The nonce that was used to generate the
     /// hash for which a solution was found
@@ -164,32 +296,30 @@ pub struct CuckooMinerSolution {
 impl Default for CuckooMinerSolution {
 	fn default() -> CuckooMinerSolution {
         CuckooMinerSolution {
-		    solution_nonces: [0; CUCKOO_SOLUTION_SIZE],
+		    solution_nonces: vec![0; DEFAULT_PROOF_SIZE as usize],
             nonce: [0;8],
         }
 	}
 }
 
-impl Clone for CuckooMinerSolution {
-	fn clone(&self) -> CuckooMinerSolution {
-		*self
-	}
-}
-
-
 impl CuckooMinerSolution{
 
     /// Creates a new cuckoo miner solution
-    /// with nonces set to a u32 array of size
-    /// 42 filled with zeroes.
+    /// with nonces set to a u32 vector of the
+    /// default proof size (42) filled with zeroes.
 
     pub fn new()->CuckooMinerSolution{
         CuckooMinerSolution::default()
     }
 
     /// Sets the solution, mostly for testing
-    pub fn set_solution(&mut self, nonces:[u32; CUCKOO_SOLUTION_SIZE]){
-        self.solution_nonces = nonces;
+    pub fn set_solution(&mut self, nonces:&[u32]){
+        self.solution_nonces = nonces.to_vec();
+    }
+
+    /// Returns the number of nonces in the solution
+    pub fn proof_size(&self) -> usize {
+        self.solution_nonces.len()
     }
 
     /// return the nonce as a u64, for convenience
@@ -199,7 +329,7 @@ impl CuckooMinerSolution{
 
     /// Converts the proof to a vector of u64s
 	pub fn to_u64s(&self) -> Vec<u64> {
-		let mut nonces = Vec::with_capacity(CUCKOO_SOLUTION_SIZE);
+		let mut nonces = Vec::with_capacity(self.solution_nonces.len());
 		for n in self.solution_nonces.iter() {
 			nonces.push(*n as u64);
 		}
@@ -245,12 +375,31 @@ impl fmt::Debug for CuckooMinerSolution {
 
 impl cmp::PartialEq for CuckooMinerSolution {
     fn eq(&self, other: &CuckooMinerSolution) -> bool {
-        for i in 0..CUCKOO_SOLUTION_SIZE {
-            if self.solution_nonces[i]!=other.solution_nonces[i]{
-                return false;
-            }
-        }
-        return true;
+        self.solution_nonces == other.solution_nonces
+    }
+}
+
+/// The result of mining a single header as part of a batch, see
+/// [CuckooMiner::mine_batch](struct.CuckooMiner.html#method.mine_batch)
+
+#[derive(Debug, Clone, Default)]
+pub struct CuckooMinerBatchResult {
+    /// Every solution found for the header, each with its `nonce` set to
+    /// the header's index in the batch
+    pub solutions: Vec<CuckooMinerSolution>,
+
+    /// How long the header took, from being handed to the plugin until its
+    /// result was known. When mining via the plugin's queue this includes any
+    /// time spent waiting in the queue behind other headers.
+    pub elapsed: time::Duration,
+}
+
+impl CuckooMinerBatchResult {
+
+    /// Whether any solution was found for the header
+
+    pub fn found(&self) -> bool {
+        !self.solutions.is_empty()
     }
 }
 
@@ -265,8 +414,36 @@ pub struct CuckooMinerConfig {
     pub plugin_full_path: String,
 
     /// A parameter list, which differs depending on which 
-    /// plugin is being called
-    pub parameter_list: HashMap<String, u32>,
+    /// plugin is being called. Values are checked against the
+    /// types and ranges the plugin declares before they're set.
+    pub parameter_list: HashMap<String, ParameterValue>,
+
+    /// Whether to load the plugin into a separate worker process
+    /// rather than into this process, so that a crashing plugin
+    /// can't take the calling process down with it. Defaults to false.
+    pub use_worker_process: bool,
+
+    /// The path to the `cuckoo_plugin_worker` executable used when
+    /// `use_worker_process` is set. Defaults to empty string, in which
+    /// case the worker is looked for in the `CUCKOO_PLUGIN_WORKER`
+    /// environment variable, then alongside the current executable.
+    pub worker_path: String,
+
+    /// Which plugin libraries may be loaded. If set, the plugin is
+    /// checked against the policy before it's loaded, and refused
+    /// with a `PluginUntrustedError` if it fails. Defaults to None.
+    pub trust_policy: Option<TrustPolicy>,
+
+    /// The graph the plugin searches. If set, every solution the
+    /// plugin returns, in either mode, is verified before it's
+    /// handed out, and invalid solutions are dropped and counted.
+    /// Defaults to None, trusting the plugin.
+    pub verify_solutions: Option<GraphParams>,
+
+    /// How long [mine_batch](struct.CuckooMiner.html#method.mine_batch) waits
+    /// for a plugin mining through its queue to make any progress before giving
+    /// up on the batch. Defaults to 60 seconds.
+    pub batch_stall_timeout: time::Duration,
 
 }
 
@@ -275,6 +452,11 @@ impl Default for CuckooMinerConfig {
 		CuckooMinerConfig{
             plugin_full_path: String::from(""),
             parameter_list: HashMap::new(),
+            use_worker_process: false,
+            worker_path: String::from(""),
+            trust_policy: None,
+            verify_solutions: None,
+            batch_stall_timeout: time::Duration::from_secs(DEFAULT_BATCH_STALL_TIMEOUT_SECS),
 		}
 	}
 }
@@ -294,19 +476,27 @@ impl CuckooMinerConfig{
 /// This will basically hold an arc reference clone of
 /// the Delegator's internal shared data
 
-pub struct CuckooMinerJobHandle {
+pub struct CuckooMinerJobHandle<S: CuckooSolver + ?Sized = dyn CuckooSolver> {
     /// Data shared across threads
     pub shared_data: Arc<RwLock<JobSharedData>>,
 
     /// Job control flags
     pub control_data: Arc<RwLock<JobControlData>>,
+
+    /// The solver the job is running on
+    pub(crate) solver: Arc<S>,
+
+    /// The number of invalid solutions the solver has returned
+    pub(crate) invalid_solutions: Arc<AtomicUsize>,
 }
 
-impl CuckooMinerJobHandle {
+impl<S: CuckooSolver + ?Sized> CuckooMinerJobHandle<S> {
 
     /// #Description 
     ///
-    /// Returns a solution if one is currently waiting.
+    /// Returns a solution if one is currently waiting. Where several cycles
+    /// were found for the same hash, each is returned as a separate solution,
+    /// all with the same nonce.
     ///
     /// #Returns
     ///
@@ -357,41 +547,72 @@ impl CuckooMinerJobHandle {
     /// Ok(n) if successful, with n containing the number of hashes processed
     /// since the last time this function was called.
     /// A [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
-    /// with specific detail if an error occurred.
+    /// with specific detail if an error occurred, including a `PluginUnsupportedError`
+    /// if the plugin doesn't report hash metrics.
 
     pub fn get_hashes_since_last_call(&self)->Result<u32, CuckooMinerError>{
-        match call_cuckoo_hashes_since_last_call() {
-            Ok(result) => {
-                return Ok(result);
-            },
-            Err(_) => {
-                return Err(CuckooMinerError::PluginNotLoadedError(
-                String::from("Please call init to load a miner plug-in")));
-            }
-        }
-    
+        self.solver.hashes_since_last_call()
+    }
+
+    /// #Description 
+    ///
+    /// Returns the number of solutions the plugin has returned which failed
+    /// verification, including any returned to the miner before the job was
+    /// started. Always 0 unless the miner's config sets `verify_solutions`.
+    ///
+
+    pub fn get_invalid_solution_count(&self)->usize{
+        self.invalid_solutions.load(Ordering::SeqCst)
+    }
+
+    /// #Description 
+    ///
+    /// Returns the error which caused the job to stop, if any. When the plugin
+    /// is running in a worker process, this will be a `PluginCrashError` if
+    /// the plugin crashed while the job was running.
+    ///
+    /// #Returns
+    ///
+    /// Some(error) if the job stopped because of an error, None otherwise. The error
+    /// is only returned once.
+    ///
+
+    pub fn get_error(&self)->Option<CuckooMinerError>{
+        let mut r=self.control_data.write().unwrap();
+        r.error.take()
     }
 
         
 }
 
-/// An instance of a miner, which loads a cuckoo-miner plugin
-/// and calls its mine function according to the provided configuration
+/// An instance of a miner, which mines using a [CuckooSolver](../solver/trait.CuckooSolver.html).
+/// Usually this is a cuckoo-miner plugin, loaded and configured according to the
+/// provided configuration via [new](#method.new), but any solver can be used via
+/// [from_solver](#method.from_solver).
 ///
 
-pub struct CuckooMiner{
+pub struct CuckooMiner<S: CuckooSolver + ?Sized = dyn CuckooSolver>{
     /// The internal Configuration object
     pub config: CuckooMinerConfig,
-    
-    ///
-    delegator: Delegator,
+
+    /// This miner's own handle to its solver
+    solver: Option<Arc<S>>,
+
+    /// Set while a sync mining call is in progress, including one
+    /// which has been cancelled but hasn't returned yet
+    busy: Arc<AtomicBool>,
+
+    /// The number of invalid solutions the solver has returned
+    invalid_solutions: Arc<AtomicUsize>,
 }
 
 impl Default for CuckooMiner {
 	fn default() -> CuckooMiner {
 		CuckooMiner {
             config: CuckooMinerConfig::default(),
-            delegator: Delegator::new(0,"","",0),
+            solver: None,
+            busy: Arc::new(AtomicBool::new(false)),
+            invalid_solutions: Arc::new(AtomicUsize::new(0)),
 		}
 	}
 }
@@ -421,7 +642,7 @@ impl CuckooMiner {
         return_val.init()?;
         //set any parameters provided in the config
         for (name, value) in return_val.config.parameter_list.clone() {
-           return_val.set_parameter(name.clone(), value.clone())?;
+           return_val.set_parameter_value(name.clone(), value.clone())?;
         }
 
         Ok(return_val)
@@ -430,7 +651,123 @@ impl CuckooMiner {
     /// Internal function to perform tha actual library loading
 
     fn init(&mut self) -> Result<(), CuckooMinerError> {
-        load_cuckoo_lib(&self.config.plugin_full_path)
+        let trust_policy = self.config.trust_policy.as_ref();
+        let plugin = if self.config.use_worker_process {
+            LoadedPlugin::load_in_worker(&self.config.worker_path, &self.config.plugin_full_path, trust_policy)?
+        } else {
+            LoadedPlugin::load(&self.config.plugin_full_path, trust_policy)?
+        };
+        self.solver = Some(Arc::new(plugin));
+        Ok(())
+    }
+}
+
+impl<S: CuckooSolver> CuckooMiner<S> {
+
+    /// #Description 
+    ///
+    /// Creates a new instance of a CuckooMiner which mines using the given solver
+    /// rather than loading a plugin. The miner's config is left at its defaults,
+    /// and any parameters should be set via [set_parameter](#method.set_parameter).
+    ///
+    /// #Arguments
+    ///
+    /// * `solver` Any implementation of [CuckooSolver](../solver/trait.CuckooSolver.html)
+    ///
+    /// #Returns
+    ///
+    /// The new miner
+    ///
+
+    pub fn from_solver(solver: S) -> CuckooMiner<S> {
+        CuckooMiner {
+            config: CuckooMinerConfig::default(),
+            solver: Some(Arc::new(solver)),
+            busy: Arc::new(AtomicBool::new(false)),
+            invalid_solutions: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl<S: CuckooSolver + ?Sized + 'static> CuckooMiner<S> {
+
+    /// #Description 
+    ///
+    /// Returns the set of capabilities supported by the loaded plugin, i.e.
+    /// whether it can be used for sync mining via [mine](#method.mine), async
+    /// mining via [notify](#method.notify), whether it accepts parameters and
+    /// whether it reports hash metrics.
+    ///
+    /// #Returns
+    ///
+    /// The plugin's capabilities, or an empty set if no plugin is loaded.
+    ///
+
+    pub fn capabilities(&self) -> PluginCapabilities {
+        match self.solver {
+            Some(ref p) => p.capabilities(),
+            None => PluginCapabilities::empty(),
+        }
+    }
+
+    /// #Description 
+    ///
+    /// Returns the number of nonces in each solution found by the loaded plugin,
+    /// as reported by the plugin, or the default proof size of 42 if it
+    /// doesn't report one.
+    ///
+    /// #Returns
+    ///
+    /// The plugin's proof size, or the default if no plugin is loaded.
+    ///
+
+    pub fn proof_size(&self) -> usize {
+        match self.solver {
+            Some(ref p) => p.proof_size(),
+            None => DEFAULT_PROOF_SIZE as usize,
+        }
+    }
+
+    /// Internal helper returning this miner's solver
+
+    fn solver(&self) -> Result<&Arc<S>, CuckooMinerError> {
+        match self.solver {
+            Some(ref p) => Ok(p),
+            None => Err(CuckooMinerError::PluginNotLoadedError(
+                String::from("Please call init to load a miner plug-in"))),
+        }
+    }
+
+    /// Internal helper returning this miner's solver, provided
+    /// it supports the given capability
+
+    fn solver_supporting(&self, capability: PluginCapabilities, mode: &str) 
+        -> Result<&Arc<S>, CuckooMinerError> {
+        let solver = self.solver()?;
+        if !solver.capabilities().contains(capability) {
+            return Err(CuckooMinerError::PluginUnsupportedError(format!(
+                "{} is unsupported by this plugin: {}", mode, solver.name())));
+        }
+        Ok(solver)
+    }
+
+    /// Internal helper returning a verifier for the solutions the solver
+    /// returns, as configured
+
+    fn verifier(&self, solver: &S) -> SolutionVerifier {
+        SolutionVerifier::new(self.config.verify_solutions, solver.proof_size(),
+                              self.invalid_solutions.clone())
+    }
+
+    /// #Description 
+    ///
+    /// Returns the number of solutions the plugin has returned which failed
+    /// verification, and so were never returned by this miner. Always 0 unless
+    /// the config sets `verify_solutions`.
+    ///
+
+    pub fn invalid_solution_count(&self) -> usize {
+        self.invalid_solutions.load(Ordering::SeqCst)
     }
 
     /// #Description 
@@ -451,28 +788,94 @@ impl CuckooMiner {
     ///
 
     pub fn set_parameter(&mut self, name: String, value:u32) -> Result<(), CuckooMinerError>{
-        let return_code = call_cuckoo_set_parameter(name.as_bytes(), value)?;
-        if return_code != 0 {
-            
-            let reason = match return_code {
-                1 => "Property doesn't exist for this plugin",
-                2 => "Property outside allowed range",
-                _ => "Unknown Error"
-            };
-
-            return Err(CuckooMinerError::ParameterError(String::from(
-                format!("Error setting parameter: {} to {} - {}", name, value, reason)
-                )));
+        self.set_parameter_value(name, ParameterValue::Int(value))
+    }
+
+    /// #Description 
+    ///
+    /// Sets a parameter in the currently loaded plugin to a typed value. If the
+    /// plugin lists its parameters, the value is checked against the parameter's
+    /// declared type and range first, and never passed to the plugin if invalid.
+    ///
+    /// #Arguments
+    ///
+    /// * `name` The name of the parameter to set
+    ///
+    /// * `value` The value to set the parameter to
+    ///
+    /// #Returns
+    ///
+    /// If successful, Ok() is returned and the parameter has been set.
+    /// Otherwise a [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
+    /// with specific detail is returned, e.g. a `ParameterError` if the parameter
+    /// doesn't exist or the value is the wrong type or out of range.
+    ///
+
+    pub fn set_parameter_value(&mut self, name: String, value: ParameterValue)
+        -> Result<(), CuckooMinerError>{
+        let solver = self.solver()?;
+        let parameters = match solver.parameters() {
+            Ok(p) => p,
+            Err(CuckooMinerError::PluginUnsupportedError(_)) => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        if !parameters.is_empty() {
+            match parameters.iter().find(|p| p.name == name) {
+                Some(p) => p.validate(&value)?,
+                None => return Err(CuckooMinerError::ParameterError(format!(
+                    "Error setting parameter: {} to {} - Property doesn't exist for this plugin",
+                    name, value))),
+            }
         }
-        Ok(())
+        solver.set_parameter_value(&name, &value)
+    }
+
+    /// #Description 
+    ///
+    /// Retrieves the value of a parameter from the currently loaded plugin
+    ///
+    /// #Arguments
+    ///
+    /// * `name` The name of the parameter to retrieve
+    ///
+    /// #Returns
+    ///
+    /// If successful, Ok(value). Otherwise a
+    /// [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
+    /// with specific detail is returned, e.g. a `ParameterError` if the
+    /// parameter doesn't exist.
+    ///
+
+    pub fn get_parameter(&self, name: &str) -> Result<u32, CuckooMinerError>{
+        self.solver()?.get_parameter(name)
+    }
+
+    /// #Description 
+    ///
+    /// Retrieves the typed value of a parameter from the currently loaded plugin
+    ///
+    /// #Arguments
+    ///
+    /// * `name` The name of the parameter to retrieve
+    ///
+    /// #Returns
+    ///
+    /// If successful, Ok(value). Otherwise a
+    /// [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
+    /// with specific detail is returned, e.g. a `ParameterError` if the
+    /// parameter doesn't exist.
+    ///
+
+    pub fn get_parameter_value(&self, name: &str) -> Result<ParameterValue, CuckooMinerError>{
+        self.solver()?.get_parameter_value(name)
     }
 
     /// #Description 
     ///
     /// Synchronous call to the cuckoo_call function of the currently loaded plugin, which 
     /// will perform 
-    /// a Cuckoo Cycle on the given seed, filling the first solution (a length 42 cycle)
-    /// that is found in the provided [CuckooMinerSolution](struct.CuckooMinerSolution.html) structure.
+    /// a Cuckoo Cycle on the given seed, filling the first solution (a cycle of the
+    /// plugin's [proof_size](#method.proof_size)) that is found in the provided [CuckooMinerSolution](struct.CuckooMinerSolution.html) structure.
     /// The implementation details are dependent on particular loaded plugin. Values provided
     /// to the loaded plugin are contained in the internal [CuckooMinerConfig](struct.CuckooMinerConfig.html) 
     ///
@@ -489,30 +892,231 @@ impl CuckooMiner {
     ///
     /// #Returns
     ///
-    /// * Ok(true) if a solution is found, with the 42 solution nonces contained within
+    /// * Ok(true) if a solution is found, with the solution nonces contained within
     /// the provided [CuckooMinerSolution](struct.CuckooMinerSolution.html).
     /// * Ok(false) if no solution is found and `solution` remains untouched.
     /// * A [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
     /// if there is no plugin loaded, or if there is an error calling the function.
+    /// * A `PluginUnsupportedError` if the loaded plugin doesn't support sync mode
     ///
 
     pub fn mine(&self, header: &[u8], solution:&mut CuckooMinerSolution) 
         -> Result<bool, CuckooMinerError> {    
-            match call_cuckoo(header, 
-                              &mut solution.solution_nonces) {
+            let solver = self.solver_supporting(PluginCapabilities::SYNC, "Sync mining")?;
+            self.wait_until_idle(None)?;
+            let mut nonces = Vec::new();
+            let result = solver.mine(header, &mut nonces);
+            self.busy.store(false, Ordering::SeqCst);
+            let found = result? && self.verifier(solver).check(solver.name(), header, &nonces);
+            if found {
+                debug!("Solution found."); 
+                solution.solution_nonces = nonces;
+            }
+            Ok(found)
+    }
+
+    /// #Description 
+    ///
+    /// As [mine](#method.mine), but returns every cycle found in the graph for
+    /// the given header rather than just the first, e.g. so each can be submitted
+    /// as a share. Plugins which don't export `cuckoo_call_all` only ever return
+    /// the first cycle they find.
+    ///
+    /// #Arguments
+    ///
+    /// * `header` (IN) As for [mine](#method.mine).
+    ///
+    /// #Returns
+    ///
+    /// * Ok(solutions), with one [CuckooMinerSolution](struct.CuckooMinerSolution.html)
+    /// for each cycle found, empty if none were found.
+    /// * A [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
+    /// if there is no plugin loaded, or if there is an error calling the function.
+    /// * A `PluginUnsupportedError` if the loaded plugin doesn't support sync mode
+    ///
+
+    pub fn mine_all(&self, header: &[u8]) -> Result<Vec<CuckooMinerSolution>, CuckooMinerError> {
+        let solver = self.solver_supporting(PluginCapabilities::SYNC, "Sync mining")?;
+        self.wait_until_idle(None)?;
+        let result = solver.mine_all(header);
+        self.busy.store(false, Ordering::SeqCst);
+        let verifier = self.verifier(solver);
+        let solutions = result?.into_iter()
+            .filter(|nonces| verifier.check(solver.name(), header, nonces))
+            .map(|nonces| {
+            let mut solution = CuckooMinerSolution::new();
+            solution.solution_nonces = nonces;
+            solution
+        }).collect::<Vec<_>>();
+        debug!("{} solution(s) found.", solutions.len());
+        Ok(solutions)
+    }
+
+    /// #Description 
+    ///
+    /// Mines each of the given headers, as [mine_all](#method.mine_all) would, but
+    /// without making a separate blocking plugin call for each. If the plugin
+    /// supports async mode and reports hash metrics, the headers are fed through
+    /// the plugin's queue, so that a plugin which works on several graphs at once
+    /// can do so. Otherwise each header is mined in turn.
+    ///
+    /// Mining via the queue relies on the plugin counting each header it's done
+    /// with in `cuckoo_hashes_since_last_call` once any solutions for it are in
+    /// its output queue, and headers are assumed to finish in the order they were
+    /// queued when timing them. A plugin which undercounts is given up on once it
+    /// has made no progress for the config's `batch_stall_timeout`.
+    ///
+    /// #Arguments
+    ///
+    /// * `headers` (IN) The headers to mine, as for [mine](#method.mine).
+    ///
+    /// #Returns
+    ///
+    /// * Ok(results), with a [CuckooMinerBatchResult](struct.CuckooMinerBatchResult.html)
+    /// for each header, in the same order as `headers`.
+    /// * A [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
+    /// if there is no plugin loaded, or if there is an error calling the plugin,
+    /// in which case no results are returned.
+    /// * A `PluginUnsupportedError` if the loaded plugin supports neither mode
+    /// * A `PluginProcessingError` if the plugin stalls while mining via its queue
+    ///
+
+    pub fn mine_batch<H: AsRef<[u8]>>(&self, headers: &[H])
+        -> Result<Vec<CuckooMinerBatchResult>, CuckooMinerError> {
+        let solver = self.solver()?;
+        let mut queued = PluginCapabilities::ASYNC_QUEUE;
+        queued.insert(PluginCapabilities::HASH_METRICS);
+        let use_queue = solver.capabilities().contains(queued);
+        if !use_queue {
+            self.solver_supporting(PluginCapabilities::SYNC, "Batch mining")?;
+        }
+        self.wait_until_idle(None)?;
+        let result = if use_queue {
+            mine_batch_queued(&**solver, headers, self.config.batch_stall_timeout)
+        } else {
+            mine_batch_sync(&**solver, headers)
+        };
+        self.busy.store(false, Ordering::SeqCst);
+        let mut results = result?;
+        let verifier = self.verifier(solver);
+        for (header, r) in headers.iter().zip(results.iter_mut()) {
+            r.solutions.retain(|s| verifier.check(solver.name(), header.as_ref(), &s.solution_nonces));
+        }
+        Ok(results)
+    }
+
+    /// #Description 
+    ///
+    /// As [mine](#method.mine), but gives up once the given deadline passes.
+    /// See [mine_cancellable](#method.mine_cancellable) for how the plugin
+    /// call is abandoned.
+    ///
+    /// #Arguments
+    ///
+    /// * `header` (IN) As for [mine](#method.mine).
+    ///
+    /// * `solution` (OUT) As for [mine](#method.mine).
+    ///
+    /// * `deadline` (IN) When to give up.
+    ///
+    /// #Returns
+    ///
+    /// As for [mine](#method.mine), or a `MiningCancelledError` if the deadline
+    /// passes first.
+    ///
+
+    pub fn mine_with_deadline(&self, header: &[u8], solution:&mut CuckooMinerSolution,
+        deadline: time::Instant) -> Result<bool, CuckooMinerError> {
+        self.mine_cancellable(header, solution, &CancellationToken::new().with_deadline(deadline))
+    }
+
+    /// #Description 
+    ///
+    /// As [mine](#method.mine), but gives up as soon as the given
+    /// [CancellationToken](../cancel/struct.CancellationToken.html) is cancelled
+    /// from another thread, or its deadline passes. The plugin is called on a
+    /// helper thread. On cancellation, a plugin which exports `cuckoo_abort` is
+    /// asked to return early, and a plugin in a worker process has its worker
+    /// restarted. Any other plugin is left to finish the call in the background,
+    /// and the next mining call on this miner waits for it to.
+    ///
+    /// #Arguments
+    ///
+    /// * `header` (IN) As for [mine](#method.mine).
+    ///
+    /// * `solution` (OUT) As for [mine](#method.mine).
+    ///
+    /// * `cancel` (IN) The token to watch.
+    ///
+    /// #Returns
+    ///
+    /// As for [mine](#method.mine), or a `MiningCancelledError` if the token
+    /// is cancelled first.
+    ///
+
+    pub fn mine_cancellable(&self, header: &[u8], solution:&mut CuckooMinerSolution,
+        cancel: &CancellationToken) -> Result<bool, CuckooMinerError> {
+        let solver = self.solver_supporting(PluginCapabilities::SYNC, "Sync mining")?.clone();
+        self.wait_until_idle(Some(cancel))?;
+        let busy = self.busy.clone();
+        let header_bytes = header.to_vec();
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let mut nonces = Vec::new();
+            let result = solver.mine(&header_bytes, &mut nonces).map(|found| (found, nonces));
+            busy.store(false, Ordering::SeqCst);
+            let _ = sender.send(result);
+        });
+
+        let poll_interval = time::Duration::from_millis(CANCEL_POLL_INTERVAL_MS);
+        loop {
+            match receiver.recv_timeout(poll_interval) {
                 Ok(result) => {
-                    match result {
-                        1 => {
-                            debug!("Solution found."); 
-                            Ok(true)
-                        }
-                        0 => Ok(false),
-                        _ => Err(CuckooMinerError::UnexpectedResultError(result))
+                    let (found, nonces) = result?;
+                    let solver = self.solver()?;
+                    let found = found && self.verifier(solver).check(solver.name(), header, &nonces);
+                    if found {
+                        debug!("Solution found.");
+                        solution.solution_nonces = nonces;
+                    }
+                    return Ok(found);
+                },
+                Err(RecvTimeoutError::Timeout) => {
+                    if cancel.is_cancelled() {
+                        break;
                     }
                 },
-                Err(_) => Err(CuckooMinerError::PluginNotLoadedError(
-                    String::from("Please call init to load a miner plug-in"))),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(CuckooMinerError::PluginProcessingError(
+                        String::from("Mining thread exited without a result")));
+                },
             }
+        }
+
+        let solver = self.solver()?;
+        match solver.abort() {
+            //give the plugin a chance to return, so the next call needn't wait
+            Ok(_) => {
+                let _ = receiver.recv_timeout(time::Duration::from_millis(ABORT_WAIT_MS));
+            },
+            Err(e) => debug!("Abandoning mining call to {}: {:?}", solver.name(), e),
+        }
+        Err(cancelled_error(cancel))
+    }
+
+    // Waits for any cancelled call still running in the plugin to return,
+    // then marks the plugin busy. Gives up if the token is cancelled first
+
+    fn wait_until_idle(&self, cancel: Option<&CancellationToken>) -> Result<(), CuckooMinerError> {
+        while self.busy.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            if let Some(c) = cancel {
+                if c.is_cancelled() {
+                    return Err(cancelled_error(c));
+                }
+            }
+            thread::sleep(time::Duration::from_millis(CANCEL_POLL_INTERVAL_MS));
+        }
+        Ok(())
     }
 
     /// #Description 
@@ -523,6 +1127,10 @@ impl CuckooMiner {
     /// for how it wishes to manage processing or distribute the load. Once called
     /// this function will continue to find solutions over the target difficulty
     /// for the given inputs and place them into its output queue until instructed to stop. 
+    /// If the config sets `verify_solutions`, solutions which fail verification are
+    /// dropped rather than placed in the output queue. If a call abandoned by
+    /// [mine_cancellable](#method.mine_cancellable) is still running in the plugin,
+    /// this waits for it to return before processing starts.
     ///
     /// Once this function is called, the miner is consumed, and all interaction with the miner,
     /// including reading solutions or stopping the job, then takes place via the returned 
@@ -550,17 +1158,23 @@ impl CuckooMiner {
     /// is successfully started.
     /// * A [CuckooMinerError](../../error/error/enum.CuckooMinerError.html) 
     /// if there is no plugin loaded, or if there is an error calling the function.
+    /// * A `PluginUnsupportedError` if the loaded plugin doesn't support async mode
     ///
 
-    pub fn notify(mut self, 
+    pub fn notify(self, 
                   job_id: u32, //Job id
                   pre_nonce: &str, //Pre-nonce portion of header
                   post_nonce: &str, //Post-nonce portion of header
                   difficulty: u64  //The target difficulty, only sols greater than this difficulty will be returned.
-                  ) -> Result<CuckooMinerJobHandle, CuckooMinerError>{
+                  ) -> Result<CuckooMinerJobHandle<S>, CuckooMinerError>{
         
-        self.delegator=Delegator::new(job_id, pre_nonce, post_nonce, difficulty); 
-        Ok(self.delegator.start_job_loop().unwrap())
+        let solver = self.solver_supporting(PluginCapabilities::ASYNC_QUEUE, "Async mining")?.clone();
+        self.wait_until_idle(None)?;
+        let verifier = self.verifier(&solver);
+        let delegator=Delegator::new(solver, verifier, job_id, pre_nonce, post_nonce, difficulty); 
+        let result = delegator.start_job_loop();
+        self.busy.store(false, Ordering::SeqCst);
+        result
     }
                   
 }
\ No newline at end of file
@@ -0,0 +1,230 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Internal module wrapping the two ways a plugin can be loaded, either
+//! directly into this process or into a separate worker process, behind
+//! the [CuckooSolver](../solver/trait.CuckooSolver.html) trait.
+//!
+//!
+
+use std::path::PathBuf;
+
+use cuckoo_sys::{PluginHandle, PluginWorker, PluginCapabilities, CuckooPluginParameter, ParameterValue,
+                 TrustPolicy, default_worker_path, parse_parameter_list};
+use error::CuckooMinerError;
+use solver::CuckooSolver;
+
+/// A loaded plugin, either in-process or hosted by a worker process
+
+pub enum LoadedPlugin {
+    /// Plugin loaded into this process
+    InProcess(PluginHandle),
+
+    /// Plugin loaded into a worker process
+    Worker(PluginWorker),
+}
+
+// Forwards a call to whichever kind of plugin is loaded
+
+macro_rules! forward {
+    ($self_:ident, $call:ident ( $($arg:expr),* )) => {
+        match *$self_ {
+            LoadedPlugin::InProcess(ref p) => p.$call($($arg),*),
+            LoadedPlugin::Worker(ref p) => p.$call($($arg),*),
+        }
+    }
+}
+
+impl LoadedPlugin {
+
+    /// Loads the plugin at the given path in process, checking it against
+    /// the trust policy first if there is one
+
+    pub fn load(full_path: &str, trust_policy: Option<&TrustPolicy>) -> Result<LoadedPlugin, CuckooMinerError> {
+        let plugin = match trust_policy {
+            Some(p) => PluginHandle::load_trusted(full_path, p)?,
+            None => PluginHandle::load(full_path)?,
+        };
+        Ok(LoadedPlugin::InProcess(plugin))
+    }
+
+    /// Loads the plugin at the given path in a worker process, which checks
+    /// it against the trust policy first if there is one. If `worker_path`
+    /// is empty, the default worker location is used
+
+    pub fn load_in_worker(worker_path: &str, full_path: &str, trust_policy: Option<&TrustPolicy>)
+        -> Result<LoadedPlugin, CuckooMinerError> {
+        let worker_path = if worker_path.is_empty() {
+            match default_worker_path() {
+                Some(p) => p,
+                None => return Err(CuckooMinerError::PluginNotFoundError(
+                    String::from("Unable to locate the cuckoo_plugin_worker executable"))),
+            }
+        } else {
+            PathBuf::from(worker_path)
+        };
+        let worker = match trust_policy {
+            Some(p) => PluginWorker::spawn_trusted(worker_path, full_path, p)?,
+            None => PluginWorker::spawn(worker_path, full_path)?,
+        };
+        Ok(LoadedPlugin::Worker(worker))
+    }
+}
+
+impl CuckooSolver for LoadedPlugin {
+
+    fn name(&self) -> &str {
+        forward!(self, full_path())
+    }
+
+    fn capabilities(&self) -> PluginCapabilities {
+        forward!(self, capabilities())
+    }
+
+    fn proof_size(&self) -> usize {
+        forward!(self, proof_size()) as usize
+    }
+
+    fn mine(&self, header: &[u8], solution: &mut Vec<u32>) -> Result<bool, CuckooMinerError> {
+        match forward!(self, call_cuckoo(header, solution))? {
+            1 => Ok(true),
+            0 => Ok(false),
+            r => Err(CuckooMinerError::UnexpectedResultError(r)),
+        }
+    }
+
+    // Plugins which don't export cuckoo_call_all only report the first cycle
+
+    fn mine_all(&self, header: &[u8]) -> Result<Vec<Vec<u32>>, CuckooMinerError> {
+        if !self.capabilities().contains(PluginCapabilities::ALL_CYCLES) {
+            let mut solution = Vec::new();
+            return Ok(if self.mine(header, &mut solution)? { vec![solution] } else { Vec::new() });
+        }
+        let mut solutions = Vec::new();
+        forward!(self, call_cuckoo_all(header, &mut solutions))?;
+        Ok(solutions)
+    }
+
+    // In process, only plugins exporting cuckoo_abort can be interrupted. A
+    // worker can always be restarted, abandoning the call it's making
+
+    fn abort(&self) -> Result<(), CuckooMinerError> {
+        match *self {
+            LoadedPlugin::InProcess(ref p) => p.call_cuckoo_abort(),
+            LoadedPlugin::Worker(ref p) => p.restart(),
+        }
+    }
+
+    fn set_parameter(&self, name: &str, value: u32) -> Result<(), CuckooMinerError> {
+        let return_code = forward!(self, call_cuckoo_set_parameter(name.as_bytes(), value))?;
+        if return_code != 0 {
+            let reason = match return_code {
+                1 => "Property doesn't exist for this plugin",
+                2 => "Property outside allowed range",
+                _ => "Unknown Error"
+            };
+            return Err(CuckooMinerError::ParameterError(
+                format!("Error setting parameter: {} to {} - {}", name, value, reason)));
+        }
+        Ok(())
+    }
+
+    fn get_parameter(&self, name: &str) -> Result<u32, CuckooMinerError> {
+        let mut value = 0;
+        let return_code = forward!(self, call_cuckoo_get_parameter(name.as_bytes(), &mut value))?;
+        if return_code != 0 {
+            let reason = match return_code {
+                1 => "Property doesn't exist for this plugin",
+                _ => "Unknown Error"
+            };
+            return Err(CuckooMinerError::ParameterError(
+                format!("Error getting parameter: {} - {}", name, reason)));
+        }
+        Ok(value)
+    }
+
+    fn parameters(&self) -> Result<Vec<CuckooPluginParameter>, CuckooMinerError> {
+        let mut param_list_bytes = Vec::new();
+        match forward!(self, call_cuckoo_parameter_list(&mut param_list_bytes))? {
+            0 => parse_parameter_list(&param_list_bytes),
+            r => Err(CuckooMinerError::UnexpectedResultError(r)),
+        }
+    }
+
+    fn set_parameter_value(&self, name: &str, value: &ParameterValue) -> Result<(), CuckooMinerError> {
+        if !self.capabilities().contains(PluginCapabilities::TYPED_PARAMETERS) {
+            return match value.as_u32() {
+                Some(v) => self.set_parameter(name, v),
+                None => Err(CuckooMinerError::ParameterError(format!(
+                    "Error setting parameter: {} to {} - Plugin only accepts integer values",
+                    name, value))),
+            };
+        }
+        let json = value.to_json();
+        let return_code = forward!(self, call_cuckoo_set_parameter_value(name.as_bytes(), json.as_bytes()))?;
+        if return_code != 0 {
+            let reason = match return_code {
+                1 => "Property doesn't exist for this plugin",
+                2 => "Property outside allowed range",
+                _ => "Unknown Error"
+            };
+            return Err(CuckooMinerError::ParameterError(
+                format!("Error setting parameter: {} to {} - {}", name, value, reason)));
+        }
+        Ok(())
+    }
+
+    fn get_parameter_value(&self, name: &str) -> Result<ParameterValue, CuckooMinerError> {
+        if !self.capabilities().contains(PluginCapabilities::TYPED_PARAMETERS) {
+            return self.get_parameter(name).map(ParameterValue::Int);
+        }
+        let mut value_bytes = Vec::new();
+        let return_code = forward!(self, call_cuckoo_get_parameter_value(name.as_bytes(), &mut value_bytes))?;
+        if return_code != 0 {
+            let reason = match return_code {
+                1 => "Property doesn't exist for this plugin",
+                3 => "Value too large",
+                _ => "Unknown Error"
+            };
+            return Err(CuckooMinerError::ParameterError(
+                format!("Error getting parameter: {} - {}", name, reason)));
+        }
+        ParameterValue::from_json(&value_bytes)
+    }
+
+    fn start_processing(&self) -> Result<(), CuckooMinerError> {
+        forward!(self, call_cuckoo_start_processing()).map(|_| ())
+    }
+
+    fn stop_processing(&self) -> Result<(), CuckooMinerError> {
+        forward!(self, call_cuckoo_stop_processing()).map(|_| ())
+    }
+
+    fn is_queue_under_limit(&self) -> Result<bool, CuckooMinerError> {
+        Ok(forward!(self, call_cuckoo_is_queue_under_limit())? == 1)
+    }
+
+    fn push_to_input_queue(&self, hash: &[u8], nonce: &[u8; 8]) -> Result<bool, CuckooMinerError> {
+        Ok(forward!(self, call_cuckoo_push_to_input_queue(hash, nonce))? != 0)
+    }
+
+    fn read_from_output_queue(&self, solution: &mut Vec<u32>, nonce: &mut [u8; 8])
+        -> Result<bool, CuckooMinerError> {
+        Ok(forward!(self, call_cuckoo_read_from_output_queue(solution, nonce))? != 0)
+    }
+
+    fn hashes_since_last_call(&self) -> Result<u32, CuckooMinerError> {
+        forward!(self, call_cuckoo_hashes_since_last_call())
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! The interface between a [CuckooMiner](../miner/struct.CuckooMiner.html) and
+//! whatever actually searches for cycles. Plugins loaded from a dylib (either
+//! in-process or in a worker process) are one implementation, but any Rust type
+//! implementing [CuckooSolver](trait.CuckooSolver.html) can be linked straight
+//! into the calling binary and handed to
+//! [CuckooMiner::from_solver](../miner/struct.CuckooMiner.html#method.from_solver),
+//! e.g. a native Rust miner, or a fake in tests.
+//!
+//! The calls mirror the plugin interface described in cuckoo-sys, but with the
+//! plugin return codes translated into Rust types.
+
+use cuckoo_sys::{PluginCapabilities, CuckooPluginParameter, ParameterValue, DEFAULT_PROOF_SIZE};
+use error::CuckooMinerError;
+
+/// #Description
+///
+/// A cuckoo cycle solver, able to mine synchronously and/or asynchronously
+/// via input and output queues. Solvers are shared between the miner and
+/// its job threads, so must be `Send + Sync` and handle their own locking.
+///
+/// Only [name](#tymethod.name) and [capabilities](#tymethod.capabilities) are
+/// required. Every other call defaults to returning a `PluginUnsupportedError`,
+/// so a solver only needs to implement the calls covered by the capabilities
+/// it reports.
+///
+/// #Example
+///
+/// ```
+///  struct FixedSolver;
+///
+///  impl CuckooSolver for FixedSolver {
+///      fn name(&self) -> &str { "fixed" }
+///      fn capabilities(&self) -> PluginCapabilities { PluginCapabilities::SYNC }
+///      fn mine(&self, header: &[u8], solution: &mut Vec<u32>) -> Result<bool, CuckooMinerError> {
+///          *solution = (0..42).collect();
+///          Ok(true)
+///      }
+///  }
+///
+///  let miner = CuckooMiner::from_solver(FixedSolver);
+/// ```
+///
+
+pub trait CuckooSolver: Send + Sync {
+
+    /// Returns a name identifying the solver, used in error messages. For
+    /// plugins, this is the full path of the plugin library
+
+    fn name(&self) -> &str;
+
+    /// Returns which groups of calls the solver supports
+
+    fn capabilities(&self) -> PluginCapabilities;
+
+    /// Returns the number of nonces in each solution the solver finds
+
+    fn proof_size(&self) -> usize {
+        DEFAULT_PROOF_SIZE as usize
+    }
+
+    /// Searches for a cycle on the given header, filling `solution` with
+    /// the solution nonces and returning true if one is found
+
+    fn mine(&self, _header: &[u8], _solution: &mut Vec<u32>) -> Result<bool, CuckooMinerError> {
+        Err(self.unsupported("mine"))
+    }
+
+    /// Searches for every cycle on the given header, returning the solution
+    /// nonces of each. Defaults to the single cycle found by [mine](#method.mine),
+    /// for solvers which stop at the first
+
+    fn mine_all(&self, header: &[u8]) -> Result<Vec<Vec<u32>>, CuckooMinerError> {
+        let mut solution = Vec::new();
+        if self.mine(header, &mut solution)? {
+            Ok(vec![solution])
+        } else {
+            Ok(Vec::new())
+        }
+    }
+
+    /// Asks a [mine](#tymethod.mine) call in progress on another thread to
+    /// return as soon as it can, without waiting for it to do so. Solvers which
+    /// can't be interrupted leave this unsupported, and calls which are cancelled
+    /// are left to finish in the background
+
+    fn abort(&self) -> Result<(), CuckooMinerError> {
+        Err(self.unsupported("abort"))
+    }
+
+    /// Sets a parameter, returning a `ParameterError` if the parameter doesn't
+    /// exist or the value is out of range
+
+    fn set_parameter(&self, _name: &str, _value: u32) -> Result<(), CuckooMinerError> {
+        Err(self.unsupported("set_parameter"))
+    }
+
+    /// Returns the current value of a parameter, or a `ParameterError` if the
+    /// parameter doesn't exist
+
+    fn get_parameter(&self, _name: &str) -> Result<u32, CuckooMinerError> {
+        Err(self.unsupported("get_parameter"))
+    }
+
+    /// Returns the parameters the solver accepts, with their types and ranges
+
+    fn parameters(&self) -> Result<Vec<CuckooPluginParameter>, CuckooMinerError> {
+        Err(self.unsupported("parameters"))
+    }
+
+    /// Sets a parameter to a typed value, returning a `ParameterError` if the
+    /// parameter doesn't exist or the value isn't valid for it. Defaults to
+    /// passing integer and boolean values to [set_parameter](#method.set_parameter)
+
+    fn set_parameter_value(&self, name: &str, value: &ParameterValue) -> Result<(), CuckooMinerError> {
+        match value.as_u32() {
+            Some(v) => self.set_parameter(name, v),
+            None => Err(self.unsupported("set_parameter_value")),
+        }
+    }
+
+    /// Returns the current typed value of a parameter, or a `ParameterError` if
+    /// the parameter doesn't exist. Defaults to the integer value returned by
+    /// [get_parameter](#method.get_parameter)
+
+    fn get_parameter_value(&self, name: &str) -> Result<ParameterValue, CuckooMinerError> {
+        self.get_parameter(name).map(ParameterValue::Int)
+    }
+
+    /// Starts processing the input queue
+
+    fn start_processing(&self) -> Result<(), CuckooMinerError> {
+        Err(self.unsupported("start_processing"))
+    }
+
+    /// Stops processing the input queue
+
+    fn stop_processing(&self) -> Result<(), CuckooMinerError> {
+        Err(self.unsupported("stop_processing"))
+    }
+
+    /// Returns whether the input queue can accept more hashes
+
+    fn is_queue_under_limit(&self) -> Result<bool, CuckooMinerError> {
+        Err(self.unsupported("is_queue_under_limit"))
+    }
+
+    /// Pushes a hash and the nonce used to generate it to the input queue,
+    /// returning whether it was accepted
+
+    fn push_to_input_queue(&self, _hash: &[u8], _nonce: &[u8; 8]) -> Result<bool, CuckooMinerError> {
+        Err(self.unsupported("push_to_input_queue"))
+    }
+
+    /// Pops a solution and the nonce of the hash it was found for from the
+    /// output queue, returning false if the queue is empty
+
+    fn read_from_output_queue(&self, _solution: &mut Vec<u32>, _nonce: &mut [u8; 8])
+        -> Result<bool, CuckooMinerError> {
+        Err(self.unsupported("read_from_output_queue"))
+    }
+
+    /// Returns the number of hashes processed since this was last called
+
+    fn hashes_since_last_call(&self) -> Result<u32, CuckooMinerError> {
+        Err(self.unsupported("hashes_since_last_call"))
+    }
+
+    /// Error returned by calls the solver doesn't implement
+
+    fn unsupported(&self, call: &str) -> CuckooMinerError {
+        CuckooMinerError::PluginUnsupportedError(format!(
+            "{} is not supported by solver {}", call, self.name()))
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Verification of Cuckoo Cycle solutions, so that whatever a plugin returns
+//! can be checked before it's used. Ported from the verifier in Grin, itself
+//! ported from John Tromp's C and Java code at https://github.com/tromp/cuckoo.
+//! The graph's siphash keys are derived from a SHA256 of the header, as the
+//! plugins do.
+//!
+//! #Example
+//! ```
+//!  let graph = GraphParams::new(caps.edge_bits(), caps.easiness);
+//!  if miner.mine(&header, &mut solution)? {
+//!      verify(&header, graph.edge_bits, graph.easiness, &solution)?;
+//!  }
+//! ```
+//!
+//! Setting [CuckooMinerConfig::verify_solutions](../miner/struct.CuckooMinerConfig.html)
+//! has the miner do this for every solution it finds.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+use error::CuckooMinerError;
+use miner::CuckooMinerSolution;
+
+/// The largest number of edge bits a graph can have
+pub const MAX_EDGE_BITS: u32 = 62;
+
+/// The shape of the graph a plugin searches, as needed to verify
+/// its solutions
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphParams {
+    /// The number of edges in the graph, as a power of 2. This is one
+    /// less than the cuckoo size in a plugin's name
+    pub edge_bits: u32,
+
+    /// The percentage of the graph's nodes used as edges, usually 50
+    pub easiness: u32,
+}
+
+impl GraphParams {
+
+    /// Returns the parameters of a graph with the given edge bits and easiness
+
+    pub fn new(edge_bits: u32, easiness: u32) -> GraphParams {
+        GraphParams {
+            edge_bits: edge_bits,
+            easiness: easiness,
+        }
+    }
+}
+
+// Cuckoo graph generated from a header
+
+struct Graph {
+    mask: u64,
+    v: [u64; 4],
+}
+
+impl Graph {
+    fn new(header: &[u8], edge_bits: u32) -> Graph {
+        let mut hasher = Sha256::new();
+        let mut hashed = [0; 32];
+        hasher.input(header);
+        hasher.result(&mut hashed);
+
+        let k0 = u8_to_u64(&hashed, 0);
+        let k1 = u8_to_u64(&hashed, 8);
+        Graph {
+            v: [k0 ^ 0x736f6d6570736575,
+                k1 ^ 0x646f72616e646f6d,
+                k0 ^ 0x6c7967656e657261,
+                k1 ^ 0x7465646279746573],
+            mask: (1 << edge_bits) - 1,
+        }
+    }
+
+    // A node on one side of the graph, materialised from a nonce
+    // and the side (0 or 1)
+
+    fn new_node(&self, nonce: u64, uorv: u64) -> u64 {
+        ((siphash24(self.v, 2 * nonce + uorv) & self.mask) << 1) | uorv
+    }
+}
+
+fn invalid(reason: String) -> CuckooMinerError {
+    CuckooMinerError::InvalidSolutionError(reason)
+}
+
+/// #Description
+///
+/// Verifies that a solution's nonces form a single cycle in the Cuckoo graph
+/// generated from the given header. The nonces must be in ascending order and
+/// within the graph's easiness bound, and the cycle's length is the number of
+/// nonces, so callers expecting a particular proof size (usually 42) should
+/// check the solution's [proof_size](../miner/struct.CuckooMinerSolution.html#method.proof_size)
+/// too.
+///
+/// #Arguments
+///
+/// * `header` (IN) The header the solution was found for, as passed to the plugin.
+///
+/// * `edge_bits` (IN) The number of edges in the graph, as a power of 2.
+///
+/// * `easiness` (IN) The percentage of the graph's nodes used as edges.
+///
+/// * `solution` (IN) The solution to verify.
+///
+/// #Returns
+///
+/// Ok if the solution is valid, otherwise an `InvalidSolutionError` saying why not.
+///
+
+pub fn verify(header: &[u8], edge_bits: u32, easiness: u32, solution: &CuckooMinerSolution)
+    -> Result<(), CuckooMinerError> {
+    verify_nonces(header, edge_bits, easiness, &solution.solution_nonces)
+}
+
+fn verify_nonces(header: &[u8], edge_bits: u32, easiness: u32, nonces: &[u32])
+    -> Result<(), CuckooMinerError> {
+    if edge_bits == 0 || edge_bits > MAX_EDGE_BITS {
+        return Err(invalid(format!("Unsupported edge bits {}", edge_bits)));
+    }
+    let proof_size = nonces.len();
+    if proof_size == 0 || proof_size % 2 != 0 {
+        return Err(invalid(format!("Solution has {} nonces, cycles have an even length", proof_size)));
+    }
+    let size = 2u64 << edge_bits;
+    let max_nonce = size.saturating_mul(easiness as u64) / 100;
+
+    let graph = Graph::new(header, edge_bits);
+    let mut us = vec![0; proof_size];
+    let mut vs = vec![0; proof_size];
+    for n in 0..proof_size {
+        let nonce = nonces[n] as u64;
+        if nonce >= max_nonce {
+            return Err(invalid(format!("Nonce {} is outside the easiness bound {}", nonce, max_nonce)));
+        }
+        if n != 0 && nonces[n] <= nonces[n - 1] {
+            return Err(invalid(format!("Nonce {} isn't in ascending order", nonce)));
+        }
+        us[n] = graph.new_node(nonce, 0);
+        vs[n] = graph.new_node(nonce, 1);
+    }
+
+    //follow the cycle from the first edge, alternating sides, until it
+    //comes back round, checking each node joins exactly two edges
+    let mut i = 0;
+    let mut count = proof_size;
+    loop {
+        let mut j = i;
+        for k in 0..proof_size {
+            if k != i && vs[k] == vs[i] {
+                if j != i {
+                    return Err(invalid(String::from("Solution contains a branch")));
+                }
+                j = k;
+            }
+        }
+        if j == i {
+            return Err(invalid(String::from("Solution contains a dead end")));
+        }
+        i = j;
+        for k in 0..proof_size {
+            if k != j && us[k] == us[j] {
+                if i != j {
+                    return Err(invalid(String::from("Solution contains a branch")));
+                }
+                i = k;
+            }
+        }
+        if i == j {
+            return Err(invalid(String::from("Solution contains a dead end")));
+        }
+        count -= 2;
+        if i == 0 {
+            break;
+        }
+    }
+    if count != 0 {
+        return Err(invalid(format!("Solution contains a cycle of length {}, not {}",
+                                   proof_size - count, proof_size)));
+    }
+    Ok(())
+}
+
+/// Checks each solution a plugin returns against the configured graph,
+/// if any, counting those which are invalid
+
+#[derive(Clone)]
+pub(crate) struct SolutionVerifier {
+    graph: Option<GraphParams>,
+    proof_size: usize,
+    invalid_count: Arc<AtomicUsize>,
+}
+
+impl SolutionVerifier {
+
+    /// A verifier expecting solutions of the given proof size in the given
+    /// graph, or one which accepts everything if `graph` is None
+
+    pub fn new(graph: Option<GraphParams>, proof_size: usize, invalid_count: Arc<AtomicUsize>)
+        -> SolutionVerifier {
+        SolutionVerifier {
+            graph: graph,
+            proof_size: proof_size,
+            invalid_count: invalid_count,
+        }
+    }
+
+    /// Whether solutions are being verified at all
+
+    pub fn is_enabled(&self) -> bool {
+        self.graph.is_some()
+    }
+
+    /// The shared count of invalid solutions
+
+    pub fn invalid_count(&self) -> Arc<AtomicUsize> {
+        self.invalid_count.clone()
+    }
+
+    /// Whether the solution the named plugin found for the header may be
+    /// handed out. Invalid solutions are logged and counted
+
+    pub fn check(&self, plugin_name: &str, header: &[u8], nonces: &[u32]) -> bool {
+        let graph = match self.graph {
+            Some(g) => g,
+            None => return true,
+        };
+        let result = if nonces.len() != self.proof_size {
+            Err(invalid(format!("Solution has {} nonces, expected {}", nonces.len(), self.proof_size)))
+        } else {
+            verify_nonces(header, graph.edge_bits, graph.easiness, nonces)
+        };
+        match result {
+            Ok(_) => true,
+            Err(e) => {
+                warn!("Plugin {} returned an invalid solution {:?}: {:?}", plugin_name, nonces, e);
+                self.invalid_count.fetch_add(1, Ordering::SeqCst);
+                false
+            },
+        }
+    }
+}
+
+// Reads 8 bytes of a byte array as a little-endian u64
+
+fn u8_to_u64(p: &[u8; 32], i: usize) -> u64 {
+    (p[i] as u64) | (p[i + 1] as u64) << 8 | (p[i + 2] as u64) << 16 | (p[i + 3] as u64) << 24 |
+    (p[i + 4] as u64) << 32 | (p[i + 5] as u64) << 40 |
+    (p[i + 6] as u64) << 48 | (p[i + 7] as u64) << 56
+}
+
+// Siphash 2-4, specialised for a 4 u64 array key and a u64 nonce
+
+fn siphash24(v: [u64; 4], nonce: u64) -> u64 {
+    let mut v0 = v[0];
+    let mut v1 = v[1];
+    let mut v2 = v[2];
+    let mut v3 = v[3] ^ nonce;
+
+    // macro for left rotation
+    macro_rules! rotl {
+        ($num:ident, $shift:expr) => {
+            $num = ($num << $shift) | ($num >> (64 - $shift));
+        }
+    }
+
+    // macro for a single siphash round
+    macro_rules! round {
+        () => {
+            v0 = v0.wrapping_add(v1);
+            v2 = v2.wrapping_add(v3);
+            rotl!(v1, 13);
+            rotl!(v3, 16);
+            v1 ^= v0;
+            v3 ^= v2;
+            rotl!(v0, 32);
+            v2 = v2.wrapping_add(v1);
+            v0 = v0.wrapping_add(v3);
+            rotl!(v1, 17);
+            rotl!(v3, 21);
+            v1 ^= v2;
+            v3 ^= v0;
+            rotl!(v2, 32);
+        }
+    }
+
+    // 2 rounds
+    round!();
+    round!();
+
+    v0 ^= nonce;
+    v2 ^= 0xff;
+
+    // and then 4 rounds, hence siphash 2-4
+    round!();
+    round!();
+    round!();
+    round!();
+
+    v0 ^ v1 ^ v2 ^ v3
+}
@@ -32,10 +32,12 @@ pub use error::CuckooMinerError;
 pub use miner::{CuckooMinerConfig,
                 CuckooMiner,
                 CuckooMinerSolution,
-                CuckooMinerJobHandle};
+                CuckooMinerJobHandle,
+                CuckooSolver};
 
 pub use manager::{CuckooPluginManager,
-                  CuckooPluginCapabilities};
+                  CuckooPluginCapabilities,
+                  PluginCapabilities};
 
 
 
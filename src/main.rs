@@ -43,13 +43,16 @@ fn main() {
                        0xfb,0xa2,0xbe,0xe0,0xd0,0x52,0xcb,0x2d,
                        0xc9,0x56,0x06,0x4f,0x8a,0x8a,0xcd,0x54];*/
 
-    //First, load and query the plugins in the given directory
+    //First, load and query the plugins on the search path, which starts with
+    //any directories in CUCKOO_PLUGIN_PATH
     let mut plugin_manager = CuckooPluginManager::new().unwrap();
-    let result=plugin_manager.load_plugin_dir(String::from("target/debug"));
+    let result=plugin_manager.load_plugin_search_path(vec![String::from("target/debug")]);
     match result {
         Ok(_) => {},
         Err(e) => println!("{:?}",e),
     }
+    //Show which files were rejected, and why
+    println!("{}", plugin_manager.get_discovery_report());
     //Get a list of installed plugins and capabilities
     let caps = plugin_manager.get_available_plugins("simple_16").unwrap();
 
@@ -63,8 +66,8 @@ fn main() {
     
     let mut config = CuckooMinerConfig::new();
     config.plugin_full_path = caps[0].full_path.clone();
-    //config.parameter_list.insert(String::from("NUM_TRIMS"), 5);
-    //config.parameter_list.insert(String::from("NUM_THREADS"), 8);
+    //config.parameter_list.insert(String::from("NUM_TRIMS"), ParameterValue::Int(5));
+    //config.parameter_list.insert(String::from("NUM_THREADS"), ParameterValue::Int(8));
     
     //Build a new miner with this info, which will load
     //the associated plugin and 
@@ -23,7 +23,7 @@ extern crate time;
 
 
 use error::CuckooMinerError;
-use miner::{CuckooMinerConfig, CuckooMinerSolution, CuckooMiner};
+use miner::{CuckooMinerConfig, CuckooMinerSolution, CuckooMiner, ParameterValue};
 use manager::{CuckooPluginManager, CuckooPluginCapabilities};
 
 // Helper function, tests a particular miner implementation against a known set
@@ -80,4 +80,31 @@ fn mine_async(){
     mine_for_duration("simple_16", 5);
     std::thread::sleep(std::time::Duration::from_millis(20));
     mine_for_duration("edgetrim_16", 5);
-}
\ No newline at end of file
+}
+
+
+// The pure Rust plugin is always built, so check its queues are
+// actually being processed
+
+#[test]
+fn mine_async_rust_plugin(){
+    let pre_header="00000000000000118e0fe6bcfaa76c6795592339f27b6d330d8f9c4ac8e86171a66357d1\
+    d0fce808000000005971f14f0000000000000000000000000000000000000000000000000000000000000000\
+    3e1fcdd453ce51ffbb16dd200aeb9ef7375aec196e97094868428a7325e4a19b00";
+    let post_header="010a020364";
+
+    let mut plugin_manager = CuckooPluginManager::new().unwrap();
+    plugin_manager.load_plugin_dir(String::from("target/debug")).expect("");
+    let caps = plugin_manager.get_available_plugins("rust_simple_16").unwrap();
+
+    let mut config = CuckooMinerConfig::new();
+    config.plugin_full_path = caps[0].full_path.clone();
+    config.parameter_list.insert(String::from("NUM_THREADS"), ParameterValue::Int(2));
+
+    let miner = CuckooMiner::new(config).expect("");
+    let job_handle=miner.notify(1, pre_header, post_header, 10).unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(1000));
+    let hashes=job_handle.get_hashes_since_last_call().unwrap();
+    job_handle.stop_jobs();
+    assert!(hashes > 0);
+}
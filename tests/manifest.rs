@@ -0,0 +1,320 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Tests for plugin discovery via manifest files. The plugin libraries
+//! here are just junk files, so any attempt to load one fails.
+
+extern crate manager;
+extern crate error;
+extern crate crypto;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use crypto::digest::Digest;
+use crypto::ed25519;
+use crypto::sha2::Sha256;
+
+use error::CuckooMinerError;
+use manager::{CuckooPluginManager, CuckooVariation, PluginCapabilities, PluginQuery, PluginSortKey,
+              PluginSource, PluginEvent, PluginWatcher, RejectionReason, TrustPolicy, PLUGIN_PATH_ENV_VAR};
+
+// Creates a fresh directory holding a junk plugin library and its manifest
+
+fn plugin_dir(name: &str, manifest_extension: &str, manifest: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("cuckoo_manifest_{}_{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    add_plugin(&dir, name, manifest_extension, manifest);
+    dir
+}
+
+// Adds a junk plugin library and its manifest to a directory
+
+fn add_plugin(dir: &PathBuf, name: &str, manifest_extension: &str, manifest: &str) {
+    let lib_name = format!("{}cuckoo_{}{}", env::consts::DLL_PREFIX, name, env::consts::DLL_SUFFIX);
+    File::create(dir.join(&lib_name)).unwrap().write_all(b"not a library").unwrap();
+    let manifest_path = dir.join(lib_name).with_extension(manifest_extension);
+    File::create(manifest_path).unwrap().write_all(manifest.as_bytes()).unwrap();
+}
+
+#[test]
+fn manifest_plugin_caps() {
+    let dir = plugin_dir("mean_30", "toml", "name = \"mean_30\"
+        variation = \"mean\"
+        cuckoo_size = 30
+        memory_required = 3221225472
+        cpu_features = [\"avx2\"]
+        capabilities = [\"sync\", \"parameters\"]
+
+        [[parameters]]
+        name = \"NUM_THREADS\"
+        description = \"Number of threads\"
+        default_value = 1
+        min_value = 1
+        max_value = 32
+        ");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    let caps = manager.get_available_plugins("").unwrap();
+    assert_eq!(caps.len(), 1);
+    assert_eq!(caps[0].name, "mean_30");
+    assert_eq!(caps[0].variation, CuckooVariation::Mean);
+    assert_eq!(caps[0].cuckoo_size, 30);
+    assert_eq!(caps[0].proof_size, 42);
+    assert_eq!(caps[0].memory_required, 3221225472);
+    assert_eq!(caps[0].cpu_features, vec!["avx2"]);
+    assert!(caps[0].capabilities.contains(PluginCapabilities::SYNC | PluginCapabilities::PARAMETERS));
+    assert!(!caps[0].capabilities.contains(PluginCapabilities::ASYNC_QUEUE));
+    assert_eq!(caps[0].parameters[0].max_value, 32);
+    assert!(caps[0].manifest_path.as_ref().unwrap().ends_with(".toml"));
+
+    //JSON manifests work too, and an empty one names the plugin after its file
+    let dir = plugin_dir("simple_16", "json", "{}");
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    let caps = manager.get_available_plugins("").unwrap();
+    assert_eq!(caps[0].name, format!("{}cuckoo_simple_16", env::consts::DLL_PREFIX));
+    assert!(caps[0].capabilities.is_empty());
+}
+
+#[test]
+fn manifest_invalid() {
+    for &(name, extension, manifest) in &[("bad_toml", "toml", "name = "),
+                                          ("bad_proof", "json", "{\"proof_size\": 41}"),
+                                          ("bad_caps", "toml", "capabilities = [\"teleport\"]")] {
+        let dir = plugin_dir(name, extension, manifest);
+        let mut manager = CuckooPluginManager::new().unwrap();
+        match manager.load_plugin_dir(dir.to_str().unwrap().to_owned()) {
+            Err(CuckooMinerError::NoPluginsFoundError(_)) => {},
+            r => panic!("Unexpected result for {}: {:?}", name, r),
+        }
+        match manager.get_discovery_report().rejected[0].reason {
+            RejectionReason::InvalidManifest(_) => {},
+            ref r => panic!("Unexpected reason for {}: {:?}", name, r),
+        }
+    }
+}
+
+#[test]
+fn manifest_discovery_report() {
+    //a junk library without a manifest can't be loaded, but doesn't stop discovery
+    let dir = plugin_dir("mean_30", "toml", "name = \"mean_30\"");
+    add_plugin(&dir, "bad_toml", "toml", "name = ");
+    let lib_name = format!("{}cuckoo_junk{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX);
+    File::create(dir.join(&lib_name)).unwrap().write_all(b"not a library").unwrap();
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    let report = manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert!(!report.is_clean());
+    assert_eq!(report.plugins.len(), 1);
+    assert_eq!(report.plugins[0].name, "mean_30");
+    assert_eq!(report.rejected.len(), 2);
+    for r in &report.rejected {
+        match r.reason {
+            RejectionReason::InvalidManifest(_) => assert!(r.full_path.contains("bad_toml")),
+            RejectionReason::InitFailed(_) => assert!(r.full_path.ends_with(&lib_name)),
+            ref e => panic!("Unexpected reason: {:?}", e),
+        }
+    }
+    assert!(report.to_string().starts_with("1 plugin(s) found, 2 rejected"));
+
+    //rejected files are reported again on refresh, and dropped once fixed
+    assert!(manager.refresh().unwrap().is_empty());
+    assert_eq!(manager.get_discovery_report().rejected.len(), 2);
+    thread::sleep(Duration::from_millis(20));
+    add_plugin(&dir, "bad_toml", "toml", "name = \"fixed\"");
+    match manager.refresh().unwrap()[..] {
+        [PluginEvent::Added(ref c)] => assert_eq!(c.name, "fixed"),
+        ref e => panic!("Unexpected events: {:?}", e),
+    }
+    assert_eq!(manager.get_discovery_report().rejected.len(), 1);
+}
+
+#[test]
+fn manifest_cpu_features() {
+    if cfg!(any(target_arch = "x86", target_arch = "x86_64")) {
+        assert!(manager::host_cpu_features().contains(&String::from("sse")));
+    }
+
+    let dir = plugin_dir("future_30", "toml", "cpu_features = [\"sse\", \"avx9000\"]");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert!(manager.get_available_plugins("").unwrap().is_empty());
+    match manager.get_available_plugins("future_30") {
+        Err(CuckooMinerError::NoPluginsFoundError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    let unsupported = manager.get_unsupported_plugins();
+    assert_eq!(unsupported.len(), 1);
+    let reason = unsupported[0].unsupported_reason.as_ref().unwrap();
+    assert!(reason.contains("avx9000"));
+    assert!(!reason.contains("sse"));
+}
+
+#[test]
+fn manifest_query() {
+    let plugin = |variation: &str, size: u32, memory: u64, caps: &str| {
+        format!("name = \"{}_{}\"\nvariation = \"{}\"\ncuckoo_size = {}\n\
+                 memory_required = {}\ncapabilities = [{}]\n\
+                 [[parameters]]\nname = \"NUM_THREADS\"\ndescription = \"\"\n\
+                 default_value = 1\nmin_value = 1\nmax_value = 8\n",
+                variation, size, variation, size, memory, caps)
+    };
+    let dir = plugin_dir("mean_30", "toml", &plugin("mean", 30, 3 << 30, "\"sync\", \"async_queue\""));
+    add_plugin(&dir, "tomato_30", "toml", &plugin("tomato", 30, 2 << 30, "\"sync\", \"async_queue\""));
+    add_plugin(&dir, "simple_30", "toml", &plugin("simple", 30, 1 << 30, "\"sync\""));
+    add_plugin(&dir, "edgetrim_28", "toml", &plugin("edgetrim", 28, 5 << 30, "\"sync\", \"async_queue\""));
+    add_plugin(&dir, "tomato_32", "json", "{\"name\": \"tomato_32\", \"variation\": \"tomato\", \"cuckoo_size\": 32}");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+
+    let names = |query: &PluginQuery| {
+        manager.query_plugins(query).unwrap().into_iter().map(|c| c.name).collect::<Vec<_>>()
+    };
+    assert_eq!(names(&PluginQuery::new()).len(), 5);
+    assert_eq!(names(&PluginQuery::new().variation("Tomato").sort_by_descending(PluginSortKey::CuckooSize)),
+               vec!["tomato_32", "tomato_30"]);
+    assert_eq!(names(&PluginQuery::new().cuckoo_size_range(30, 32).requires_parameter("NUM_THREADS")),
+               vec!["mean_30", "simple_30", "tomato_30"]);
+
+    //async-capable cuckoo30 plugins that fit in 4 GB, smallest first
+    let query = PluginQuery::new()
+        .cuckoo_size_range(30, 30)
+        .requires_capabilities(PluginCapabilities::ASYNC_QUEUE)
+        .max_memory(4 << 30)
+        .sort_by(PluginSortKey::MemoryRequired);
+    assert_eq!(names(&query), vec!["tomato_30", "mean_30"]);
+
+    match manager.query_plugins(&PluginQuery::new().requires_parameter("NO_SUCH_PARAM")) {
+        Err(CuckooMinerError::NoPluginsFoundError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn manifest_search_path() {
+    let env_dir = plugin_dir("env_mean_30", "toml", "name = \"mean_30\"\ndescription = \"from env\"");
+    let configured_dir = plugin_dir("mean_30", "toml", "name = \"mean_30\"\ndescription = \"configured\"");
+    add_plugin(&configured_dir, "tomato_30", "toml", "name = \"tomato_30\"");
+    env::set_var(PLUGIN_PATH_ENV_VAR, env::join_paths(vec![&env_dir, &configured_dir]).unwrap());
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_search_path(vec![String::from("/no/such/dir"),
+                                         configured_dir.to_str().unwrap().to_owned()]).unwrap();
+    env::remove_var(PLUGIN_PATH_ENV_VAR);
+
+    //the configured dir was already on the path from the environment
+    let search_path = manager.get_search_path();
+    assert_eq!(search_path[0], (env_dir.to_str().unwrap().to_owned(), PluginSource::Environment));
+    assert_eq!(search_path[1], (configured_dir.to_str().unwrap().to_owned(), PluginSource::Environment));
+    assert_eq!(search_path[2], (String::from("/no/such/dir"), PluginSource::Configured));
+
+    let caps = manager.get_available_plugins("").unwrap();
+    assert_eq!(caps.len(), 2);
+    assert_eq!(caps[0].name, "mean_30");
+    assert_eq!(caps[0].description, "from env");
+    assert_eq!(caps[0].plugin_dir, env_dir.to_str().unwrap());
+    assert_eq!(caps[0].shadowed.len(), 1);
+    assert!(caps[0].shadowed[0].starts_with(configured_dir.to_str().unwrap()));
+    assert_eq!(caps[1].name, "tomato_30");
+    assert!(caps[1].shadowed.is_empty());
+
+    //without the environment, configured directories come first
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_search_path(vec![configured_dir.to_str().unwrap().to_owned(),
+                                         env_dir.to_str().unwrap().to_owned()]).unwrap();
+    let caps = manager.get_available_plugins("mean_30").unwrap();
+    assert_eq!(caps[0].description, "configured");
+    assert_eq!(caps[0].source, PluginSource::Configured);
+}
+
+#[test]
+fn manifest_refresh() {
+    let dir = plugin_dir("simple_16", "toml", "name = \"simple_16\"");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert!(manager.refresh().unwrap().is_empty());
+
+    //make sure modification times move on
+    thread::sleep(Duration::from_millis(20));
+    add_plugin(&dir, "simple_16", "toml", "name = \"simple_16\"\ndescription = \"rebuilt\"");
+    add_plugin(&dir, "tomato_16", "toml", "name = \"tomato_16\"");
+    let events = manager.refresh().unwrap();
+    assert_eq!(events.len(), 2);
+    match events[0] {
+        PluginEvent::Changed(ref c) => assert_eq!(c.description, "rebuilt"),
+        ref e => panic!("Unexpected event: {:?}", e),
+    }
+    match events[1] {
+        PluginEvent::Added(ref c) => assert_eq!(c.name, "tomato_16"),
+        ref e => panic!("Unexpected event: {:?}", e),
+    }
+
+    //and the watcher publishes the same events from its own thread
+    let (watcher, events) = PluginWatcher::start(manager, Duration::from_millis(10));
+    let lib_name = format!("{}cuckoo_tomato_16{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX);
+    fs::remove_file(dir.join(&lib_name)).unwrap();
+    match events.recv_timeout(Duration::from_secs(5)).unwrap() {
+        PluginEvent::Removed(p) => assert!(p.ends_with(&lib_name)),
+        e => panic!("Unexpected event: {:?}", e),
+    }
+    assert_eq!(watcher.manager().get_available_plugins("").unwrap().len(), 1);
+}
+
+#[test]
+fn manifest_trust_policy() {
+    let (secret_key, public_key) = ed25519::keypair(&[7; 32]);
+    let signature = ed25519::signature(b"not a library", &secret_key);
+    let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+
+    //only the signed library is trusted, even though the others have manifests
+    let dir = plugin_dir("signed_30", "toml", "name = \"signed_30\"");
+    add_plugin(&dir, "unsigned_30", "toml", "name = \"unsigned_30\"");
+    add_plugin(&dir, "forged_30", "toml", "name = \"forged_30\"");
+    let lib_path = |name: &str| {
+        dir.join(format!("{}cuckoo_{}{}", env::consts::DLL_PREFIX, name, env::consts::DLL_SUFFIX))
+    };
+    let sig_path = |name: &str| format!("{}.sig", lib_path(name).to_str().unwrap());
+    File::create(sig_path("signed_30")).unwrap().write_all(hex(&signature).as_bytes()).unwrap();
+    File::create(sig_path("forged_30")).unwrap().write_all(&[0; 64]).unwrap();
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.set_trust_policy(Some(TrustPolicy::new().trust_key_hex(&hex(&public_key)).unwrap()));
+    let report = manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(report.plugins.len(), 1);
+    assert_eq!(report.plugins[0].name, "signed_30");
+    assert_eq!(report.rejected.len(), 2);
+    for r in &report.rejected {
+        match r.reason {
+            RejectionReason::Untrusted(_) => {},
+            ref e => panic!("Unexpected reason: {:?}", e),
+        }
+    }
+
+    //allowlists use the format written by sha256sum, and every junk library is the same
+    let mut hasher = Sha256::new();
+    hasher.input(b"not a library");
+    let allowlist = dir.join("plugins.sha256");
+    File::create(&allowlist).unwrap().write_all(
+        format!("# trusted plugins\n{}  libcuckoo_signed_30.so\n", hasher.result_str()).as_bytes()).unwrap();
+    let policy = TrustPolicy::new().load_allowlist(allowlist.to_str().unwrap()).unwrap();
+    manager.set_trust_policy(Some(policy));
+    assert_eq!(manager.refresh().unwrap().len(), 2);
+    assert!(manager.get_discovery_report().is_clean());
+}
@@ -0,0 +1,656 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Tests using the scriptable mock plugin, to exercise the miner's handling
+//! of plugin results deterministically. The mock is configured via an environment
+//! variable and has global state, so tests hold a lock while running.
+
+extern crate miner;
+extern crate manager;
+extern crate error;
+#[macro_use]
+extern crate lazy_static;
+extern crate serde_json;
+extern crate crypto;
+
+use std::{cmp, env};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+use error::CuckooMinerError;
+use miner::{CuckooMinerConfig, CuckooMinerSolution, CuckooMiner, GraphParams, ParameterValue,
+            default_worker_path};
+use manager::{CuckooPluginManager, CuckooPluginCapabilities, CuckooVariation, PluginCapabilities, PluginEvent,
+              RejectionReason, BenchmarkConfig, TrustPolicy};
+
+lazy_static!{
+    static ref MOCK_LOCK: Mutex<()> = Mutex::new(());
+}
+
+static PRE_HEADER:&str = "00000000000000118e0fe6bcfaa76c6795592339f27b6d330d8f9c4ac8e86171a66357d1";
+static POST_HEADER:&str = "010a020364";
+
+// Locks the mock for the test, even if an earlier test panicked holding the lock
+
+fn lock_mock() -> MutexGuard<'static, ()> {
+    MOCK_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// Whether to run each test in process and in a worker. The worker executable
+// is only built by cargo build, not cargo test, so worker runs are skipped
+// without it (cuckoo-sys has worker tests which always run)
+
+fn worker_modes() -> Vec<bool> {
+    if default_worker_path().is_some() {
+        return vec![false, true];
+    }
+    println!("cuckoo_plugin_worker not found, skipping worker process runs. \
+              Build it with cargo build --all to include them");
+    vec![false]
+}
+
+// Helper to build a config for the mock, with the given script
+
+fn mock_config(script: &str) -> CuckooMinerConfig {
+    env::set_var("CUCKOO_MOCK_CONFIG", script);
+    let mut config = CuckooMinerConfig::new();
+    config.plugin_full_path = format!("target/debug/{}cuckoo_mock{}",
+                                      env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX);
+    config
+}
+
+// Solution nonces 72..114, whose hash has a difficulty of at least 1
+
+fn test_solution() -> CuckooMinerSolution {
+    let mut nonces = [0; 42];
+    for i in 0..42 {
+        nonces[i] = 72 + i as u32;
+    }
+    let mut solution = CuckooMinerSolution::new();
+    solution.set_solution(&nonces);
+    solution
+}
+
+// The difficulty of a solution, as calculated by the delegator
+
+fn solution_difficulty(solution: &CuckooMinerSolution) -> u64 {
+    let num = solution.hash()[0..8].iter().fold(0u64, |acc, b| acc << 8 | *b as u64);
+    0x0fffffffffffffff / num
+}
+
+#[test]
+fn mock_sync_solutions() {
+    let _lock = lock_mock();
+    let hash = [1u8; 32];
+    let script = format!("{{\"solutions\": {{\"{}\": {:?}}}}}",
+                         "01".repeat(32), &test_solution().solution_nonces[..]);
+    let miner = CuckooMiner::new(mock_config(&script)).unwrap();
+
+    let mut solution = CuckooMinerSolution::new();
+    assert!(miner.mine(&hash, &mut solution).unwrap());
+    assert_eq!(solution, test_solution());
+
+    let mut solution = CuckooMinerSolution::new();
+    assert!(!miner.mine(&[2u8; 32], &mut solution).unwrap());
+}
+
+// Another solution with a difficulty of at least 1
+
+fn second_solution() -> CuckooMinerSolution {
+    (0..).map(|k: u32| {
+        let mut solution = CuckooMinerSolution::new();
+        solution.set_solution(&(k..k + 42).collect::<Vec<_>>());
+        solution
+    }).find(|s| *s != test_solution() && solution_difficulty(s) > 0).unwrap()
+}
+
+#[test]
+fn mock_all_cycles() {
+    let _lock = lock_mock();
+    let (first, second) = (test_solution(), second_solution());
+    let script = format!("{{\"solutions\": {{\"{}\": {:?}}}, \"extra_solutions\": [{:?}]}}",
+                         "01".repeat(32), &first.solution_nonces[..], &second.solution_nonces[..]);
+
+    for use_worker_process in worker_modes() {
+        let mut config = mock_config(&script);
+        config.use_worker_process = use_worker_process;
+        let miner = CuckooMiner::new(config).unwrap();
+        assert!(miner.capabilities().contains(PluginCapabilities::ALL_CYCLES));
+        assert_eq!(miner.mine_all(&[1u8; 32]).unwrap(), vec![first.clone(), second.clone()]);
+        assert!(miner.mine_all(&[2u8; 32]).unwrap().is_empty());
+
+        //mine still returns just the first
+        let mut solution = CuckooMinerSolution::new();
+        assert!(miner.mine(&[1u8; 32], &mut solution).unwrap());
+        assert_eq!(solution, first);
+    }
+
+    //in async mode, both cycles are returned for the same nonce
+    let script = format!("{{\"default_solution\": {:?}, \"extra_solutions\": [{:?}]}}",
+                         &first.solution_nonces[..], &second.solution_nonces[..]);
+    let difficulty = cmp::min(solution_difficulty(&first), solution_difficulty(&second)) - 1;
+    let miner = CuckooMiner::new(mock_config(&script)).unwrap();
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, difficulty).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    job_handle.stop_jobs();
+    thread::sleep(Duration::from_millis(100));
+    let found = job_handle.shared_data.read().unwrap().solutions.clone();
+    assert!(!found.is_empty());
+    for pair in found.chunks(2) {
+        assert_eq!(pair[0], first);
+        assert_eq!(pair[1], second);
+        assert_eq!(pair[0].nonce, pair[1].nonce);
+    }
+}
+
+#[test]
+fn mock_mine_batch() {
+    let _lock = lock_mock();
+    let script = format!("{{\"solutions\": {{\"{}\": {:?}}}}}",
+                         "01".repeat(32), &test_solution().solution_nonces[..]);
+    //more headers than fit in the mock's queue at once
+    let headers = (0..40).map(|i| [(i % 4) as u8; 32]).collect::<Vec<_>>();
+
+    for use_worker_process in worker_modes() {
+        let mut config = mock_config(&script);
+        config.use_worker_process = use_worker_process;
+        let miner = CuckooMiner::new(config).unwrap();
+        let results = miner.mine_batch(&headers).unwrap();
+        assert_eq!(results.len(), headers.len());
+        for (i, r) in results.iter().enumerate() {
+            assert_eq!(r.found(), i % 4 == 1);
+            for s in &r.solutions {
+                assert_eq!(*s, test_solution());
+                assert_eq!(s.get_nonce_as_u64(), i as u64);
+            }
+        }
+
+        //the queue is stopped again afterwards
+        assert!(miner.mine_batch(&headers[..2]).unwrap()[1].found());
+    }
+}
+
+#[test]
+fn mock_set_parameter_errors() {
+    let _lock = lock_mock();
+    let script = "{\"set_parameter_results\": {\"MISSING\": 1, \"OUT_OF_RANGE\": 2}}";
+    let mut miner = CuckooMiner::new(mock_config(script)).unwrap();
+
+    assert!(miner.set_parameter(String::from("NUM_THREADS"), 4).is_ok());
+    match miner.set_parameter(String::from("MISSING"), 4) {
+        Err(CuckooMinerError::ParameterError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    match miner.set_parameter(String::from("OUT_OF_RANGE"), 4) {
+        Err(CuckooMinerError::ParameterError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+
+    //errors setting parameters from the config should fail miner creation
+    let mut config = mock_config(script);
+    config.parameter_list.insert(String::from("OUT_OF_RANGE"), ParameterValue::Int(1));
+    assert!(CuckooMiner::new(config).is_err());
+}
+
+#[test]
+fn mock_typed_parameters() {
+    let _lock = lock_mock();
+    let script = "{\"parameters\": [
+        {\"name\": \"THREADS\", \"description\": \"\", \"default_value\": 1, \"min_value\": 1, \"max_value\": 8},
+        {\"name\": \"VERBOSE\", \"type\": \"bool\", \"description\": \"\", \"default_value\": false},
+        {\"name\": \"MODE\", \"type\": \"enum\", \"description\": \"\", \"default_value\": \"lean\",
+         \"values\": [\"lean\", \"mean\"]},
+        {\"name\": \"DEVICES\", \"type\": \"string\", \"description\": \"\", \"default_value\": \"\"},
+        {\"name\": \"DEVICE_THREADS\", \"type\": \"per_device\", \"description\": \"\", \"default_value\": [1],
+         \"min_value\": 1, \"max_value\": 4}]}";
+    let values = vec![("THREADS", ParameterValue::Int(8)),
+                      ("VERBOSE", ParameterValue::Bool(true)),
+                      ("MODE", ParameterValue::from("mean")),
+                      ("DEVICES", ParameterValue::from("0,2")),
+                      ("DEVICE_THREADS", ParameterValue::PerDevice(vec![2, 4]))];
+
+    for use_worker_process in worker_modes() {
+        let mut config = mock_config(script);
+        config.use_worker_process = use_worker_process;
+        let mut miner = CuckooMiner::new(config).unwrap();
+        assert!(miner.capabilities().contains(PluginCapabilities::TYPED_PARAMETERS));
+        for &(ref name, ref value) in &values {
+            miner.set_parameter_value(String::from(*name), value.clone()).unwrap();
+            assert_eq!(&miner.get_parameter_value(name).unwrap(), value);
+        }
+        assert_eq!(miner.get_parameter("THREADS").unwrap(), 8);
+    }
+
+    //invalid values never reach the plugin
+    let mut miner = CuckooMiner::new(mock_config(script)).unwrap();
+    let invalid = vec![("THREADS", ParameterValue::Int(9)),
+                       ("THREADS", ParameterValue::Bool(true)),
+                       ("VERBOSE", ParameterValue::Int(1)),
+                       ("MODE", ParameterValue::from("tomato")),
+                       ("DEVICE_THREADS", ParameterValue::PerDevice(vec![2, 5])),
+                       ("DEVICE_THREADS", ParameterValue::PerDevice(vec![])),
+                       ("MISSING", ParameterValue::Int(1))];
+    for (name, value) in invalid {
+        match miner.set_parameter_value(String::from(name), value) {
+            Err(CuckooMinerError::ParameterError(_)) => {},
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        assert!(miner.get_parameter_value(name).is_err());
+    }
+
+    //as are invalid values in the config
+    let mut config = mock_config(script);
+    config.parameter_list.insert(String::from("MODE"), ParameterValue::from("tomato"));
+    assert!(CuckooMiner::new(config).is_err());
+    let mut config = mock_config(script);
+    config.parameter_list.insert(String::from("MODE"), ParameterValue::from("lean"));
+    let miner = CuckooMiner::new(config).unwrap();
+    assert_eq!(miner.get_parameter_value("MODE").unwrap(), ParameterValue::from("lean"));
+}
+
+#[test]
+fn mock_mine_with_deadline() {
+    let _lock = lock_mock();
+    let script = format!("{{\"call_delay_ms\": 5000, \"default_solution\": {:?}}}",
+                         &test_solution().solution_nonces[..]);
+
+    //in process, the plugin is asked to abort, and in a worker the worker is
+    //restarted, so either way later calls needn't wait for the abandoned one
+    for use_worker_process in worker_modes() {
+        let mut config = mock_config(&script);
+        config.use_worker_process = use_worker_process;
+        let mut miner = CuckooMiner::new(config).unwrap();
+        assert!(miner.capabilities().contains(PluginCapabilities::ABORT));
+        miner.set_parameter(String::from("NUM_THREADS"), 4).unwrap();
+        let start = Instant::now();
+        for _ in 0..2 {
+            let mut solution = CuckooMinerSolution::new();
+            let deadline = Instant::now() + Duration::from_millis(100);
+            match miner.mine_with_deadline(&[1u8; 32], &mut solution, deadline) {
+                Err(CuckooMinerError::MiningCancelledError(_)) => {},
+                r => panic!("Unexpected result: {:?}", r),
+            }
+        }
+        assert!(start.elapsed() < Duration::from_secs(4));
+        //restarted workers have their parameters set again
+        assert_eq!(miner.get_parameter("NUM_THREADS").unwrap(), 4);
+    }
+}
+
+#[test]
+fn mock_async_difficulty_filtering() {
+    let _lock = lock_mock();
+    let solution = test_solution();
+    let difficulty = solution_difficulty(&solution);
+    assert!(difficulty > 0);
+    let script = format!("{{\"default_solution\": {:?}}}", &solution.solution_nonces[..]);
+
+    //solutions at the target difficulty are returned
+    let miner = CuckooMiner::new(mock_config(&script)).unwrap();
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, difficulty - 1).unwrap();
+    let mut found = None;
+    for _ in 0..100 {
+        found = job_handle.get_solution();
+        if found.is_some() {
+            break;
+        }
+    }
+    job_handle.stop_jobs();
+    assert_eq!(found, Some(solution));
+    assert!(job_handle.get_hashes_since_last_call().unwrap() > 0);
+    thread::sleep(Duration::from_millis(100));
+
+    //but those under it aren't
+    let miner = CuckooMiner::new(mock_config(&script)).unwrap();
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, difficulty).unwrap();
+    for _ in 0..20 {
+        assert!(job_handle.get_solution().is_none());
+    }
+    job_handle.stop_jobs();
+    assert!(job_handle.get_error().is_none());
+    thread::sleep(Duration::from_millis(100));
+}
+
+#[test]
+fn mock_invalid_solutions_dropped() {
+    let _lock = lock_mock();
+    //the mock's solutions aren't real cycles
+    let script = format!("{{\"default_solution\": {:?}}}", &test_solution().solution_nonces[..]);
+    let mut config = mock_config(&script);
+    config.verify_solutions = Some(GraphParams::new(15, 50));
+
+    let miner = CuckooMiner::new(config.clone()).unwrap();
+    let mut solution = CuckooMinerSolution::new();
+    assert!(!miner.mine(&[1u8; 32], &mut solution).unwrap());
+    assert!(miner.mine_all(&[1u8; 32]).unwrap().is_empty());
+    assert_eq!(miner.invalid_solution_count(), 2);
+
+    let miner = CuckooMiner::new(config).unwrap();
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, 0).unwrap();
+    for _ in 0..20 {
+        assert!(job_handle.get_solution().is_none());
+    }
+    job_handle.stop_jobs();
+    assert!(job_handle.get_invalid_solution_count() > 0);
+    thread::sleep(Duration::from_millis(100));
+}
+
+#[test]
+fn mock_slow_stop_doesnt_block() {
+    let _lock = lock_mock();
+    let script = "{\"stop_processing_delay_ms\": 500}";
+    let miner = CuckooMiner::new(mock_config(script)).unwrap();
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, 0).unwrap();
+    thread::sleep(Duration::from_millis(50));
+
+    let start = Instant::now();
+    job_handle.stop_jobs();
+    assert!(start.elapsed() < Duration::from_millis(500));
+
+    //let the job loop finish stopping before another test starts
+    thread::sleep(Duration::from_millis(700));
+    assert!(job_handle.get_error().is_none());
+}
+
+#[test]
+fn mock_large_plugin_caps() {
+    let _lock = lock_mock();
+    let name = format!("mock_{}", "x".repeat(1000));
+    let parameters = (0..100).map(|i| {
+        format!("{{\"name\":\"PARAM_{}\", \"description\":\"Parameter {}\", \
+                 \"default_value\":1, \"min_value\":0, \"max_value\":10}}", i, i)
+    }).collect::<Vec<_>>().join(",");
+    mock_config(&format!("{{\"name\": \"{}\", \"parameters\": [{}]}}", name, parameters));
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(String::from("target/debug")).unwrap();
+    let caps = manager.get_available_plugins("cuckoo_mock").unwrap();
+    assert_eq!(caps.len(), 1);
+    assert_eq!(caps[0].name, name);
+    assert_eq!(caps[0].parameters.len(), 100);
+    assert_eq!(caps[0].parameters[99].name, "PARAM_99");
+
+    //parameter lists which don't parse get the plugin rejected rather than panicking,
+    //and the rest are still loaded
+    mock_config("{\"parameters\": [{\"name\": \"NUM_THREADS\"}]}");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    let report = manager.load_plugin_dir(String::from("target/debug")).unwrap();
+    let rejected = report.rejected.iter().find(|r| r.full_path.contains("cuckoo_mock")).unwrap();
+    match rejected.reason {
+        RejectionReason::BadJson(_) => {},
+        ref r => panic!("Unexpected reason: {:?}", r),
+    }
+    assert!(manager.get_available_plugins("cuckoo_mock").is_err());
+    assert!(manager.get_available_plugins("rust_simple_16").is_ok());
+}
+
+#[test]
+fn mock_custom_proof_size() {
+    let _lock = lock_mock();
+    //nonces 16..28, whose hash has a difficulty of at least 1
+    let nonces = (16..28).collect::<Vec<u32>>();
+    let script = format!("{{\"proof_size\": 12, \"default_solution\": {:?}}}", nonces);
+
+    //sync, both in process and in a worker
+    for use_worker_process in worker_modes() {
+        let mut config = mock_config(&script);
+        config.use_worker_process = use_worker_process;
+        let miner = CuckooMiner::new(config).unwrap();
+        assert_eq!(miner.proof_size(), 12);
+        let mut solution = CuckooMinerSolution::new();
+        assert!(miner.mine(&[1u8; 32], &mut solution).unwrap());
+        assert_eq!(solution.solution_nonces, nonces);
+    }
+
+    //async
+    let miner = CuckooMiner::new(mock_config(&script)).unwrap();
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, 0).unwrap();
+    let mut found = None;
+    for _ in 0..100 {
+        found = job_handle.get_solution();
+        if found.is_some() {
+            break;
+        }
+    }
+    job_handle.stop_jobs();
+    assert_eq!(found.unwrap().solution_nonces, nonces);
+    thread::sleep(Duration::from_millis(100));
+
+    //odd proof sizes can't be cycles
+    match CuckooMiner::new(mock_config("{\"proof_size\": 41}")) {
+        Err(CuckooMinerError::PluginUnsupportedError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r.map(|_| ())),
+    }
+}
+
+#[test]
+fn mock_plugin_metadata() {
+    let _lock = lock_mock();
+    mock_config("{\"metadata\": {\"variation\": \"Tomato\", \"edge_bits\": 27, \"easiness\": 60}}");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(String::from("target/debug")).unwrap();
+    let caps = manager.get_available_plugins("cuckoo_mock").unwrap();
+    assert_eq!(caps[0].variation, CuckooVariation::Tomato);
+    assert_eq!(caps[0].cuckoo_size, 28);
+    assert_eq!(caps[0].edge_bits(), 27);
+    assert_eq!(caps[0].easiness, 60);
+
+    //capabilities round trip through JSON
+    let json = serde_json::to_string(&caps[0]).unwrap();
+    assert!(json.contains("\"variation\":\"tomato\""));
+    let read: CuckooPluginCapabilities = serde_json::from_str(&json).unwrap();
+    assert_eq!(read.variation, CuckooVariation::Tomato);
+    assert_eq!(read.cuckoo_size, 28);
+    assert!(read.capabilities.contains(PluginCapabilities::SYNC | PluginCapabilities::ASYNC_QUEUE));
+
+    //the rust plugin reports its own
+    let caps = manager.get_available_plugins("rust_simple_16").unwrap();
+    assert_eq!(caps[0].variation, CuckooVariation::Simple);
+    assert_eq!(caps[0].cuckoo_size, 16);
+    assert_eq!(caps[0].easiness, 50);
+}
+
+#[test]
+fn mock_refresh_plugin_in_use() {
+    let _lock = lock_mock();
+    let mut config = mock_config("{}");
+    let dir = env::temp_dir().join(format!("cuckoo_mock_refresh_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let lib_path = dir.join(format!("{}cuckoo_mock{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX));
+    fs::copy(&config.plugin_full_path, &lib_path).unwrap();
+    config.plugin_full_path = lib_path.to_str().unwrap().to_owned();
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    let mut miner = CuckooMiner::new(config.clone()).unwrap();
+
+    //querying the plugin while it's in use leaves its state alone
+    miner.set_parameter(String::from("NUM_THREADS"), 4).unwrap();
+    let mut other_manager = CuckooPluginManager::new().unwrap();
+    other_manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(other_manager.get_available_plugins("").unwrap().len(), 1);
+    assert_eq!(miner.get_parameter("NUM_THREADS").unwrap(), 4);
+
+    //swap in a "rebuilt" library while the miner holds the old one
+    thread::sleep(Duration::from_millis(20));
+    let rebuilt = dir.join("rebuilt");
+    fs::copy(&lib_path, &rebuilt).unwrap();
+    OpenOptions::new().append(true).open(&rebuilt).unwrap().write_all(b"rebuilt").unwrap();
+    fs::rename(&rebuilt, &lib_path).unwrap();
+    match manager.refresh().unwrap()[..] {
+        [PluginEvent::ChangeDeferred(ref p)] => assert_eq!(p, &config.plugin_full_path),
+        ref e => panic!("Unexpected events: {:?}", e),
+    }
+    assert!(manager.refresh().unwrap().is_empty());
+
+    //once the miner lets go, it's picked up
+    drop(miner);
+    match manager.refresh().unwrap()[..] {
+        [PluginEvent::Changed(ref c)] => assert_eq!(c.full_path, config.plugin_full_path),
+        ref e => panic!("Unexpected events: {:?}", e),
+    }
+}
+
+#[test]
+fn mock_plugin_cache() {
+    let _lock = lock_mock();
+    let config = mock_config("{\"name\": \"cached\"}");
+    let dir = env::temp_dir().join(format!("cuckoo_mock_cache_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let lib_path = dir.join(format!("{}cuckoo_mock{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX));
+    fs::copy(&config.plugin_full_path, &lib_path).unwrap();
+    let cache_file = dir.join("plugins.json");
+    let cache_file = cache_file.to_str().unwrap();
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.set_cache_file(cache_file);
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].name, "cached");
+
+    //a new manager is served from the cache, without querying the plugin
+    mock_config("{\"name\": \"queried\"}");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.set_cache_file(cache_file);
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].name, "cached");
+
+    //as is a library that's been touched but not changed
+    thread::sleep(Duration::from_millis(20));
+    fs::copy(&config.plugin_full_path, &lib_path).unwrap();
+    assert!(manager.refresh().unwrap().is_empty());
+
+    //unless asked to query every plugin again
+    match manager.force_refresh().unwrap()[..] {
+        [PluginEvent::Changed(ref c)] => assert_eq!(c.name, "queried"),
+        ref e => panic!("Unexpected events: {:?}", e),
+    }
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.set_cache_file(cache_file);
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].name, "queried");
+
+    //a corrupt cache is ignored
+    fs::write(cache_file, "not json").unwrap();
+    mock_config("{\"name\": \"requeried\"}");
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.set_cache_file(cache_file);
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].name, "requeried");
+}
+
+#[test]
+fn mock_benchmark() {
+    let _lock = lock_mock();
+    let config = mock_config(&format!("{{\"default_solution\": {:?}}}", &test_solution().solution_nonces[..]));
+    let dir = env::temp_dir().join(format!("cuckoo_benchmark_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let lib_paths = ["a", "b"].iter()
+        .map(|n| dir.join(format!("{}cuckoo_mock_{}{}", env::consts::DLL_PREFIX, n, env::consts::DLL_SUFFIX)))
+        .collect::<Vec<_>>();
+    for p in &lib_paths {
+        fs::copy(&config.plugin_full_path, p).unwrap();
+    }
+    let slower = lib_paths[0].to_str().unwrap();
+    let faster = lib_paths[1].to_str().unwrap();
+    let benchmark_file = dir.join("benchmarks.json");
+    let benchmark_file = benchmark_file.to_str().unwrap();
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.set_benchmark_file(benchmark_file);
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].full_path, slower);
+
+    //both modes are measured, and benchmarked plugins are ranked first
+    let bench_config = BenchmarkConfig {
+        duration: Duration::from_millis(200),
+        ..BenchmarkConfig::default()
+    };
+    let results = manager.benchmark_plugins("mock_b", &bench_config).unwrap();
+    assert_eq!(results.len(), 1);
+    for m in vec![&results[0].sync, &results[0].async_queue] {
+        let m = m.as_ref().unwrap();
+        assert!(m.graphs > 0);
+        assert!(m.first_solution_ms.is_some());
+    }
+    assert!(results[0].graphs_per_sec() > 0.0);
+    if cfg!(target_os = "linux") {
+        assert!(results[0].peak_rss.unwrap() > 0);
+    }
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].full_path, faster);
+
+    //results are kept between runs
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.set_benchmark_file(benchmark_file);
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    assert_eq!(manager.get_benchmark(faster), Some(&results[0]));
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].full_path, faster);
+
+    //until the plugin is rebuilt
+    thread::sleep(Duration::from_millis(20));
+    OpenOptions::new().append(true).open(faster).unwrap().write_all(b"rebuilt").unwrap();
+    manager.refresh().unwrap();
+    assert!(manager.get_benchmark(faster).is_none());
+    assert_eq!(manager.get_available_plugins("").unwrap()[0].full_path, slower);
+}
+
+#[test]
+fn mock_benchmark_trusted() {
+    let _lock = lock_mock();
+    let config = mock_config(&format!("{{\"default_solution\": {:?}}}", &test_solution().solution_nonces[..]));
+    let dir = env::temp_dir().join(format!("cuckoo_benchmark_trusted_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let lib_path = dir.join(format!("{}cuckoo_mock_t{}", env::consts::DLL_PREFIX, env::consts::DLL_SUFFIX));
+    let lib_path = lib_path.to_str().unwrap();
+    fs::copy(&config.plugin_full_path, lib_path).unwrap();
+    let mut hasher = Sha256::new();
+    hasher.input(&fs::read(lib_path).unwrap());
+    let hash = hasher.result_str();
+
+    let mut manager = CuckooPluginManager::new().unwrap();
+    manager.load_plugin_dir(dir.to_str().unwrap().to_owned()).unwrap();
+    manager.set_trust_policy(Some(TrustPolicy::new().allow_hash(&hash)));
+    for use_worker in worker_modes() {
+        let bench_config = BenchmarkConfig {
+            duration: Duration::from_millis(100),
+            use_worker_process: use_worker,
+            ..BenchmarkConfig::default()
+        };
+        //results are kept against the contents which were checked
+        let results = manager.benchmark_plugins("mock_t", &bench_config).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].hash, hash);
+    }
+
+    //a library changed since it was discovered is refused as it's loaded
+    OpenOptions::new().append(true).open(lib_path).unwrap().write_all(b"changed").unwrap();
+    for use_worker in worker_modes() {
+        let bench_config = BenchmarkConfig {
+            duration: Duration::from_millis(100),
+            use_worker_process: use_worker,
+            ..BenchmarkConfig::default()
+        };
+        assert!(manager.benchmark_plugins("mock_t", &bench_config).unwrap().is_empty());
+    }
+}
@@ -33,7 +33,7 @@ use crypto::sha2::Sha256;
 use blake2::blake2b::Blake2b;
 
 use error::CuckooMinerError;
-use miner::{CuckooMinerConfig, CuckooMinerSolution, CuckooMiner};
+use miner::{CuckooMinerConfig, CuckooMinerSolution, CuckooMiner, GraphParams, verify};
 use manager::{CuckooPluginManager, CuckooPluginCapabilities};
 
 use std::time::{Duration, SystemTime};
@@ -47,6 +47,21 @@ static KNOWN_SOLUTION_16:[u32;42] = [671, 2624, 3044, 4429, 4682, 4734, 6727, 72
 18523, 19892, 20277, 22231, 22964, 22965, 23993, 24624, 26735, 26874, 27312, 27502, 28637, 
 29606, 30616, 30674, 30727, 31162, 31466, 31706];
 
+// A size 16 seed whose graph contains two 42-cycles
+
+static KNOWN_MULTI_SEED_16:[u8;32] = [0x00, 0x00, 0x19, 0x28, 0x00, 0x00, 0x00, 0x00,
+                                      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                                      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                                      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+static KNOWN_MULTI_SOLUTIONS_16:[[u32;42];2] = [[21, 66, 638, 736, 1034, 2198, 2919, 4286, 
+4467, 4505, 5323, 6094, 7360, 7398, 8102, 9108, 9964, 10071, 11300, 11590, 11683, 11698, 
+13281, 13621, 13708, 14695, 16108, 16807, 18937, 19287, 20032, 21129, 23052, 24446, 24738, 
+27702, 28398, 28781, 30129, 30809, 30976, 31687],
+[1133, 4898, 5465, 5883, 6042, 8777, 8781, 8918, 9624, 10000, 10033, 10175, 10231, 10687, 
+11235, 13193, 13531, 13576, 14854, 15195, 15590, 16578, 18007, 18623, 19153, 19403, 21045, 
+21940, 22315, 22825, 23449, 24116, 24216, 24308, 24743, 26875, 26967, 28283, 28537, 28608, 
+32374, 32491]];
+
 static KNOWN_SEED_20:[u8;32] = [0xa7,0x02,0x9c,0xe6,0x70,0xe7,0x81,0xc3,
                                 0xa4,0xe7,0x55,0x68,0x3a,0x3b,0x6f,0xb9,
                                 0xaa,0x94,0x05,0x1b,0x33,0xd6,0x36,0x2a,
@@ -189,25 +204,128 @@ fn mine_until_solution_found(plugin_filter:&str,
 #[test]
 fn test_known_solutions() {
     let mut solution = CuckooMinerSolution::new();
-    solution.set_solution(KNOWN_SOLUTION_16);
+    solution.set_solution(&KNOWN_SOLUTION_16);
     test_for_known_set("cuda_28", &KNOWN_TEST_28, 1);
     panic!("stop");
 
 
 
     /*solution = CuckooMinerSolution::new();
-    solution.set_solution(KNOWN_SOLUTION_20);
+    solution.set_solution(&KNOWN_SOLUTION_20);
     test_for_known_set("20", &KNOWN_SEED_20, solution, 1);*/
 
     /*solution = CuckooMinerSolution::new();
-    solution.set_solution(KNOWN_SOLUTION_25);
+    solution.set_solution(&KNOWN_SOLUTION_25);
     test_for_known_set("25", &KNOWN_SEED_25, solution, 1);*/
 
     /*solution = CuckooMinerSolution::new();
-    solution.set_solution(KNOWN_SOLUTION_20);
+    solution.set_solution(&KNOWN_SOLUTION_20);
     test_for_known_set("cuda_20", &KNOWN_SEED_20, solution, 8);*/
 }
 
+// Tests the pure Rust reference plugin, which should always be
+// available, against the known seed and solution for size 16
+
+#[test]
+fn test_rust_plugin_known_solution() {
+    let solution = test_for_known_set("rust_simple_16", &KNOWN_SEED_16, 1)
+        .expect("No solution found for known seed");
+    let mut expected = CuckooMinerSolution::new();
+    expected.set_solution(&KNOWN_SOLUTION_16);
+    assert_eq!(solution, expected);
+}
+
+// Checks the Rust plugin returns every cycle in a graph containing
+// several, both when called directly and via its queue
+
+#[test]
+fn test_rust_plugin_all_cycles() {
+    let mut plugin_manager = CuckooPluginManager::new().unwrap();
+    plugin_manager.load_plugin_dir(String::from("target/debug")).expect("");
+    let caps = plugin_manager.get_available_plugins("rust_simple_16").unwrap();
+
+    let mut config = CuckooMinerConfig::new();
+    config.plugin_full_path = caps[0].full_path.clone();
+    config.verify_solutions = Some(GraphParams::new(caps[0].edge_bits(), caps[0].easiness));
+    let miner = CuckooMiner::new(config).unwrap();
+    let expected = KNOWN_MULTI_SOLUTIONS_16.iter().map(|s| s.to_vec()).collect::<Vec<_>>();
+
+    let solutions = miner.mine_all(&KNOWN_MULTI_SEED_16).unwrap();
+    assert_eq!(solutions.iter().map(|s| s.solution_nonces.clone()).collect::<Vec<_>>(), expected);
+
+    //mine still returns just the first
+    let mut solution = CuckooMinerSolution::new();
+    assert!(miner.mine(&KNOWN_MULTI_SEED_16, &mut solution).unwrap());
+    assert_eq!(solution.solution_nonces, expected[0]);
+
+    //both are output for the same nonce when mining via the queue
+    let results = miner.mine_batch(&[KNOWN_SEED_16, KNOWN_MULTI_SEED_16]).unwrap();
+    assert_eq!(results[0].solutions.len(), 1);
+    assert_eq!(results[1].solutions.iter().map(|s| s.solution_nonces.clone()).collect::<Vec<_>>(),
+               expected);
+    assert!(results[1].solutions.iter().all(|s| s.get_nonce_as_u64() == 1));
+    assert_eq!(miner.invalid_solution_count(), 0);
+}
+
+// Checks the verifier accepts the known solutions, and rejects them
+// once tampered with
+
+#[test]
+fn verify_known_solutions() {
+    let known = [(&KNOWN_SEED_16, &KNOWN_SOLUTION_16, 15),
+                 (&KNOWN_SEED_20, &KNOWN_SOLUTION_20, 19),
+                 (&KNOWN_SEED_25, &KNOWN_SOLUTION_25, 24),
+                 (&KNOWN_SEED_28, &KNOWN_SOLUTION_28, 27)];
+    for &(seed, nonces, edge_bits) in known.iter() {
+        let mut solution = CuckooMinerSolution::new();
+        solution.set_solution(nonces);
+        verify(seed, edge_bits, 50, &solution).expect("Known solution should verify");
+
+        //wrong graph
+        assert!(verify(&KNOWN_TEST_28, edge_bits, 50, &solution).is_err());
+        assert!(verify(seed, edge_bits - 1, 50, &solution).is_err());
+
+        //out of order
+        let mut swapped = solution.clone();
+        swapped.solution_nonces.swap(0, 1);
+        assert!(verify(seed, edge_bits, 50, &swapped).is_err());
+
+        //a different edge
+        let mut changed = solution.clone();
+        changed.solution_nonces[0] += 1;
+        assert!(verify(seed, edge_bits, 50, &changed).is_err());
+
+        //too few edges
+        let mut short = solution.clone();
+        short.solution_nonces.truncate(40);
+        assert!(verify(seed, edge_bits, 50, &short).is_err());
+    }
+}
+
+// Has the miner verify the Rust plugin's solutions, counting those
+// which don't verify against the configured graph
+
+#[test]
+fn verify_rust_plugin_solutions() {
+    let mut plugin_manager = CuckooPluginManager::new().unwrap();
+    plugin_manager.load_plugin_dir(String::from("target/debug")).expect("");
+    let caps = plugin_manager.get_available_plugins("rust_simple_16").unwrap();
+
+    let mut config = CuckooMinerConfig::new();
+    config.plugin_full_path = caps[0].full_path.clone();
+    config.verify_solutions = Some(GraphParams::new(caps[0].edge_bits(), caps[0].easiness));
+    let miner = CuckooMiner::new(config.clone()).unwrap();
+    let mut solution = CuckooMinerSolution::new();
+    assert!(miner.mine(&KNOWN_SEED_16, &mut solution).unwrap());
+    assert_eq!(miner.invalid_solution_count(), 0);
+
+    config.verify_solutions = Some(GraphParams::new(caps[0].edge_bits() + 1, caps[0].easiness));
+    let miner = CuckooMiner::new(config).unwrap();
+    let mut solution = CuckooMinerSolution::new();
+    assert!(!miner.mine(&KNOWN_SEED_16, &mut solution).unwrap());
+    assert_eq!(miner.invalid_solution_count(), 1);
+}
+
 // Performs basic test mining on plugins, finding a solution
 
 #[test]
@@ -0,0 +1,285 @@
+// Copyright 2017 The Grin Developers
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Tests driving the miner with a fake solver linked straight into the
+//! test, rather than a plugin library.
+
+extern crate miner;
+extern crate error;
+
+use std::cmp;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use error::CuckooMinerError;
+use miner::{CancellationToken, CuckooMiner, CuckooMinerSolution, CuckooSolver, PluginCapabilities};
+
+static PRE_HEADER:&str = "00000000000000118e0fe6bcfaa76c6795592339f27b6d330d8f9c4ac8e86171a66357d1";
+static POST_HEADER:&str = "010a020364";
+
+// Finds a solution for every hash whose first byte is even, taking
+// half a second over hashes starting 0xff. Can also be made to fail
+// pushes to its input queue, to report hash metrics, skipping the
+// first `uncounted` hashes, and to hold back queued solutions until
+// processing is stopped
+
+struct FakeSolver {
+    running: Arc<AtomicBool>,
+    output: Mutex<VecDeque<[u8; 8]>>,
+    held: Mutex<VecDeque<[u8; 8]>>,
+    hashes: AtomicUsize,
+    fail_pushes: bool,
+    hash_metrics: bool,
+    uncounted: AtomicUsize,
+    hold_output: bool,
+}
+
+impl FakeSolver {
+    fn new() -> FakeSolver {
+        FakeSolver {
+            running: Arc::new(AtomicBool::new(false)),
+            output: Mutex::new(VecDeque::new()),
+            held: Mutex::new(VecDeque::new()),
+            hashes: AtomicUsize::new(0),
+            fail_pushes: false,
+            hash_metrics: false,
+            uncounted: AtomicUsize::new(0),
+            hold_output: false,
+        }
+    }
+}
+
+// Nonces 72..114, whose hash has a difficulty of at least 1
+
+fn fake_solution() -> Vec<u32> {
+    (72..114).collect()
+}
+
+impl CuckooSolver for FakeSolver {
+    fn name(&self) -> &str {
+        "fake"
+    }
+
+    fn capabilities(&self) -> PluginCapabilities {
+        let mut caps = PluginCapabilities::SYNC;
+        caps.insert(PluginCapabilities::ASYNC_QUEUE);
+        if self.hash_metrics {
+            caps.insert(PluginCapabilities::HASH_METRICS);
+        }
+        caps
+    }
+
+    fn mine(&self, header: &[u8], solution: &mut Vec<u32>) -> Result<bool, CuckooMinerError> {
+        if header[0] == 0xff {
+            thread::sleep(Duration::from_millis(500));
+        }
+        if header[0] % 2 != 0 {
+            return Ok(false);
+        }
+        *solution = fake_solution();
+        Ok(true)
+    }
+
+    fn start_processing(&self) -> Result<(), CuckooMinerError> {
+        self.running.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn stop_processing(&self) -> Result<(), CuckooMinerError> {
+        self.running.store(false, Ordering::SeqCst);
+        let mut held = self.held.lock().unwrap();
+        self.output.lock().unwrap().extend(held.drain(..));
+        Ok(())
+    }
+
+    fn is_queue_under_limit(&self) -> Result<bool, CuckooMinerError> {
+        Ok(self.running.load(Ordering::SeqCst) && self.output.lock().unwrap().len() < 16)
+    }
+
+    fn push_to_input_queue(&self, hash: &[u8], nonce: &[u8; 8]) -> Result<bool, CuckooMinerError> {
+        if self.fail_pushes {
+            return Err(CuckooMinerError::PluginProcessingError(String::from("Queue failed")));
+        }
+        if hash[0] % 2 == 0 {
+            if self.hold_output {
+                self.held.lock().unwrap().push_back(*nonce);
+            } else {
+                self.output.lock().unwrap().push_back(*nonce);
+            }
+        }
+        self.hashes.fetch_add(1, Ordering::SeqCst);
+        Ok(true)
+    }
+
+    fn read_from_output_queue(&self, solution: &mut Vec<u32>, nonce: &mut [u8; 8])
+        -> Result<bool, CuckooMinerError> {
+        match self.output.lock().unwrap().pop_front() {
+            Some(n) => {
+                *solution = fake_solution();
+                *nonce = n;
+                Ok(true)
+            },
+            None => Ok(false),
+        }
+    }
+
+    fn hashes_since_last_call(&self) -> Result<u32, CuckooMinerError> {
+        let hashes = self.hashes.swap(0, Ordering::SeqCst);
+        let skipped = cmp::min(hashes, self.uncounted.load(Ordering::SeqCst));
+        self.uncounted.fetch_sub(skipped, Ordering::SeqCst);
+        Ok((hashes - skipped) as u32)
+    }
+}
+
+#[test]
+fn fake_solver_sync() {
+    let miner = CuckooMiner::from_solver(FakeSolver::new());
+    let mut solution = CuckooMinerSolution::new();
+    assert!(miner.mine(&[2u8; 32], &mut solution).unwrap());
+    assert_eq!(solution.solution_nonces, fake_solution());
+    assert!(!miner.mine(&[1u8; 32], &mut solution).unwrap());
+
+    //calls the solver doesn't implement are reported as unsupported
+    match miner.get_parameter("NUM_THREADS") {
+        Err(CuckooMinerError::PluginUnsupportedError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}
+
+#[test]
+fn fake_solver_async() {
+    let miner = CuckooMiner::from_solver(FakeSolver::new());
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, 0).unwrap();
+    let mut found = None;
+    for _ in 0..100 {
+        found = job_handle.get_solution();
+        if found.is_some() {
+            break;
+        }
+    }
+    job_handle.stop_jobs();
+    assert_eq!(found.unwrap().solution_nonces, fake_solution());
+    assert!(job_handle.get_error().is_none());
+}
+
+#[test]
+fn fake_solver_async_error() {
+    //the solver is stopped even though the job loop fails
+    let mut solver = FakeSolver::new();
+    solver.fail_pushes = true;
+    let running = solver.running.clone();
+    let job_handle = CuckooMiner::from_solver(solver).notify(1, PRE_HEADER, POST_HEADER, 0).unwrap();
+    let start = Instant::now();
+    let mut error = None;
+    while error.is_none() && start.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(10));
+        error = job_handle.get_error();
+    }
+    match error {
+        Some(CuckooMinerError::PluginProcessingError(_)) => {},
+        e => panic!("Unexpected error: {:?}", e),
+    }
+    assert!(!running.load(Ordering::SeqCst));
+}
+
+#[test]
+fn fake_solver_async_after_deadline() {
+    //async processing doesn't start until an abandoned call returns
+    let miner = CuckooMiner::from_solver(FakeSolver::new());
+    let mut solution = CuckooMinerSolution::new();
+    let start = Instant::now();
+    match miner.mine_with_deadline(&[0xffu8; 32], &mut solution, start + Duration::from_millis(50)) {
+        Err(CuckooMinerError::MiningCancelledError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    let job_handle = miner.notify(1, PRE_HEADER, POST_HEADER, 0).unwrap();
+    assert!(start.elapsed() >= Duration::from_millis(500));
+    job_handle.stop_jobs();
+}
+
+#[test]
+fn fake_solver_batch() {
+    //without hash metrics, the batch is mined one header at a time
+    let miner = CuckooMiner::from_solver(FakeSolver::new());
+    let headers = vec![vec![1u8; 32], vec![2u8; 32], vec![3u8; 32]];
+    let results = miner.mine_batch(&headers).unwrap();
+    assert_eq!(results.iter().map(|r| r.found()).collect::<Vec<_>>(), vec![false, true, false]);
+    assert_eq!(results[1].solutions[0].solution_nonces, fake_solution());
+    assert_eq!(results[1].solutions[0].get_nonce_as_u64(), 1);
+}
+
+#[test]
+fn fake_solver_batch_queued() {
+    //solutions still held by the solver when it's stopped aren't lost
+    let mut solver = FakeSolver::new();
+    solver.hash_metrics = true;
+    solver.hold_output = true;
+    let miner = CuckooMiner::from_solver(solver);
+    let headers = vec![vec![1u8; 32], vec![2u8; 32], vec![4u8; 32]];
+    let results = miner.mine_batch(&headers).unwrap();
+    assert_eq!(results.iter().map(|r| r.found()).collect::<Vec<_>>(), vec![false, true, true]);
+    assert_eq!(results[2].solutions[0].get_nonce_as_u64(), 2);
+
+    //a solver which never counts a header is given up on
+    let mut solver = FakeSolver::new();
+    solver.hash_metrics = true;
+    solver.uncounted = AtomicUsize::new(1);
+    let running = solver.running.clone();
+    let mut miner = CuckooMiner::from_solver(solver);
+    miner.config.batch_stall_timeout = Duration::from_millis(100);
+    match miner.mine_batch(&headers) {
+        Err(CuckooMinerError::PluginProcessingError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r.map(|r| r.len())),
+    }
+    assert!(!running.load(Ordering::SeqCst));
+}
+
+#[test]
+fn fake_solver_deadline() {
+    let miner = CuckooMiner::from_solver(FakeSolver::new());
+    let mut solution = CuckooMinerSolution::new();
+
+    //calls which finish in time aren't affected
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert!(miner.mine_with_deadline(&[2u8; 32], &mut solution, deadline).unwrap());
+    assert_eq!(solution.solution_nonces, fake_solution());
+
+    //the solver can't be interrupted, so is abandoned
+    let start = Instant::now();
+    match miner.mine_with_deadline(&[0xffu8; 32], &mut solution, start + Duration::from_millis(50)) {
+        Err(CuckooMinerError::MiningCancelledError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+    assert!(start.elapsed() < Duration::from_millis(400));
+
+    //and the next call waits for it to finish
+    assert!(miner.mine(&[2u8; 32], &mut solution).unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(500));
+
+    //tokens can be cancelled from another thread
+    let token = CancellationToken::new();
+    let cancel_token = token.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        cancel_token.cancel();
+    });
+    match miner.mine_cancellable(&[0xffu8; 32], &mut solution, &token) {
+        Err(CuckooMinerError::MiningCancelledError(_)) => {},
+        r => panic!("Unexpected result: {:?}", r),
+    }
+}